@@ -0,0 +1,499 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fs,
+    io::{self, BufReader, Write},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use rodio::{
+    buffer::SamplesBuffer,
+    cpal::{self, traits::HostTrait},
+    source::{Buffered, ChannelVolume, Source, Zero},
+    Decoder, DeviceTrait, OutputStream, OutputStreamHandle,
+};
+
+use crate::{
+    config::{Config, Sink},
+    dirs::config_dir,
+    workout::{BeepLevel, Sound},
+};
+
+type BoxedSource = Box<dyn Source<Item = f32> + Send>;
+
+// decoded all the way up front, the audio thread only copies samples then,
+// decoding there too starves ALSA and clips the start of the sound
+fn decoded(source: BoxedSource) -> Buffered<BoxedSource> {
+    let buffered = source.buffered();
+    buffered.clone().for_each(drop);
+    buffered
+}
+
+struct AudioOutput {
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    // the sounds one after the other, at the volume
+    sink: rodio::Sink,
+    // when the last sound queued should be done playing by
+    last: Option<Instant>,
+    // dropped to stop the metronome that's clicking
+    clicking: Option<rodio::Sink>,
+}
+impl AudioOutput {
+    fn open(device: Option<&str>, volume: f32) -> Result<Self> {
+        let (stream, stream_handle) = match device {
+            Some(name) => {
+                let device = cpal::default_host()
+                    .output_devices()?
+                    .find(|d| d.name().is_ok_and(|n| n == name))
+                    .ok_or_else(|| anyhow::Error::msg(format!("No audio device called {name}")))?;
+                OutputStream::try_from_device(&device)?
+            }
+            None => OutputStream::try_default()?,
+        };
+        // silence for good, so the stream never runs dry in the long gaps
+        // between cues (ALSA underruns and clips the next one otherwise)
+        stream_handle.play_raw(Zero::<f32>::new(2, 48000))?;
+        let sink = rodio::Sink::try_new(&stream_handle)?;
+        sink.set_volume(volume);
+        Ok(Self {
+            _stream: stream,
+            handle: stream_handle,
+            sink,
+            last: None,
+            clicking: None,
+        })
+    }
+
+    // the device stopped taking samples, e.g. the Bluetooth speaker died
+    fn lost(&self) -> bool {
+        let Some(by) = self.last else {
+            return false;
+        };
+        Instant::now() > by + Duration::from_secs(1) && !self.sink.empty()
+    }
+
+    // 0 while muted
+    fn set_volume(&self, volume: f32) {
+        self.sink.set_volume(volume);
+        if let Some(clicking) = &self.clicking {
+            clicking.set_volume(volume);
+        }
+    }
+
+    fn play(&mut self, source: BoxedSource) {
+        let length = source.total_duration().unwrap_or(Duration::from_secs(10));
+        // sounds queue up behind each other
+        let start = match self.last {
+            Some(by) => Instant::now().max(by),
+            None => Instant::now(),
+        };
+        self.sink.append(source);
+        self.last = Some(start + length);
+    }
+
+    // mixed in next to the sounds, the beeps don't wait for it
+    fn click(&mut self, clicks: BoxedSource) {
+        self.stop_clicking();
+        if let Ok(sink) = rodio::Sink::try_new(&self.handle) {
+            sink.set_volume(self.sink.volume());
+            sink.append(clicks);
+            self.clicking = Some(sink);
+        }
+    }
+
+    fn stop_clicking(&mut self) {
+        self.clicking = None;
+    }
+}
+
+/// How long a GPIO pin stays on for a cue.
+const FLASH: Duration = Duration::from_millis(300);
+
+/// Somewhere the cues go, stacked in the config's `sinks`.
+pub trait AnnouncementSink {
+    fn announce(&self, sound: &Sound);
+}
+impl<F: Fn(&Sound)> AnnouncementSink for F {
+    fn announce(&self, sound: &Sound) {
+        self(sound);
+    }
+}
+
+/// Reads `say` cues out loud.
+pub struct Speech;
+impl AnnouncementSink for Speech {
+    fn announce(&self, sound: &Sound) {
+        match sound {
+            Sound::Speech(text) => speak(text),
+            Sound::Panned(sound, _) => self.announce(sound),
+            _ => {}
+        }
+    }
+}
+
+/// Rings the terminal bell for every sound.
+pub struct Bell;
+impl AnnouncementSink for Bell {
+    fn announce(&self, _: &Sound) {
+        print!("\x07");
+        let _ = io::stdout().flush();
+    }
+}
+
+/// Switches a GPIO pin on for a moment for every sound, e.g. a relay with a
+/// light on it.
+pub struct Gpio {
+    value: String,
+}
+impl Gpio {
+    /// Exports the pin through sysfs if it isn't yet.
+    pub fn open(pin: u32) -> Result<Self> {
+        let dir = format!("/sys/class/gpio/gpio{pin}");
+        let error =
+            |e: io::Error| anyhow::Error::msg(format!("Couldn't set up GPIO pin {pin}: {e}"));
+        if !Path::new(&dir).exists() {
+            fs::write("/sys/class/gpio/export", pin.to_string()).map_err(error)?;
+        }
+        fs::write(format!("{dir}/direction"), "out").map_err(error)?;
+        Ok(Self {
+            value: format!("{dir}/value"),
+        })
+    }
+}
+impl AnnouncementSink for Gpio {
+    fn announce(&self, _: &Sound) {
+        let value = self.value.clone();
+        thread::spawn(move || {
+            let _ = fs::write(&value, "1");
+            thread::sleep(FLASH);
+            let _ = fs::write(&value, "0");
+        });
+    }
+}
+
+/// POSTs the name of every sound, to a phone through ntfy, or a home
+/// automation.
+pub struct Webhook {
+    pub url: String,
+}
+impl AnnouncementSink for Webhook {
+    fn announce(&self, sound: &Sound) {
+        let data = sound.name();
+        spawn("curl", &["-fsS", "-m", "5", "--data-raw", &data, &self.url]);
+    }
+}
+
+/// Publishes the name of every sound over MQTT, to flash lights.
+pub struct Mqtt {
+    pub host: String,
+    pub topic: String,
+}
+impl AnnouncementSink for Mqtt {
+    fn announce(&self, sound: &Sound) {
+        let name = sound.name();
+        spawn(
+            "mosquitto_pub",
+            &["-h", &self.host, "-t", &self.topic, "-m", &name],
+        );
+    }
+}
+
+// in the background, once told when it's not there
+fn spawn(program: &str, args: &[&str]) {
+    use std::process::{Command, Stdio};
+    use std::sync::Mutex;
+
+    static MISSING: Mutex<Vec<String>> = Mutex::new(vec![]);
+    let spawned = Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match spawned {
+        Ok(child) => reap(child),
+        Err(_) => {
+            let mut missing = MISSING.lock().unwrap_or_else(|e| e.into_inner());
+            if !missing.iter().any(|p| p == program) {
+                eprintln!("Couldn't run {program} for the cues, is it installed?");
+                missing.push(program.to_owned());
+            }
+        }
+    }
+}
+
+// waited for once it's done, a kiosk running for months would pile up
+// zombies otherwise
+pub(crate) fn reap(mut child: std::process::Child) {
+    thread::spawn(move || child.wait());
+}
+
+// the config's sinks besides the audio device, and whether they're heard,
+// so muting leaves them out
+fn sinks(config: &Config, sinks: &[Sink]) -> Result<Vec<(Box<dyn AnnouncementSink>, bool)>> {
+    let mut out: Vec<(Box<dyn AnnouncementSink>, bool)> = vec![];
+    for sink in sinks {
+        out.push(match sink {
+            Sink::Audio => continue,
+            // it's sound too
+            Sink::Speech if config.no_audio => continue,
+            Sink::Speech => (Box::new(Speech), true),
+            Sink::Bell => (Box::new(Bell), true),
+            Sink::Gpio { pin } => (Box::new(Gpio::open(*pin)?), false),
+            Sink::Webhook { url } => (Box::new(Webhook { url: url.clone() }), false),
+            Sink::Mqtt { host, topic } => (
+                Box::new(Mqtt {
+                    host: host.clone(),
+                    topic: topic.clone(),
+                }),
+                false,
+            ),
+        });
+    }
+    Ok(out)
+}
+
+/// Length, fade in and fade out of the beeps in seconds, unless the config
+/// says.
+const BEEP_ENVELOPE: [f64; 3] = [0.5, 0.1, 0.5];
+const RELAX_ENVELOPE: [f64; 3] = [1.5, 0.6, 1.2];
+
+/// Calls `f` with what plays a [`Sound`] on the config's device, its beeps
+/// built the way the config says, and hands it to the config's other
+/// `sinks`.
+pub fn with_audio<T>(config: &Config, f: impl FnOnce(&dyn Fn(&Sound)) -> Result<T>) -> Result<T> {
+    let stacked = match config.sinks.is_empty() {
+        true => vec![Sink::Audio, Sink::Speech],
+        false => config.sinks.clone(),
+    };
+    let others = sinks(config, &stacked)?;
+    let muted = Cell::new(false);
+    let announce = |sound: &Sound| {
+        match sound {
+            Sound::Mute(mute) => return muted.set(*mute),
+            // it's only for the audio device
+            Sound::Metronome(_) | Sound::MetronomeStop | Sound::Preload(_) => return,
+            _ => {}
+        }
+        for (sink, heard) in &others {
+            if !(*heard && muted.get()) {
+                sink.announce(sound);
+            }
+        }
+    };
+    if config.no_audio || !stacked.contains(&Sink::Audio) {
+        return f(&announce);
+    }
+    let volume = || if muted.get() { 0. } else { config.volume };
+
+    // `None` once there's no device left, the terminal bell rings instead
+    let output = RefCell::new(Some(AudioOutput::open(config.device.as_deref(), volume())?));
+    let fallen_back = Cell::new(false);
+    let play = |source: BoxedSource| {
+        let mut output = output.borrow_mut();
+        if output.as_ref().is_some_and(AudioOutput::lost) {
+            // the default device first, unless that's the one that was lost
+            *output = if fallen_back.replace(true) || config.device.is_none() {
+                None
+            } else {
+                AudioOutput::open(None, volume()).ok()
+            };
+            match *output {
+                Some(_) => println!("\nLost the audio device, switched to the default one"),
+                None => println!("\nLost the audio device, ringing the terminal bell instead"),
+            }
+        }
+        match output.as_mut() {
+            Some(output) => output.play(source),
+            None if muted.get() => {}
+            None => {
+                print!("\x07");
+                let _ = io::stdout().flush();
+            }
+        }
+    };
+
+    let open = |path: &Path| -> Result<BoxedSource> {
+        let decoded = Decoder::new(BufReader::new(fs::File::open(path)?))?;
+        Ok(Box::new(decoded.convert_samples()))
+    };
+    let config_dir = config_dir()?;
+    // the config's files, read once, relative to the config
+    let load = |file: &Option<String>| -> Result<Option<Buffered<BoxedSource>>> {
+        let Some(file) = file else {
+            return Ok(None);
+        };
+        let path = config_dir.join(file);
+        let source = open(&path)
+            .map_err(|e| anyhow::Error::msg(format!("Couldn't play {}: {e}", path.display())))?;
+        Ok(Some(decoded(source)))
+    };
+
+    // a sine wave faded in and out, linearly
+    let beep_sample = |level: BeepLevel, envelope: [f64; 3]| -> Result<Buffered<BoxedSource>> {
+        const RATE: u32 = 48000;
+        let beep = config.beeps.level(level);
+        if let Some(file) = load(&beep.file)? {
+            return Ok(file);
+        }
+        let frequency = beep.frequency.unwrap_or(level.get_frequency()) as f64;
+        let [length, fade_in, fade_out] = envelope;
+        let length = beep.length.unwrap_or(length);
+        let samples = (0..(length.max(0.) * RATE as f64) as usize)
+            .map(|i| {
+                let t = i as f64 / RATE as f64;
+                let gain = (t / fade_in).min((length - t) / fade_out).clamp(0., 1.);
+                ((t * frequency * std::f64::consts::TAU).sin() * gain) as f32
+            })
+            .collect::<Vec<_>>();
+        let source: BoxedSource = Box::new(SamplesBuffer::new(1, RATE, samples));
+        Ok(decoded(source))
+    };
+
+    // a tick every second, louder where a phase starts and loudest where
+    // the rep does, the whole rep over and over
+    let clicks = |phases: &[Duration]| -> BoxedSource {
+        const RATE: u32 = 48000;
+        const LENGTH: usize = RATE as usize / 50;
+        let rep: Duration = phases.iter().sum();
+        let mut samples = vec![0.; (rep.as_secs_f64() * RATE as f64) as usize];
+        let mut click = |at: Duration, frequency: f64, gain: f32| {
+            let start = (at.as_secs_f64() * RATE as f64) as usize;
+            for (i, sample) in samples.iter_mut().skip(start).take(LENGTH).enumerate() {
+                let t = i as f64 / RATE as f64;
+                let decay = 1. - i as f32 / LENGTH as f32;
+                *sample += (t * frequency * std::f64::consts::TAU).sin() as f32 * decay * gain;
+            }
+        };
+        let mut at = Duration::ZERO;
+        for (i, phase) in phases.iter().enumerate() {
+            match i {
+                0 => click(at, 2000., 0.4),
+                _ => click(at, 1500., 0.3),
+            }
+            for second in 1..phase.as_secs() {
+                click(at + Duration::from_secs(second), 1000., 0.15);
+            }
+            at += *phase;
+        }
+        Box::new(SamplesBuffer::new(1, RATE, samples).repeat_infinite())
+    };
+
+    let presample = |envelope: [f64; 3]| -> Result<_> {
+        Ok([
+            beep_sample(BeepLevel::High, envelope)?,
+            beep_sample(BeepLevel::Mid, envelope)?,
+            beep_sample(BeepLevel::Low, envelope)?,
+        ])
+    };
+    // cue files, decoded the first time they play (or a step ahead) and
+    // from memory after
+    let files: RefCell<HashMap<String, Buffered<BoxedSource>>> = RefCell::default();
+    let file = |path: &str| -> Result<BoxedSource> {
+        let mut files = files.borrow_mut();
+        if let Some(buffered) = files.get(path) {
+            return Ok(Box::new(buffered.clone()));
+        }
+        let buffered = decoded(open(Path::new(path))?);
+        files.insert(path.to_owned(), buffered.clone());
+        Ok(Box::new(buffered))
+    };
+
+    let presampled = presample(config.envelope.or(BEEP_ENVELOPE))?;
+    let relaxed = presample(config.relax_envelope.or(RELAX_ENVELOPE))?;
+    let start = load(&config.beeps.start)?;
+    let complete = load(&config.beeps.complete)?;
+    // three beeps unless there's a file for it
+    let jingle = |file: &Option<Buffered<BoxedSource>>, levels: [BeepLevel; 3]| match file {
+        Some(file) => play(Box::new(file.clone())),
+        None => {
+            for level in levels {
+                play(Box::new(presampled[level as usize].clone()));
+            }
+        }
+    };
+
+    let audio = |sound: &Sound| {
+        let (sound, volumes) = match sound {
+            Sound::Panned(sound, pan) => (&**sound, pan.volumes()),
+            sound => (sound, None),
+        };
+        let source: BoxedSource = match sound {
+            Sound::Beep(level) => Box::new(presampled[*level as usize].clone()),
+            Sound::Relax(level) => Box::new(relaxed[*level as usize].clone()),
+            Sound::File(path) => match file(path) {
+                Ok(source) => source,
+                Err(e) => return eprintln!("Couldn't play {path}: {e}"),
+            },
+            Sound::Start => {
+                return jingle(&start, [BeepLevel::High, BeepLevel::Mid, BeepLevel::Low])
+            }
+            Sound::Complete => {
+                return jingle(&complete, [BeepLevel::Low, BeepLevel::Mid, BeepLevel::High])
+            }
+            // the session only pans once
+            Sound::Panned(..) => return,
+            // up to the speech sink
+            Sound::Speech(_) => return,
+            Sound::Metronome(phases) => {
+                if let Some(output) = output.borrow_mut().as_mut() {
+                    output.click(clicks(phases));
+                }
+                return;
+            }
+            Sound::MetronomeStop => {
+                if let Some(output) = output.borrow_mut().as_mut() {
+                    output.stop_clicking();
+                }
+                return;
+            }
+            // `announce` keeps track of it
+            Sound::Mute(_) => return,
+            Sound::Preload(path) => {
+                if let Err(e) = file(path) {
+                    eprintln!("Couldn't play {path}: {e}");
+                }
+                return;
+            }
+        };
+        // mixed down and played on the given side only
+        play(match volumes {
+            Some(volumes) => Box::new(ChannelVolume::new(source, volumes.to_vec())),
+            None => source,
+        })
+    };
+    f(&|sound| {
+        audio(sound);
+        announce(sound);
+        if let (Sound::Mute(_), Some(output)) = (sound, &*output.borrow()) {
+            output.set_volume(volume());
+        }
+    })
+}
+
+// in the background, through whatever the system reads text out loud with
+fn speak(text: &str) {
+    use std::process::{Command, Stdio};
+    use std::sync::Once;
+
+    static MISSING: Once = Once::new();
+    let commands: &[&str] = if cfg!(target_os = "macos") {
+        &["say"]
+    } else {
+        &["espeak-ng", "espeak"]
+    };
+    for command in commands {
+        let spawned = Command::new(command)
+            .arg(text)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Ok(child) = spawned {
+            return reap(child);
+        }
+    }
+    MISSING.call_once(|| eprintln!("Couldn't read out loud, install espeak-ng"));
+}
@@ -0,0 +1,116 @@
+use std::{
+    fs::{self, File},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use crate::{
+    config::Config,
+    dirs::{config_dir, data_dir, state_dir},
+};
+
+// the archive has a directory for each place things are kept:
+// config/, data/ (history and the default workouts directory), state/
+// and workouts/ if they're kept somewhere else
+
+/// Writes everything into a `.tar.zst` archive. Returns the number of files.
+pub fn backup(out: &Path, config: &Config) -> Result<usize> {
+    let mut dirs = vec![
+        ("config", config_dir()?),
+        ("data", data_dir()?),
+        ("state", state_dir()?),
+    ];
+    if let Some(workouts) = &config.workouts {
+        if !workouts.starts_with(data_dir()?) {
+            dirs.push(("workouts", workouts.clone()));
+        }
+    }
+
+    let encoder = zstd::Encoder::new(File::create(out)?, 0)?;
+    let mut archive = tar::Builder::new(encoder);
+    let mut files = 0;
+    for (name, dir) in dirs {
+        if !dir.is_dir() {
+            continue;
+        }
+        archive.append_dir_all(name, &dir)?;
+        files += count_files(&dir)?;
+    }
+    archive.into_inner()?.finish()?;
+    Ok(files)
+}
+
+fn count_files(dir: &Path) -> Result<usize> {
+    let mut count = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        count += if path.is_dir() {
+            count_files(&path)?
+        } else {
+            1
+        };
+    }
+    Ok(count)
+}
+
+/// Puts back what [`backup`] saved. Files that are already there are left
+/// alone unless `force`. Returns the number of files restored and skipped.
+pub fn restore(archive: &Path, force: bool) -> Result<(usize, usize)> {
+    let decoder = zstd::Decoder::new(File::open(archive)?)?;
+    let mut archive = tar::Archive::new(decoder);
+
+    // config/ comes first, so it's restored by the time workouts/ is reached
+    let mut workouts: Option<PathBuf> = None;
+    let (mut restored, mut skipped) = (0, 0);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type().is_dir() {
+            continue;
+        }
+
+        let path = entry.path()?.into_owned();
+        let mut components = path.components();
+        let dir = match components.next() {
+            Some(Component::Normal(d)) if d == "config" => config_dir()?,
+            Some(Component::Normal(d)) if d == "data" => data_dir()?,
+            Some(Component::Normal(d)) if d == "state" => state_dir()?,
+            Some(Component::Normal(d)) if d == "workouts" => match &workouts {
+                Some(w) => w.clone(),
+                None => {
+                    let w = match Config::load()?.workouts {
+                        Some(w) => w,
+                        None => data_dir()?.join("workouts"),
+                    };
+                    workouts = Some(w.clone());
+                    w
+                }
+            },
+            _ => {
+                return Err(anyhow::Error::msg(format!(
+                    "Unexpected {} in backup",
+                    path.display()
+                )))
+            }
+        };
+        let rest = components.as_path();
+        if !rest.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(anyhow::Error::msg(format!(
+                "Unexpected {} in backup",
+                path.display()
+            )));
+        }
+
+        let to = dir.join(rest);
+        if to.exists() && !force {
+            skipped += 1;
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&to)?;
+        restored += 1;
+    }
+    Ok((restored, skipped))
+}
@@ -0,0 +1,28 @@
+use std::fs;
+
+use anyhow::Result;
+
+// ready-made sessions, so there's something to run before writing any
+const WORKOUTS: &[(&str, &str)] = &[
+    ("beginner", include_str!("builtin/beginner.wk")),
+    ("7min", include_str!("builtin/7min.wk")),
+    ("tabata-core", include_str!("builtin/tabata-core.wk")),
+];
+
+/// The source of a `builtin:NAME` workout, or of the file.
+pub fn read(file: &str) -> Result<String> {
+    let Some(name) = file.strip_prefix("builtin:") else {
+        return Ok(fs::read_to_string(file)?);
+    };
+    match WORKOUTS.iter().find(|(n, _)| *n == name) {
+        Some((_, source)) => Ok(source.to_string()),
+        None => Err(anyhow::Error::msg(format!(
+            "No built in workout called {name}, there's {}",
+            names().join(", ")
+        ))),
+    }
+}
+
+pub fn names() -> Vec<&'static str> {
+    WORKOUTS.iter().map(|(name, _)| *name).collect()
+}
@@ -0,0 +1,61 @@
+use std::{fmt::Write, time::Duration};
+
+use crate::{record::SessionRecord, session::StepKind};
+
+// a caption for every step, from when it started to when it ended, e.g.
+// `Set 2 — Push-ups x12 — 00:12:31` with the time into the session, so a
+// recording started with the session lines up with it
+
+fn clock(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn timestamp(d: Duration, separator: char) -> String {
+    format!("{}{separator}{:03}", clock(d), d.subsec_millis())
+}
+
+fn text(kind: &StepKind) -> String {
+    match kind {
+        StepKind::Countdown => "Get ready".to_owned(),
+        StepKind::Excercise { name, amount, .. } => format!("{name} {amount}"),
+        StepKind::Rest { next: Some(next) } => format!("Rest, next: {next}"),
+        StepKind::Rest { next: None } | StepKind::SetRest => "Rest".to_owned(),
+        StepKind::Prep { name } => format!("Prep for {name}"),
+        StepKind::Optional { name } => format!("{name}?"),
+        StepKind::Acknowledge { name } => format!("Done with {name}?"),
+    }
+}
+
+/// SubRip (`.srt`), or WebVTT if `vtt`.
+pub fn captions(record: &SessionRecord, vtt: bool) -> String {
+    let separator = if vtt { '.' } else { ',' };
+    let mut out = String::new();
+    if vtt {
+        let _ = writeln!(out, "WEBVTT\n");
+    }
+    let mut number = 0;
+    for step in &record.steps {
+        if step.duration.is_zero() {
+            continue;
+        }
+        number += 1;
+        if !vtt {
+            let _ = writeln!(out, "{number}");
+        }
+        let _ = writeln!(
+            out,
+            "{} --> {}",
+            timestamp(step.started, separator),
+            timestamp(step.started + step.duration, separator)
+        );
+        let _ = writeln!(
+            out,
+            "Set {} — {} — {}\n",
+            step.set + 1,
+            text(&step.kind),
+            clock(step.started)
+        );
+    }
+    out
+}
@@ -0,0 +1,379 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use workout::{
+    history::Backend,
+    workout::{parse_duration, FileFormat},
+};
+
+/// Where to start: the set, the round of it and the excercise, from 0, and
+/// how far into it.
+pub type From = ((u16, u16, u16), Option<Duration>);
+
+/// Runs workouts written down as text, with a countdown and beeps.
+#[derive(Parser)]
+#[command(name = "workout", version)]
+pub struct Cli {
+    /// Whose history, settings and resume state, instead of `WORKOUT_USER`
+    #[arg(long, global = true)]
+    pub user: Option<String>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a workout (`workout FILE` is short for it)
+    Run {
+        file: String,
+        #[command(flatten)]
+        options: RunArgs,
+    },
+    /// Print the sets of a workout without running it
+    Show {
+        file: String,
+        /// Turn on `If flag(NAME)` blocks
+        #[arg(long = "flag", value_name = "NAME")]
+        flags: Vec<String>,
+        /// Use a profile of the config
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+        /// Read it as text, markdown, toml, json or yaml, whatever its
+        /// extension
+        #[arg(long)]
+        format: Option<FileFormat>,
+    },
+    /// Load a workout (every day of a program) and warn about what's
+    /// probably a mistake in it, and muscle groups over their `work_caps`
+    Validate {
+        file: String,
+        /// Turn on `If flag(NAME)` blocks
+        #[arg(long = "flag", value_name = "NAME")]
+        flags: Vec<String>,
+        /// Use a profile of the config
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+        /// Read it as text, markdown, toml, json or yaml, whatever its
+        /// extension
+        #[arg(long)]
+        format: Option<FileFormat>,
+    },
+    /// The workouts in the workouts directory and the built in ones
+    List,
+    /// Run whichever day of the program is due
+    Today {
+        program: String,
+        #[command(flatten)]
+        options: RunArgs,
+    },
+    /// A gentle stretching session for rest days
+    Recovery {
+        /// How long, like 20m or 20:00
+        #[arg(long, default_value = "20m", value_parser = parse_minutes)]
+        duration: Duration,
+        #[command(flatten)]
+        options: RunArgs,
+    },
+    /// Announced run/walk intervals, like "4x(03:00 run / 01:30 walk)"
+    RunIntervals {
+        spec: String,
+        /// Per kilometer (per mile in imperial units), MM:SS
+        #[arg(long, value_parser = parse_pace)]
+        pace: Option<Duration>,
+        /// The heart rate zone of the hard parts, LOW-HIGH
+        #[arg(long = "hr", value_name = "LOW-HIGH", value_parser = parse_heart_rate)]
+        heart_rate: Option<(u16, u16)>,
+        #[command(flatten)]
+        options: RunArgs,
+    },
+    /// Charts of the last weeks and rep trends from the history
+    Stats {
+        /// Only the sessions of this workout
+        workout: Option<String>,
+        /// An SVG of every excercise's weekly volume instead
+        #[arg(long, value_name = "OUT.svg")]
+        chart: Option<String>,
+        /// A calendar of the training days of the last year instead
+        #[arg(long)]
+        heatmap: bool,
+        /// Every session in InfluxDB line protocol instead, to backfill a
+        /// database
+        #[arg(long)]
+        influx: bool,
+        /// The numbers it prints as JSON instead, for other programs
+        #[arg(long)]
+        json: bool,
+        /// Only the sessions tagged with this
+        #[arg(long = "tag", value_name = "NAME")]
+        tags: Vec<String>,
+        /// Leave out the sessions tagged with this
+        #[arg(long = "without-tag", value_name = "NAME")]
+        without_tags: Vec<String>,
+    },
+    /// A client's --results next to what the workout prescribes
+    Review {
+        file: String,
+        #[arg(value_name = "RESULTS.json")]
+        results: String,
+    },
+    /// A printable one-page card (print to PDF from the browser)
+    Card {
+        file: String,
+        #[arg(value_name = "OUT.html")]
+        out: Option<String>,
+    },
+    /// A self-contained page that runs the workout timer in a browser
+    ExportHtml {
+        file: String,
+        #[arg(value_name = "OUT.html")]
+        out: Option<String>,
+    },
+    /// Convert between formats, picked by OUT's extension (.toml, .json,
+    /// .yaml or text)
+    Convert { file: String, out: String },
+    /// Turn an interval timer app's exported preset (Seconds Pro,
+    /// IntervalTimer JSON) into a workout, in the format of OUT's extension
+    Import { file: String, out: String },
+    /// Which format version the files are in
+    Fmt {
+        /// Rewrite older ones to the current format
+        #[arg(long)]
+        upgrade: bool,
+        /// Put a `Set rest` into the sets done more than once without one
+        #[arg(long)]
+        fix: bool,
+        /// The rest --fix puts in, the config's `set_rest` (or 01:00) if not
+        /// given
+        #[arg(long, value_name = "MM:SS", value_parser = parse_duration, requires = "fix")]
+        set_rest: Option<Duration>,
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Run a saved session (or the last one of a .jsonl history) again, every
+    /// step taking as long as it did then
+    Replay {
+        log: String,
+        /// Use a profile of the config
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+    /// Play every cue one after the other, saying which it is, to check the
+    /// speakers and volume before a session
+    TestAudio {
+        /// Its sound files and `say` lines too
+        file: Option<String>,
+        /// Use a profile of the config
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+    /// Move the history into another backend, the old one is kept
+    Migrate {
+        /// jsonl, sqlite or age
+        #[arg(long)]
+        from: Backend,
+        /// jsonl, sqlite or age
+        #[arg(long)]
+        to: Backend,
+    },
+    /// Archive the config, history, resume state and workouts
+    Backup {
+        #[arg(value_name = "OUT.tar.zst")]
+        out: String,
+    },
+    /// Put a backup back (on a new machine)
+    Restore {
+        #[arg(value_name = "BACKUP.tar.zst")]
+        archive: String,
+        /// Overwrite the files already there
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Everything that goes for running a workout, whichever way.
+#[derive(Args, Clone, Default)]
+pub struct RunArgs {
+    /// Start from a position, that far into a timed excercise
+    #[arg(long, value_name = "SET[/SET_REP].EXCERCISE[+MM:SS]", value_parser = parse_from)]
+    pub from: Option<From>,
+    /// Start where the last unfinished session left off
+    #[arg(long)]
+    pub resume: bool,
+    /// Use a profile of the config
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Read it as text, markdown, toml, json or yaml, whatever its extension
+    #[arg(long)]
+    pub format: Option<FileFormat>,
+    /// Which day of a program file to run
+    #[arg(long, value_name = "N|LABEL")]
+    pub day: Option<String>,
+    /// In which week of the program's cycle
+    #[arg(long, value_name = "N")]
+    pub week: Option<usize>,
+    /// Don't open an audio device at all
+    #[arg(long)]
+    pub no_sound: bool,
+    /// Of the beeps, from 0.0 to 1.0
+    #[arg(long, value_parser = parse_volume)]
+    pub volume: Option<f32>,
+    /// Start with the sound off, m turns it on (the device is still opened)
+    #[arg(long, conflicts_with = "no_sound")]
+    pub mute: bool,
+    /// How long the countdown before the first excercise is, MM:SS
+    #[arg(long, value_name = "MM:SS", value_parser = parse_duration)]
+    pub countdown: Option<Duration>,
+    /// Afterwards write the session for Apple Health
+    #[arg(long, value_name = "OUT.xml")]
+    pub apple_health: Option<String>,
+    /// ... or as a Google Fit (Takeout) session
+    #[arg(long, value_name = "OUT.json")]
+    pub google_fit: Option<String>,
+    /// ... or as a FIT activity
+    #[arg(long, value_name = "OUT.fit")]
+    pub fit: Option<String>,
+    /// ... or append it in InfluxDB line protocol
+    #[arg(long, value_name = "OUT.lp")]
+    pub influx: Option<String>,
+    /// ... and the answers to its `collect:` excercises, for a coach
+    #[arg(long, value_name = "OUT.json")]
+    pub results: Option<String>,
+    /// ... and a caption for every step (`.vtt` for WebVTT)
+    #[arg(long, value_name = "OUT.srt")]
+    pub captions: Option<String>,
+    /// Ask for a key press after every excercise, and log whether it came
+    #[arg(long)]
+    pub acknowledge: bool,
+    /// Tag the session, like `travel`, to filter the stats by
+    #[arg(long = "tag", value_name = "NAME")]
+    pub tags: Vec<String>,
+    /// Ask for more tags at the end
+    #[arg(long)]
+    pub ask_tags: bool,
+    /// Turn on `If flag(NAME)` blocks
+    #[arg(long = "flag", value_name = "NAME")]
+    pub flags: Vec<String>,
+    /// Race rep excercises against the last session of this workout
+    #[arg(long)]
+    pub ghost: bool,
+    /// Show and update the workout's leaderboard, as the --user
+    #[arg(long)]
+    pub leaderboard: bool,
+    /// No motivational line after every set and at the end
+    #[arg(long)]
+    pub no_motivation: bool,
+    /// No line with the time left and the set at the bottom
+    #[arg(long)]
+    pub no_status_line: bool,
+    /// At the end, how late every step started and its cues played
+    #[arg(long)]
+    pub timing_report: bool,
+    /// Upload the FIT activity to Garmin Connect
+    #[cfg(feature = "garmin")]
+    #[arg(long)]
+    pub garmin: bool,
+    /// Show the countdown in an always on top window
+    #[cfg(feature = "overlay")]
+    #[arg(long)]
+    pub overlay: bool,
+    /// Bars for the countdown and the whole session at the top, with what's
+    /// next
+    #[cfg(feature = "tui")]
+    #[arg(long, conflicts_with = "kiosk")]
+    pub tui: bool,
+    /// For a screen always on in the gym
+    #[arg(long)]
+    pub kiosk: bool,
+    /// Redraw at most this often, for slow terminals (a serial console, SSH
+    /// over a bad link)
+    #[arg(long, value_name = "MM:SS", value_parser = parse_duration, conflicts_with = "kiosk")]
+    pub redraw_every: Option<Duration>,
+    /// Stop the session after this many minutes, paused or not
+    #[arg(long, value_name = "MINUTES", value_parser = parse_session_minutes)]
+    pub max_session: Option<u64>,
+}
+
+impl Cli {
+    /// Like [`Parser::parse`], with `workout FILE ...` for `workout run FILE
+    /// ...`.
+    pub fn parse_args(args: impl Iterator<Item = String>) -> Self {
+        use clap::CommandFactory;
+
+        let mut args: Vec<_> = args.collect();
+        // the first that isn't an option or --user's value
+        let mut i = 1;
+        while i < args.len() && args[i].starts_with('-') {
+            i += if args[i] == "--user" { 2 } else { 1 };
+        }
+        if let Some(arg) = args.get(i) {
+            if Self::command().find_subcommand(arg).is_none() && arg != "help" {
+                args.insert(i, "run".to_owned());
+            }
+        }
+        Self::parse_from(args)
+    }
+}
+
+fn parse_from(s: &str) -> Result<From> {
+    let error = || anyhow::Error::msg("Starting position format: SET[/SET_REP].EXCERCISE[+MM:SS]");
+    let (s, offset) = match s.split_once('+') {
+        Some((s, offset)) => (s, Some(parse_duration(offset).map_err(|_| error())?)),
+        None => (s, None),
+    };
+    let Some((mut set, excercise)) = s.split_once('.') else {
+        return Err(error());
+    };
+
+    let set_rep;
+    if let Some((seti, srep)) = set.split_once('/') {
+        set = seti;
+        set_rep = srep.parse::<u16>()?.saturating_sub(1);
+    } else {
+        set_rep = 0;
+    }
+
+    let set = set.parse::<u16>()?.saturating_sub(1);
+    let excercise = excercise.parse::<u16>()?.saturating_sub(1);
+    Ok(((set, set_rep, excercise), offset))
+}
+
+// 20m, or 20:00
+fn parse_minutes(s: &str) -> Result<Duration> {
+    let error = || anyhow::Error::msg(format!("Expected a duration like 20m or 20:00, got: {s}"));
+    match s.strip_suffix('m').map(str::parse::<u64>) {
+        Some(Ok(minutes)) => minutes
+            .checked_mul(60)
+            .map(Duration::from_secs)
+            .ok_or_else(error),
+        _ => parse_duration(s).map_err(|_| error()),
+    }
+}
+
+// 240, or 240m, as long as it's still a duration
+fn parse_session_minutes(s: &str) -> Result<u64> {
+    s.strip_suffix('m')
+        .unwrap_or(s)
+        .parse::<u64>()
+        .ok()
+        .filter(|minutes| minutes.checked_mul(60).is_some())
+        .ok_or_else(|| anyhow::Error::msg(format!("Expected minutes like 240 or 240m, got: {s}")))
+}
+
+fn parse_volume(s: &str) -> Result<f32> {
+    match s.parse::<f32>() {
+        Ok(volume) if (0. ..=1.).contains(&volume) => Ok(volume),
+        _ => Err(anyhow::Error::msg(format!(
+            "Expected a volume from 0.0 to 1.0, got: {s}"
+        ))),
+    }
+}
+
+fn parse_pace(s: &str) -> Result<Duration> {
+    parse_duration(s)
+        .map_err(|_| anyhow::Error::msg(format!("Expected a pace like 05:30, got: {s}")))
+}
+
+fn parse_heart_rate(s: &str) -> Result<(u16, u16)> {
+    workout::intervals::Targets::parse_heart_rate(s)
+}
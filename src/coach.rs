@@ -0,0 +1,159 @@
+use std::{fmt::Write, time::Duration};
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locale,
+    record::SessionRecord,
+    session::{schedule, StepKind},
+    workout::{format_duration, ExcerciseAmout, Workout},
+};
+
+/// What a client sends back: the answers to every `collect:` excercise of a
+/// session.
+#[derive(Serialize, Deserialize)]
+pub struct Results {
+    pub workout: String,
+    pub start: DateTime<Local>,
+    pub completed: bool,
+    pub excercises: Vec<ExcerciseResult>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExcerciseResult {
+    pub set: usize,
+    pub set_rep: u16,
+    pub element: Option<usize>,
+    pub name: String,
+    pub amount: ExcerciseAmout,
+    #[serde(with = "crate::record::secs")]
+    pub duration: Duration,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reps: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpe: Option<f32>,
+    #[serde(default)]
+    pub video: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledged: Option<bool>,
+}
+
+impl Results {
+    pub fn new(record: &SessionRecord) -> Self {
+        // with acknowledgements every excercise is reported
+        let acknowledged = record.steps.iter().any(|s| s.acknowledged.is_some());
+        let excercises = record
+            .steps
+            .iter()
+            .filter(|s| !s.collect.is_empty() || acknowledged)
+            .filter_map(|s| match &s.kind {
+                StepKind::Excercise { name, amount, .. } => Some(ExcerciseResult {
+                    set: s.set,
+                    set_rep: s.set_rep,
+                    element: s.element,
+                    name: name.clone(),
+                    amount: amount.clone(),
+                    duration: s.duration,
+                    reps: s.reps,
+                    rpe: s.rpe,
+                    video: s.video,
+                    acknowledged: s.acknowledged,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            workout: record.workout.clone(),
+            start: record.start,
+            completed: record.completed,
+            excercises,
+        }
+    }
+}
+
+fn result_line(prescribed: &ExcerciseAmout, result: &ExcerciseResult) -> String {
+    let mut parts = vec![];
+    match (prescribed, result.reps) {
+        (ExcerciseAmout::Reps(planned), Some(reps)) if reps != *planned => {
+            parts.push(format!("{reps} reps ({:+})", reps as i32 - *planned as i32))
+        }
+        (_, Some(reps)) => parts.push(format!("{reps} reps")),
+        (_, None) => {}
+    }
+    if let Some(rpe) = result.rpe {
+        parts.push(format!("RPE {}", locale::number(rpe)));
+    }
+    if result.video {
+        parts.push("video".to_owned());
+    }
+    if parts.is_empty() {
+        parts.push(format!("done in {}", format_duration(result.duration)));
+    }
+    match result.acknowledged {
+        Some(true) => parts.push("acknowledged".to_owned()),
+        Some(false) => parts.push("not acknowledged".to_owned()),
+        None => {}
+    }
+    parts.join(", ")
+}
+
+/// Every collected excercise the workout prescribes, next to what the
+/// results say about it.
+pub fn review(workout: &Workout, results: &Results) -> Result<String> {
+    if results.workout != workout.name {
+        return Err(anyhow::Error::msg(format!(
+            "The results are of {}, not {}",
+            results.workout, workout.name
+        )));
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{} on {}{}",
+        results.workout,
+        results.start.format("%Y-%m-%d %H:%M"),
+        if results.completed {
+            ""
+        } else {
+            " (finished early)"
+        }
+    );
+
+    // every excercise was asked about
+    let acknowledged = results.excercises.iter().any(|r| r.acknowledged.is_some());
+    // circuits repeat the same excercise, so they're matched up in order
+    let mut used = vec![false; results.excercises.len()];
+    for step in schedule(workout, (0, 0, 0))? {
+        let StepKind::Excercise { name, amount, .. } = &step.kind else {
+            continue;
+        };
+        if step.collect.is_empty() && !acknowledged {
+            continue;
+        }
+
+        let found = results.excercises.iter().enumerate().position(|(i, r)| {
+            !used[i]
+                && (r.set, r.set_rep, r.element, &r.name)
+                    == (step.set, step.set_rep, step.element, name)
+        });
+        let result = match found {
+            Some(i) => {
+                used[i] = true;
+                result_line(amount, &results.excercises[i])
+            }
+            None => "not done".to_owned(),
+        };
+        let _ = writeln!(
+            out,
+            "  Set {} ({}/{}) {name} {amount}: {result}",
+            step.set + 1,
+            step.set_rep + 1,
+            workout.sections[step.set].reps
+        );
+    }
+    Ok(out)
+}
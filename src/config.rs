@@ -0,0 +1,683 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dirs::{config_dir, data_dir},
+    history,
+    locale::{self, Locale},
+    measured::Measured,
+    session::CueEvent,
+    workout::{
+        parse_duration, BeepLevel, ExcerciseAmout, Pan, Workout, WorkoutSetElement,
+        RAMP_PERCENTAGES,
+    },
+};
+
+mod mmss {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::workout::{format_duration, parse_duration};
+
+    pub fn serialize<S: Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match d {
+            Some(d) => s.serialize_some(&format_duration(*d)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        let Some(s) = Option::<String>::deserialize(d)? else {
+            return Ok(None);
+        };
+        parse_duration(&s)
+            .map(Some)
+            .map_err(|_| serde::de::Error::custom(format!("expected MM:SS, got {s}")))
+    }
+}
+
+mod mmss_map {
+    use std::{collections::BTreeMap, time::Duration};
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::workout::{format_duration, parse_duration};
+
+    pub fn serialize<S: Serializer>(
+        m: &BTreeMap<String, Duration>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        s.collect_map(m.iter().map(|(k, d)| (k, format_duration(*d))))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<BTreeMap<String, Duration>, D::Error> {
+        BTreeMap::<String, String>::deserialize(d)?
+            .into_iter()
+            .map(|(k, s)| match parse_duration(&s) {
+                Ok(d) => Ok((k.to_lowercase(), d)),
+                Err(_) => Err(serde::de::Error::custom(format!("expected MM:SS, got {s}"))),
+            })
+            .collect()
+    }
+}
+
+/// How fast machine excercises usually go, to estimate how long they take.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Pace {
+    /// Split time, like rowers and ski ergs show it.
+    #[serde(default, with = "mmss", skip_serializing_if = "Option::is_none")]
+    pub per_500m: Option<Duration>,
+    #[serde(default, with = "mmss", skip_serializing_if = "Option::is_none")]
+    pub per_calorie: Option<Duration>,
+}
+impl Pace {
+    pub fn estimate(&self, amount: &ExcerciseAmout) -> Option<Duration> {
+        match amount {
+            ExcerciseAmout::Meters(m) => self.per_500m.map(|p| p.mul_f64(*m as f64 / 500.)),
+            ExcerciseAmout::Calories(c) => self.per_calorie.map(|p| p * *c as u32),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    #[default]
+    Metric,
+    Imperial,
+}
+impl Units {
+    pub fn distance(&self, meters: u32) -> String {
+        match self {
+            Units::Metric => format!("{meters}m"),
+            Units::Imperial => format!("{:.0}yd", meters as f64 / 0.9144),
+        }
+    }
+}
+
+fn full_volume() -> f32 {
+    1.
+}
+fn one() -> f64 {
+    1.
+}
+fn is_one(v: &f64) -> bool {
+    *v == 1.
+}
+fn is_false(v: &bool) -> bool {
+    !v
+}
+
+fn env_var<T>(name: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Option<T>> {
+    match env::var(name) {
+        Ok(v) => parse(&v)
+            .map(Some)
+            .ok_or_else(|| anyhow::Error::msg(format!("Couldn't parse {name}={v}"))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// The shape of a beep in seconds, whatever isn't set stays as it is.
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+pub struct Envelope {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fade_in: Option<f64>,
+    /// Into silence at the end, the whole beep long if as long as it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fade_out: Option<f64>,
+}
+impl Envelope {
+    fn is_empty(&self) -> bool {
+        self.length.is_none() && self.fade_in.is_none() && self.fade_out.is_none()
+    }
+
+    /// Length, fade in and fade out, over `defaults`.
+    pub fn or(&self, defaults: [f64; 3]) -> [f64; 3] {
+        let [length, fade_in, fade_out] = defaults;
+        [
+            self.length.unwrap_or(length),
+            self.fade_in.unwrap_or(fade_in),
+            self.fade_out.unwrap_or(fade_out),
+        ]
+    }
+}
+
+/// What a beep level sounds like, the built in sine wave where nothing's
+/// set.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct BeepSound {
+    /// Of the sine wave, in Hz.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<f32>,
+    /// In seconds, over the envelope's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<f64>,
+    /// A WAV, OGG, MP3 or FLAC file played instead, relative to the config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+impl BeepSound {
+    fn is_empty(&self) -> bool {
+        self.frequency.is_none() && self.length.is_none() && self.file.is_none()
+    }
+}
+
+/// The beeps of each level, and files for when a workout starts and is
+/// done instead of three beeps.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Beeps {
+    #[serde(default, skip_serializing_if = "BeepSound::is_empty")]
+    pub high: BeepSound,
+    #[serde(default, skip_serializing_if = "BeepSound::is_empty")]
+    pub mid: BeepSound,
+    #[serde(default, skip_serializing_if = "BeepSound::is_empty")]
+    pub low: BeepSound,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complete: Option<String>,
+}
+impl Beeps {
+    fn is_empty(&self) -> bool {
+        self.high.is_empty()
+            && self.mid.is_empty()
+            && self.low.is_empty()
+            && self.start.is_none()
+            && self.complete.is_none()
+    }
+
+    pub fn level(&self, level: BeepLevel) -> &BeepSound {
+        match level {
+            BeepLevel::High => &self.high,
+            BeepLevel::Mid => &self.mid,
+            BeepLevel::Low => &self.low,
+        }
+    }
+}
+
+/// Somewhere the cues go, see [`crate::audio::AnnouncementSink`].
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Sink {
+    /// The beeps and sound files, on the audio device.
+    Audio,
+    /// `say` cues read out loud.
+    Speech,
+    /// The terminal bell, for every sound.
+    Bell,
+    /// A GPIO pin switched on for a moment (a relay, a light), through sysfs.
+    Gpio { pin: u32 },
+    /// The sound's name POSTed to it, with curl.
+    Webhook { url: String },
+    /// The sound's name published, with mosquitto_pub.
+    Mqtt { host: String, topic: String },
+}
+
+/// Overrides for [`Config`], picked with `--profile NAME`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    #[serde(default, with = "mmss", skip_serializing_if = "Option::is_none")]
+    pub set_rest: Option<Duration>,
+    #[serde(default, with = "mmss", skip_serializing_if = "Option::is_none")]
+    pub rest_after_set: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    /// Instead of the config's, for a different sounding set of beeps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beeps: Option<Beeps>,
+}
+
+/// `config.toml` in the config directory.
+#[derive(Serialize, Deserialize)]
+pub struct Config {
+    /// Where workout files are looked for when they aren't found as given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workouts: Option<PathBuf>,
+    #[serde(default)]
+    pub units: Units,
+    /// Like `de_DE`, for how numbers and times of day are written, the
+    /// environment's if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Of the beeps and sounds, from 0 to 1.
+    #[serde(default = "full_volume")]
+    pub volume: f32,
+    /// Audio output by name, the system's default if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// Of the beeps.
+    #[serde(default, skip_serializing_if = "Envelope::is_empty")]
+    pub envelope: Envelope,
+    /// Of the gentler beeps of `relax` excercises.
+    #[serde(default, skip_serializing_if = "Envelope::is_empty")]
+    pub relax_envelope: Envelope,
+    #[serde(default, skip_serializing_if = "Beeps::is_empty")]
+    pub beeps: Beeps,
+    /// Where the cues go, all of them, the audio device and speech if not
+    /// set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sinks: Vec<Sink>,
+    /// Where the cues of each event are heard.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pan: BTreeMap<CueEvent, Pan>,
+    /// Between the rounds of sets that don't say.
+    #[serde(default, with = "mmss", skip_serializing_if = "Option::is_none")]
+    pub set_rest: Option<Duration>,
+    /// Between sets that don't say.
+    #[serde(default, with = "mmss", skip_serializing_if = "Option::is_none")]
+    pub rest_after_set: Option<Duration>,
+    /// Scales the work of every excercise, like a program's week does.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    /// The countdown before the first excercise.
+    #[serde(default, with = "mmss", skip_serializing_if = "Option::is_none")]
+    pub pre_roll: Option<Duration>,
+    /// How often the stopwatch and the overlay are redrawn, every second if
+    /// not set, for slow terminals.
+    #[serde(default, with = "mmss", skip_serializing_if = "Option::is_none")]
+    pub redraw_every: Option<Duration>,
+    /// In minutes, the longest a session runs before it's stopped, paused
+    /// or not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_session: Option<u64>,
+    /// Beeps for the last this many seconds of timed excercises and rests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub countdown_beeps: Option<u8>,
+    /// Doesn't open an audio device at all.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub no_audio: bool,
+    /// Asks for a key press after every timed excercise, and logs whether
+    /// every excercise got one.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub acknowledge: bool,
+    /// Asks for tags at the end of every session.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub ask_tags: bool,
+    /// Doesn't print a motivational line after every set and at the end.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub no_motivation: bool,
+    /// Doesn't keep a line with the time left at the bottom.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub no_status_line: bool,
+    /// Works out the length of a workout from how long its excercises and
+    /// rests took in the history.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub measured_length: bool,
+    /// Runs the timers this many times faster, to try out a workout.
+    #[serde(default = "one", skip_serializing_if = "is_one")]
+    pub speed: f64,
+    /// Lengthens the rest after falling short of an excercise's reps by this
+    /// much (`0.2` is 20%), and shortens it after doing more.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adaptive_rest: Option<f64>,
+    /// Pauses a rep excercise that waited this many times longer than its
+    /// median in the history.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_pause: Option<f64>,
+    /// Of the working weight, for the ramp-up sets of `ramp` excercises.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ramp: Option<Vec<u16>>,
+    /// The most work a session should plan for a muscle group, by the
+    /// excercises' `muscles`.
+    #[serde(default, with = "mmss_map", skip_serializing_if = "BTreeMap::is_empty")]
+    pub work_caps: BTreeMap<String, Duration>,
+    /// By excercise name, case insensitive.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub paces: BTreeMap<String, Pace>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            workouts: None,
+            units: Units::default(),
+            locale: None,
+            volume: full_volume(),
+            device: None,
+            envelope: Envelope::default(),
+            relax_envelope: Envelope::default(),
+            beeps: Beeps::default(),
+            sinks: vec![],
+            pan: BTreeMap::new(),
+            set_rest: None,
+            rest_after_set: None,
+            scale: None,
+            pre_roll: None,
+            redraw_every: None,
+            max_session: None,
+            countdown_beeps: None,
+            no_audio: false,
+            acknowledge: false,
+            ask_tags: false,
+            no_motivation: false,
+            no_status_line: false,
+            measured_length: false,
+            speed: 1.,
+            adaptive_rest: None,
+            idle_pause: None,
+            ramp: None,
+            work_caps: BTreeMap::new(),
+            paces: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+fn ask(question: &str, default: &str) -> Result<String> {
+    print!("{question} [{default}] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_owned())
+}
+
+impl Config {
+    pub fn path() -> Result<PathBuf> {
+        Ok(config_dir()?.join("config.toml"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        toml::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| anyhow::Error::msg(format!("Couldn't read {}: {e}", path.display())))
+    }
+
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, toml::to_string(self)?)?;
+        Ok(path)
+    }
+
+    /// Asks for the basics and saves them, for the first run. `test_beep`
+    /// plays a beep at the given volume.
+    pub fn wizard(test_beep: impl Fn(f32) -> Result<()>) -> Result<Self> {
+        println!("No config yet, let's set one up (just press enter for the default)");
+        let mut config = Self::default();
+
+        let workouts = ask("Where do you keep your workouts?", "here")?;
+        if workouts != "here" {
+            config.workouts = Some(workouts.into());
+        }
+
+        config.units = loop {
+            match ask("Metric or imperial units?", "metric")?
+                .to_lowercase()
+                .as_str()
+            {
+                "metric" | "m" => break Units::Metric,
+                "imperial" | "i" => break Units::Imperial,
+                _ => println!("Type metric or imperial"),
+            }
+        };
+
+        config.volume = loop {
+            let Ok(volume) = ask("Volume, from 0 to 100?", "100")?.parse::<u8>() else {
+                println!("Type a number from 0 to 100");
+                continue;
+            };
+            let volume = volume.min(100) as f32 / 100.;
+
+            println!("Playing a test beep...");
+            test_beep(volume)?;
+            if !ask("Did you hear it? (y/n)", "y")?.starts_with(['n', 'N']) {
+                break volume;
+            }
+            println!("Check your audio device, or try a different volume");
+        };
+
+        let path = config.save()?;
+        println!("Saved to {}, edit it any time\n", path.display());
+        Ok(config)
+    }
+
+    /// Overrides the settings with the ones of the profile called `name`.
+    pub fn use_profile(&mut self, name: &str) -> Result<()> {
+        let profile = self.take_profile(name)?;
+        self.set_rest = profile.set_rest.or(self.set_rest);
+        self.rest_after_set = profile.rest_after_set.or(self.rest_after_set);
+        self.scale = profile.scale.or(self.scale);
+        self.use_sound(profile);
+        Ok(())
+    }
+
+    /// Only the device, volume and beeps of the profile called `name`, for a
+    /// workout's `audio`.
+    pub fn use_audio_profile(&mut self, name: &str) -> Result<()> {
+        let profile = self.take_profile(name)?;
+        self.use_sound(profile);
+        Ok(())
+    }
+
+    fn use_sound(&mut self, profile: Profile) {
+        self.device = profile.device.or(self.device.take());
+        self.volume = profile.volume.unwrap_or(self.volume);
+        if let Some(beeps) = profile.beeps {
+            self.beeps = beeps;
+        }
+    }
+
+    fn take_profile(&mut self, name: &str) -> Result<Profile> {
+        let Some(profile) = self.profiles.remove(name) else {
+            let names: Vec<_> = self.profiles.keys().map(String::as_str).collect();
+            return Err(anyhow::Error::msg(format!(
+                "No profile called {name} in the config (there's {})",
+                if names.is_empty() {
+                    "none".to_owned()
+                } else {
+                    names.join(", ")
+                }
+            )));
+        };
+        Ok(profile)
+    }
+
+    /// `WORKOUT_VOLUME`, `WORKOUT_DEVICE`, ... override the config and its
+    /// profile.
+    pub fn apply_env(&mut self) -> Result<()> {
+        let non_empty = |v: &str| (!v.is_empty()).then(|| v.to_owned());
+        let flag = |v: &str| match v {
+            "1" | "true" | "yes" => Some(true),
+            "0" | "false" | "no" | "" => Some(false),
+            _ => None,
+        };
+
+        if let Some(workouts) = env_var("WORKOUT_WORKOUTS", non_empty)? {
+            self.workouts = Some(workouts.into());
+        }
+        if let Some(units) = env_var("WORKOUT_UNITS", |v| match v {
+            "metric" => Some(Units::Metric),
+            "imperial" => Some(Units::Imperial),
+            _ => None,
+        })? {
+            self.units = units;
+        }
+        if let Some(locale) = env_var("WORKOUT_LOCALE", non_empty)? {
+            self.locale = Some(locale);
+        }
+        if let Some(volume) = env_var("WORKOUT_VOLUME", |v| v.parse().ok())? {
+            self.volume = volume;
+        }
+        if let Some(device) = env_var("WORKOUT_DEVICE", non_empty)? {
+            self.device = Some(device);
+        }
+        if let Some(rest) = env_var("WORKOUT_SET_REST", |v| parse_duration(v).ok())? {
+            self.set_rest = Some(rest);
+        }
+        if let Some(rest) = env_var("WORKOUT_REST_AFTER_SET", |v| parse_duration(v).ok())? {
+            self.rest_after_set = Some(rest);
+        }
+        if let Some(scale) = env_var("WORKOUT_SCALE", |v| v.parse().ok())? {
+            self.scale = Some(scale);
+        }
+        if let Some(pre_roll) = env_var("WORKOUT_PRE_ROLL", |v| parse_duration(v).ok())? {
+            self.pre_roll = Some(pre_roll);
+        }
+        if let Some(every) = env_var("WORKOUT_REDRAW_EVERY", |v| parse_duration(v).ok())? {
+            self.redraw_every = Some(every);
+        }
+        if let Some(minutes) = env_var("WORKOUT_MAX_SESSION", |v| v.parse().ok())? {
+            self.max_session = Some(minutes);
+        }
+        if let Some(seconds) = env_var("WORKOUT_COUNTDOWN_BEEPS", |v| v.parse().ok())? {
+            self.countdown_beeps = Some(seconds);
+        }
+        if let Some(no_audio) = env_var("WORKOUT_NO_AUDIO", flag)? {
+            self.no_audio = no_audio;
+        }
+        if let Some(acknowledge) = env_var("WORKOUT_ACKNOWLEDGE", flag)? {
+            self.acknowledge = acknowledge;
+        }
+        if let Some(ask_tags) = env_var("WORKOUT_ASK_TAGS", flag)? {
+            self.ask_tags = ask_tags;
+        }
+        if let Some(no_motivation) = env_var("WORKOUT_NO_MOTIVATION", flag)? {
+            self.no_motivation = no_motivation;
+        }
+        if let Some(no_status_line) = env_var("WORKOUT_NO_STATUS_LINE", flag)? {
+            self.no_status_line = no_status_line;
+        }
+        if let Some(measured) = env_var("WORKOUT_MEASURED_LENGTH", flag)? {
+            self.measured_length = measured;
+        }
+        if let Some(speed) = env_var("WORKOUT_SPEED", |v| v.parse().ok().filter(|s| *s > 0.))? {
+            self.speed = speed;
+        }
+        if let Some(adaptive) = env_var("WORKOUT_ADAPTIVE_REST", |v| {
+            v.parse().ok().filter(|a| (0. ..1.).contains(a))
+        })? {
+            self.adaptive_rest = Some(adaptive);
+        }
+        if let Some(idle) = env_var("WORKOUT_IDLE_PAUSE", |v| v.parse().ok().filter(|i| *i > 1.))? {
+            self.idle_pause = Some(idle);
+        }
+        if let Some(ramp) = env_var("WORKOUT_RAMP", |v| {
+            v.split(',').map(|p| p.trim().parse().ok()).collect()
+        })? {
+            self.ramp = Some(ramp);
+        }
+        Ok(())
+    }
+
+    /// A warning for every muscle group the workout plans more work for than
+    /// its cap.
+    pub fn over_caps(&self, workout: &Workout) -> Vec<String> {
+        workout
+            .muscle_work()
+            .into_iter()
+            .filter_map(|(muscle, work)| {
+                let cap = *self.work_caps.get(&muscle)?;
+                (work > cap).then(|| {
+                    format!(
+                        "{} of work for {muscle}, over its cap of {}",
+                        locale::minutes(work),
+                        locale::decimal(cap.as_secs_f64() / 60., 1)
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// What the history says, if the length should go by it.
+    pub fn measured(&self) -> Result<Option<Measured>> {
+        Ok(match self.measured_length {
+            true => Some(Measured::new(&history::load_all()?)),
+            false => None,
+        })
+    }
+
+    pub fn ramp_percentages(&self) -> &[u16] {
+        self.ramp.as_deref().unwrap_or(&RAMP_PERCENTAGES)
+    }
+
+    /// The config's locale, or else the environment's.
+    pub fn locale(&self) -> Locale {
+        match &self.locale {
+            Some(tag) => Locale::parse(tag),
+            None => Locale::from_env(),
+        }
+    }
+
+    /// `workouts` in the data directory, unless the config says.
+    pub fn workouts_dir(&self) -> Option<PathBuf> {
+        match &self.workouts {
+            Some(dir) => Some(dir.clone()),
+            None => data_dir().ok().map(|d| d.join("workouts")),
+        }
+    }
+
+    /// `file` as given if it exists, otherwise from the
+    /// [workouts directory](Config::workouts_dir).
+    pub fn find_workout(&self, file: &str) -> String {
+        match self.workouts_dir() {
+            Some(dir) if !Path::new(file).exists() && dir.join(file).exists() => {
+                dir.join(file).to_string_lossy().into_owned()
+            }
+            _ => file.to_owned(),
+        }
+    }
+
+    pub fn pace(&self, excercise: &str) -> Option<&Pace> {
+        self.paces
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(excercise))
+            .map(|(_, pace)| pace)
+    }
+
+    /// Fills in the default set rests, scales the work and then fills in the
+    /// estimates of machine excercises that don't have one.
+    pub fn apply(&self, workout: &mut Workout) {
+        if let Some(rest) = self.set_rest {
+            for set in &mut workout.sections {
+                set.set_rest = set.set_rest.or(Some(rest));
+            }
+        }
+        if let Some(rest) = self.rest_after_set {
+            for set in &mut workout.sections {
+                set.rest_after = set.rest_after.or(Some(rest));
+            }
+        }
+        if let Some(scale) = self.scale {
+            workout.scale(scale);
+        }
+
+        workout.visit_elements_mut(|part| {
+            let WorkoutSetElement::Excercise {
+                name,
+                amount,
+                options,
+            } = part
+            else {
+                return;
+            };
+            if options.estimate.is_none() {
+                options.estimate = self.pace(name).and_then(|p| p.estimate(amount));
+            }
+        });
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use directories::ProjectDirs;
+
+// the XDG base directories on Linux, Application Support on macOS and
+// AppData on Windows
+fn project() -> Result<ProjectDirs> {
+    ProjectDirs::from("", "", "workout")
+        .ok_or_else(|| anyhow::Error::msg("Can't find the home directory"))
+}
+
+/// `config.toml`.
+pub fn config_dir() -> Result<PathBuf> {
+    Ok(project()?.config_dir().to_owned())
+}
+
+/// The history and the default workouts directory.
+pub fn data_dir() -> Result<PathBuf> {
+    Ok(project()?.data_dir().to_owned())
+}
+
+/// Where unfinished sessions can be resumed from.
+pub fn state_dir() -> Result<PathBuf> {
+    let project = project()?;
+    // only Linux has a separate one
+    Ok(project
+        .state_dir()
+        .unwrap_or(project.data_local_dir())
+        .to_owned())
+}
+
+// where everything used to live
+fn legacy_dir() -> Result<PathBuf> {
+    let exe = env::current_exe()?;
+    let Some(dir) = exe.parent() else {
+        return Err(anyhow::Error::msg("Can't find the executable's directory"));
+    };
+    Ok(dir.to_owned())
+}
+
+fn move_to(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    // different file systems
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            move_to(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::remove_dir(from)?;
+    } else {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// Moves the config and history from next to the binary, where they were
+/// kept before, unless there's something in the new place already.
+pub fn migrate() -> Result<()> {
+    let legacy = legacy_dir()?;
+    for (from, to) in [
+        (
+            legacy.join("config.toml"),
+            config_dir()?.join("config.toml"),
+        ),
+        (legacy.join("history"), data_dir()?.join("history")),
+    ] {
+        if !from.exists() || to.exists() {
+            continue;
+        }
+        move_to(&from, &to)?;
+        println!("Moved {} to {}", from.display(), to.display());
+    }
+    Ok(())
+}
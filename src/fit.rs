@@ -0,0 +1,177 @@
+use chrono::{DateTime, Local};
+
+use crate::record::SessionRecord;
+
+// Just enough of the FIT protocol to describe a finished strength session:
+// file_id, start/stop events, one lap, one session and the activity summary.
+
+const FIT_EPOCH_OFFSET: i64 = 631_065_600; // 1989-12-31T00:00:00Z
+
+const ENUM: u8 = 0x00;
+const UINT16: u8 = 0x84;
+const UINT32: u8 = 0x86;
+const UINT32Z: u8 = 0x8C;
+
+const CRC_TABLE: [u16; 16] = [
+    0x0000, 0xCC01, 0xD801, 0x1400, 0xF001, 0x3C00, 0x2800, 0xE401, 0xA001, 0x6C00, 0x7800, 0xB401,
+    0x5000, 0x9C01, 0x8801, 0x4400,
+];
+
+fn crc(data: &[u8]) -> u16 {
+    data.iter().fold(0, |mut crc, &byte| {
+        for nibble in [byte & 0xF, byte >> 4] {
+            let tmp = CRC_TABLE[(crc & 0xF) as usize];
+            crc = ((crc >> 4) & 0x0FFF) ^ tmp ^ CRC_TABLE[nibble as usize];
+        }
+        crc
+    })
+}
+
+enum Value {
+    Enum(u8),
+    U16(u16),
+    U32(u32),
+    U32Z(u32),
+}
+
+struct Writer {
+    data: Vec<u8>,
+}
+impl Writer {
+    fn message(&mut self, local: u8, global: u16, fields: &[(u8, Value)]) {
+        self.data.push(0x40 | local);
+        self.data.extend([0, 0]); // reserved, little endian
+        self.data.extend(global.to_le_bytes());
+        self.data.push(fields.len() as u8);
+        for (num, value) in fields {
+            let (size, base) = match value {
+                Value::Enum(_) => (1, ENUM),
+                Value::U16(_) => (2, UINT16),
+                Value::U32(_) => (4, UINT32),
+                Value::U32Z(_) => (4, UINT32Z),
+            };
+            self.data.extend([*num, size, base]);
+        }
+
+        self.data.push(local);
+        for (_, value) in fields {
+            match value {
+                Value::Enum(v) => self.data.push(*v),
+                Value::U16(v) => self.data.extend(v.to_le_bytes()),
+                Value::U32(v) | Value::U32Z(v) => self.data.extend(v.to_le_bytes()),
+            }
+        }
+    }
+}
+
+fn fit_time(t: &DateTime<Local>) -> u32 {
+    (t.timestamp() - FIT_EPOCH_OFFSET) as u32
+}
+
+pub fn session_fit(record: &SessionRecord) -> Vec<u8> {
+    const TIMER: u8 = 0;
+    const START: u8 = 0;
+    const STOP_ALL: u8 = 4;
+    const TRAINING: u8 = 10;
+    const STRENGTH_TRAINING: u8 = 20;
+
+    let start = fit_time(&record.start);
+    let end = fit_time(&record.end);
+    let elapsed = record.duration().as_millis() as u32;
+    let local_end = end.wrapping_add(record.end.offset().local_minus_utc() as u32);
+
+    let mut w = Writer { data: vec![] };
+
+    // file_id
+    w.message(
+        0,
+        0,
+        &[
+            (0, Value::Enum(4)),     // type: activity
+            (1, Value::U16(255)),    // manufacturer: development
+            (2, Value::U16(0)),      // product
+            (3, Value::U32Z(start)), // serial_number
+            (4, Value::U32(start)),  // time_created
+        ],
+    );
+    // event: timer start
+    w.message(
+        1,
+        21,
+        &[
+            (253, Value::U32(start)),
+            (0, Value::Enum(TIMER)),
+            (1, Value::Enum(START)),
+        ],
+    );
+    // event: timer stop
+    w.message(
+        1,
+        21,
+        &[
+            (253, Value::U32(end)),
+            (0, Value::Enum(TIMER)),
+            (1, Value::Enum(STOP_ALL)),
+        ],
+    );
+    // lap
+    w.message(
+        2,
+        19,
+        &[
+            (253, Value::U32(end)),
+            (2, Value::U32(start)),
+            (7, Value::U32(elapsed)),
+            (8, Value::U32(elapsed)),
+            (0, Value::Enum(9)), // event: lap
+            (1, Value::Enum(1)), // event_type: stop
+            (254, Value::U16(0)),
+        ],
+    );
+    // session
+    w.message(
+        3,
+        18,
+        &[
+            (253, Value::U32(end)),
+            (2, Value::U32(start)),
+            (7, Value::U32(elapsed)),
+            (8, Value::U32(elapsed)),
+            (5, Value::Enum(TRAINING)),
+            (6, Value::Enum(STRENGTH_TRAINING)),
+            (25, Value::U16(0)), // first_lap_index
+            (26, Value::U16(1)), // num_laps
+            (0, Value::Enum(8)), // event: session
+            (1, Value::Enum(1)), // event_type: stop
+            (254, Value::U16(0)),
+        ],
+    );
+    // activity
+    w.message(
+        4,
+        34,
+        &[
+            (253, Value::U32(end)),
+            (0, Value::U32(elapsed)),
+            (1, Value::U16(1)),   // num_sessions
+            (2, Value::Enum(0)),  // type: manual
+            (3, Value::Enum(26)), // event: activity
+            (4, Value::Enum(1)),  // event_type: stop
+            (5, Value::U32(local_end)),
+        ],
+    );
+
+    let mut out = Vec::with_capacity(w.data.len() + 16);
+    out.push(14); // header size
+    out.push(0x20); // protocol version 2.0
+    out.extend(2132u16.to_le_bytes()); // profile version 21.32
+    out.extend((w.data.len() as u32).to_le_bytes());
+    out.extend(b".FIT");
+    let header_crc = crc(&out);
+    out.extend(header_crc.to_le_bytes());
+
+    out.extend(w.data);
+    let file_crc = crc(&out);
+    out.extend(file_crc.to_le_bytes());
+    out
+}
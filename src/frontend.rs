@@ -0,0 +1,140 @@
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    session::{Session, State, StepKind},
+    workout::{format_duration, ExcerciseAmout, Sound},
+};
+
+/// How a running session's going, for whatever shows it besides the lines
+/// printed.
+pub struct View {
+    /// What's on, like `Squats` or `Rest, next: Squats`.
+    pub label: String,
+    /// Its time left, the stopwatch of a to failure excercise, or its reps.
+    pub time: String,
+    /// How far into the current step, from 0 to 1, while its timer runs.
+    pub step: Option<f64>,
+    /// Like `set 1/3, round 2/2`.
+    pub position: String,
+    /// The next two steps.
+    pub upcoming: Vec<String>,
+    /// Of the whole session, from 0 to 100.
+    pub percent: f64,
+    pub elapsed: Duration,
+    /// Planned, without what waits for the user.
+    pub remaining: Duration,
+}
+impl View {
+    pub fn of<B: Fn(&Sound)>(session: &Session<B>, started: Instant, now: Instant) -> Self {
+        let progress = session.progress(now);
+        let (label, time) = label_and_time(session, now);
+        let timing = matches!(
+            session.state(),
+            State::Countdown | State::Preparing | State::Excercising | State::Resting
+        );
+        let step = session
+            .current_step()
+            .and_then(|s| s.duration)
+            .filter(|d| timing && !d.is_zero())
+            .map(|d| (session.elapsed(now).as_secs_f64() / d.as_secs_f64()).min(1.));
+
+        let sets = session.sets().len();
+        let mut position = format!("set {}/{sets}", progress.set + 1);
+        if progress.set_reps > 1 {
+            position += &format!(", round {}/{}", progress.set_rep + 1, progress.set_reps);
+        }
+        Self {
+            label,
+            time,
+            step,
+            position,
+            upcoming: session.upcoming().take(2).map(|(_, d)| d).collect(),
+            percent: progress.percent,
+            elapsed: now.saturating_duration_since(started),
+            remaining: progress.left,
+        }
+    }
+}
+
+// what's on and its time, as the overlay shows them
+fn label_and_time<B: Fn(&Sound)>(session: &Session<B>, now: Instant) -> (String, String) {
+    let Some(step) = session.current_step() else {
+        return (String::new(), String::new());
+    };
+    let label = match &step.kind {
+        StepKind::Countdown => "Get ready".to_owned(),
+        StepKind::Excercise { name, .. } => name.clone(),
+        StepKind::Rest { next: Some(next) } => format!("Rest, next: {next}"),
+        StepKind::Rest { next: None } | StepKind::SetRest => "Rest".to_owned(),
+        StepKind::Prep { name } => format!("Prep for {name}"),
+        StepKind::Optional { name } => format!("{name}?"),
+        StepKind::Acknowledge { name } => format!("Done with {name}?"),
+    };
+
+    let elapsed = session.elapsed(now);
+    let time = match (&step.kind, step.duration) {
+        (_, Some(d)) => format_duration(Duration::from_secs(
+            d.saturating_sub(elapsed).as_secs_f64().ceil() as u64,
+        )),
+        (
+            StepKind::Excercise {
+                amount: ExcerciseAmout::Failure,
+                ..
+            },
+            None,
+        ) => format_duration(elapsed),
+        (StepKind::Excercise { amount, .. }, None) => amount.to_string(),
+        _ => String::new(),
+    };
+    (label, time)
+}
+
+/// Shows a running session, drawn again every second (or `redraw_every`)
+/// and for every new step.
+pub trait Frontend {
+    fn draw(&self, view: &View);
+
+    /// Out of the way of the lines printed next, it's drawn again after.
+    fn clear(&self) {}
+}
+
+/// Told what's on and its countdown, like the overlay and the kiosk are.
+impl<F: Fn(&str, &str)> Frontend for F {
+    fn draw(&self, view: &View) {
+        self(&view.label, &view.time);
+    }
+}
+
+/// A line at the bottom with the time left and the set, redrawn in place
+/// while a timer runs.
+#[derive(Default)]
+pub struct StatusLine {
+    drawn: Cell<bool>,
+}
+impl Frontend for StatusLine {
+    fn draw(&self, view: &View) {
+        if view.step.is_none() {
+            return self.clear();
+        }
+        let mut out = io::stdout().lock();
+        let _ = write!(
+            out,
+            "\r\x1b[2K    {} | {} left | {}",
+            view.label, view.time, view.position
+        );
+        let _ = out.flush();
+        self.drawn.set(true);
+    }
+
+    fn clear(&self) {
+        if self.drawn.replace(false) {
+            let mut out = io::stdout().lock();
+            let _ = write!(out, "\r\x1b[2K");
+            let _ = out.flush();
+        }
+    }
+}
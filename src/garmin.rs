@@ -0,0 +1,55 @@
+use std::{env, fs, io::Write, path::PathBuf};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+const UPLOAD_URL: &str = "https://connectapi.garmin.com/upload-service/upload/.fit";
+
+// Same shape garth (https://github.com/matin/garth) saves its OAuth2 token in,
+// so logging in once with it is enough.
+#[derive(Deserialize)]
+struct Token {
+    access_token: String,
+}
+
+fn token_path() -> Result<PathBuf> {
+    if let Ok(path) = env::var("GARMIN_TOKEN_FILE") {
+        return Ok(path.into());
+    }
+    let Ok(home) = env::var("HOME") else {
+        return Err(anyhow::Error::msg(
+            "Can't find the Garmin token, set GARMIN_TOKEN_FILE",
+        ));
+    };
+    Ok(PathBuf::from(home).join(".garth/oauth2_token.json"))
+}
+
+pub fn upload(fit: &[u8]) -> Result<()> {
+    let path = token_path()?;
+    let token: Token = serde_json::from_str(&fs::read_to_string(&path).map_err(|e| {
+        anyhow::Error::msg(format!(
+            "Couldn't read Garmin token {}: {e}",
+            path.display()
+        ))
+    })?)?;
+
+    const BOUNDARY: &str = "----workout-fit-upload";
+    let mut body = vec![];
+    write!(
+        body,
+        "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"workout.fit\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+    )?;
+    body.extend(fit);
+    write!(body, "\r\n--{BOUNDARY}--\r\n")?;
+
+    ureq::post(UPLOAD_URL)
+        .set("Authorization", &format!("Bearer {}", token.access_token))
+        .set("NK", "NT")
+        .set(
+            "Content-Type",
+            &format!("multipart/form-data; boundary={BOUNDARY}"),
+        )
+        .send_bytes(&body)?;
+
+    Ok(())
+}
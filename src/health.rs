@@ -0,0 +1,51 @@
+use anyhow::Result;
+use chrono::{SecondsFormat, Utc};
+use serde_json::json;
+
+use crate::{html::escape, record::SessionRecord};
+
+pub fn apple_health_xml(record: &SessionRecord) -> String {
+    const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+    let start = record.start.format(DATE_FORMAT);
+    let end = record.end.format(DATE_FORMAT);
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE HealthData>
+<HealthData locale="en_US">
+ <Workout workoutActivityType="HKWorkoutActivityTypeTraditionalStrengthTraining" duration="{:.2}" durationUnit="min" sourceName="workout" creationDate="{end}" startDate="{start}" endDate="{end}">
+  <MetadataEntry key="HKWorkoutBrandName" value="{}"/>
+ </Workout>
+</HealthData>
+"#,
+        record.duration().as_secs_f64() / 60.,
+        escape(&record.workout),
+    )
+}
+
+pub fn google_fit_json(record: &SessionRecord) -> Result<String> {
+    let start = record
+        .start
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Millis, true);
+    let end = record
+        .end
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Millis, true);
+
+    let session = json!({
+        "fitnessActivity": "strength_training",
+        "name": record.workout,
+        "startTime": start,
+        "endTime": end,
+        "duration": format!("{:.3}s", record.duration().as_secs_f64()),
+        "segment": [{
+            "fitnessActivity": "strength_training",
+            "startTime": start,
+            "endTime": end,
+        }],
+        "aggregate": [],
+    });
+
+    Ok(serde_json::to_string_pretty(&session)?)
+}
@@ -0,0 +1,630 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Lines, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+    time::Duration,
+};
+
+use age::secrecy::SecretString;
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    dirs::{data_dir, state_dir},
+    record::{SessionRecord, StepRecord},
+};
+
+// set with --user, everyone else shares the top of the directories
+static USER: OnceLock<String> = OnceLock::new();
+
+/// Keeps the history and resume state of `name` apart from everyone else's
+/// for the rest of the run.
+pub fn set_user(name: String) -> Result<()> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(anyhow::Error::msg(format!("Invalid user name {name}")));
+    }
+    USER.set(name)
+        .map_err(|_| anyhow::Error::msg("User already set"))
+}
+
+pub fn user() -> Option<&'static str> {
+    USER.get().map(String::as_str)
+}
+
+fn user_dir(dir: PathBuf) -> PathBuf {
+    match user() {
+        Some(user) => dir.join("users").join(user),
+        None => dir,
+    }
+}
+
+pub fn history_dir() -> Result<PathBuf> {
+    Ok(data_dir()?.join("history"))
+}
+
+// unfinished positions by workout file (and day of a program)
+fn resume_path() -> Result<PathBuf> {
+    Ok(user_dir(state_dir()?).join("resume.json"))
+}
+
+// just the position unless it's partway into an excercise, like before
+// there was the offset
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Resume {
+    At((u16, u16, u16)),
+    Into((u16, u16, u16), f64),
+}
+
+fn load_resumes() -> Result<BTreeMap<String, Resume>> {
+    let path = resume_path()?;
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn resume_key(file: &str, day: Option<usize>) -> Result<String> {
+    let mut key = fs::canonicalize(file)?.to_string_lossy().into_owned();
+    if let Some(day) = day {
+        key.push_str(&format!("#{}", day + 1));
+    }
+    Ok(key)
+}
+
+/// Where `file` was left off and how far into that excercise, `None`
+/// forgets it.
+pub fn save_resume(
+    file: &str,
+    day: Option<usize>,
+    from: Option<(u16, u16, u16)>,
+    offset: Option<Duration>,
+) -> Result<()> {
+    let mut resumes = load_resumes()?;
+    let key = resume_key(file, day)?;
+    match (from, offset) {
+        (Some(from), Some(offset)) => resumes.insert(key, Resume::Into(from, offset.as_secs_f64())),
+        (Some(from), None) => resumes.insert(key, Resume::At(from)),
+        (None, _) => resumes.remove(&key),
+    };
+
+    let path = resume_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&resumes)?)?;
+    Ok(())
+}
+
+/// A `--from` position and how far into that excercise.
+pub type ResumeAt = ((u16, u16, u16), Option<Duration>);
+
+pub fn load_resume(file: &str, day: Option<usize>) -> Result<Option<ResumeAt>> {
+    Ok(load_resumes()?
+        .remove(&resume_key(file, day)?)
+        .map(|r| match r {
+            Resume::At(from) => (from, None),
+            Resume::Into(from, offset) => (from, Some(Duration::from_secs_f64(offset))),
+        }))
+}
+
+// the session going on, kept at every step until it's in the history, in
+// the log's lines
+fn running_path() -> Result<PathBuf> {
+    Ok(user_dir(state_dir()?).join("running.jsonl"))
+}
+
+// where it was kept whole before
+fn old_running_path() -> Result<PathBuf> {
+    Ok(user_dir(state_dir()?).join("running.json"))
+}
+
+/// How far the session going on got, a line after its new steps.
+#[derive(Serialize, Deserialize)]
+struct RunningAt {
+    end: DateTime<Local>,
+    #[serde(default)]
+    resume_from: Option<(u16, u16, u16)>,
+}
+
+/// Keeps the session going on, so it isn't lost if it never gets to the end
+/// (the terminal was closed, the power went out): its line when it starts,
+/// then at every step only the steps logged since and how far it got. Not
+/// with an encrypted history, which would be left around in plain text.
+pub struct Running {
+    out: File,
+    start: DateTime<Local>,
+    kept: usize,
+}
+impl Running {
+    pub fn start(record: &SessionRecord) -> Result<Option<Self>> {
+        if Backend::current()? == Backend::Age {
+            return Ok(None);
+        }
+        let path = running_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = HistoryWriter::new(File::create(path)?);
+        writer.append(record)?;
+        Ok(Some(Self {
+            out: writer.out,
+            start: record.start,
+            kept: record.steps.len(),
+        }))
+    }
+
+    /// The steps logged so far, the ones already kept are left alone (reps
+    /// typed in for those only make it into the history).
+    pub fn keep(
+        &mut self,
+        steps: &[StepRecord],
+        resume_from: Option<(u16, u16, u16)>,
+    ) -> Result<()> {
+        let mut lines = String::new();
+        for step in steps.get(self.kept..).unwrap_or_default() {
+            lines.push_str(&step_line(step, self.start)?);
+        }
+        let at = RunningAt {
+            end: Local::now(),
+            resume_from,
+        };
+        lines.push_str(&record_line(&at, "at")?);
+        self.out.write_all(lines.as_bytes())?;
+        self.kept = steps.len();
+        Ok(())
+    }
+}
+
+/// A session kept with [`Running`] that never got to the end.
+pub fn running() -> Result<Option<SessionRecord>> {
+    let old = old_running_path()?;
+    if old.exists() {
+        return Ok(Some(serde_json::from_str(&fs::read_to_string(old)?)?));
+    }
+    let path = running_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut record: Option<SessionRecord> = None;
+    // a line cut off by the power going out is left out
+    for line in fs::read_to_string(path)?.lines() {
+        let Ok(value) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        match (value.get("record").and_then(Value::as_str), &mut record) {
+            (Some("session"), _) => record = Some(serde_json::from_value(value)?),
+            (Some("step"), Some(record)) => record.steps.push(serde_json::from_value(value)?),
+            (Some("at"), Some(record)) => {
+                let at: RunningAt = serde_json::from_value(value)?;
+                record.end = at.end;
+                record.resume_from = at.resume_from;
+            }
+            _ => {}
+        }
+    }
+    Ok(record)
+}
+
+/// Once it's in the history.
+pub fn clear_running() -> Result<()> {
+    for path in [running_path()?, old_running_path()?] {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+// a line of the log, `record` saying what it is
+fn record_line(value: &impl Serialize, record: &str) -> Result<String> {
+    let mut value = serde_json::to_value(value)?;
+    if let Value::Object(fields) = &mut value {
+        fields.insert("record".to_owned(), record.into());
+    }
+    let mut line = serde_json::to_string(&value)?;
+    line.push('\n');
+    Ok(line)
+}
+
+fn step_line(step: &StepRecord, session: DateTime<Local>) -> Result<String> {
+    let mut value = serde_json::to_value(step)?;
+    if let Value::Object(fields) = &mut value {
+        fields.insert("session".to_owned(), serde_json::to_value(session)?);
+    }
+    record_line(&value, "step")
+}
+
+/// `history.jsonl`: a `{"record": "session", ...}` line for every session,
+/// followed by a `{"record": "step", "session": START, ...}` line for every
+/// step it went through.
+pub fn log_path() -> Result<PathBuf> {
+    Ok(user_dir(data_dir()?).join("history.jsonl"))
+}
+
+/// Appends sessions to a JSONL log.
+pub struct HistoryWriter<W: Write> {
+    out: W,
+}
+impl HistoryWriter<File> {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let out = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { out })
+    }
+}
+impl<W: Write> HistoryWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    pub fn append(&mut self, record: &SessionRecord) -> Result<()> {
+        let mut session = serde_json::to_value(record)?;
+        if let Value::Object(fields) = &mut session {
+            fields.remove("steps");
+        }
+        let mut lines = record_line(&session, "session")?;
+        for step in &record.steps {
+            lines.push_str(&step_line(step, record.start)?);
+        }
+
+        // in one go, so a crash doesn't leave half a session behind
+        self.out.write_all(lines.as_bytes())?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads the sessions of a JSONL log back, with their steps.
+pub struct HistoryReader<R: BufRead> {
+    lines: Lines<R>,
+    line: usize,
+    current: Option<SessionRecord>,
+}
+impl HistoryReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+impl<R: BufRead> HistoryReader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            lines: input.lines(),
+            line: 0,
+            current: None,
+        }
+    }
+
+    fn read_line(&mut self, line: &str) -> Result<Option<SessionRecord>> {
+        let value: Value = serde_json::from_str(line)?;
+        match value.get("record").and_then(Value::as_str) {
+            Some("session") => Ok(self.current.replace(serde_json::from_value(value)?)),
+            Some("step") => {
+                let Some(session) = &mut self.current else {
+                    return Err(anyhow::Error::msg("Step before any session"));
+                };
+                session.steps.push(serde_json::from_value(value)?);
+                Ok(None)
+            }
+            _ => Err(anyhow::Error::msg(
+                "Expected a `record` of `session` or `step`",
+            )),
+        }
+    }
+}
+impl<R: BufRead> Iterator for HistoryReader<R> {
+    type Item = Result<SessionRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => return Some(Err(e.into())),
+                None => return self.current.take().map(Ok),
+            };
+            self.line += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match self.read_line(&line) {
+                Ok(Some(session)) => return Some(Ok(session)),
+                Ok(None) => {}
+                Err(e) => {
+                    return Some(Err(anyhow::Error::msg(format!(
+                        "Line {} of the history: {e}",
+                        self.line
+                    ))))
+                }
+            }
+        }
+    }
+}
+
+/// `history.db`: a `sessions` row for every session and a `steps` row for
+/// every step it went through, as the same JSON as in the log. Sessions are
+/// keyed by their start, like the steps of the log.
+pub fn db_path() -> Result<PathBuf> {
+    Ok(user_dir(data_dir()?).join("history.db"))
+}
+
+pub struct HistoryDb {
+    conn: Connection,
+}
+impl HistoryDb {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                start TEXT PRIMARY KEY,
+                workout TEXT NOT NULL,
+                completed INTEGER NOT NULL,
+                session TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS steps (
+                session TEXT NOT NULL REFERENCES sessions(start),
+                position INTEGER NOT NULL,
+                step TEXT NOT NULL,
+                PRIMARY KEY (session, position)
+            );",
+        )?;
+        Ok(Self { conn })
+    }
+
+    pub fn append(&mut self, record: &SessionRecord) -> Result<()> {
+        let mut session = serde_json::to_value(record)?;
+        if let Value::Object(fields) = &mut session {
+            fields.remove("steps");
+        }
+        let Value::String(start) = serde_json::to_value(record.start)? else {
+            return Err(anyhow::Error::msg("Session start isn't a string"));
+        };
+
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO sessions (start, workout, completed, session) VALUES (?1, ?2, ?3, ?4)",
+            params![start, record.workout, record.completed, session.to_string()],
+        )?;
+        for (position, step) in record.steps.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO steps (session, position, step) VALUES (?1, ?2, ?3)",
+                params![start, position as i64, serde_json::to_string(step)?],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Every session, oldest first.
+    pub fn load(&self) -> Result<Vec<SessionRecord>> {
+        let mut sessions = self
+            .conn
+            .prepare("SELECT start, session FROM sessions ORDER BY rowid")?;
+        let mut steps = self
+            .conn
+            .prepare("SELECT step FROM steps WHERE session = ?1 ORDER BY position")?;
+
+        let mut records = vec![];
+        let rows = sessions.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (start, session): (String, String) = row?;
+            let mut record: SessionRecord = serde_json::from_str(&session)?;
+            for step in steps.query_map([&start], |row| row.get::<_, String>(0))? {
+                record.steps.push(serde_json::from_str(&step?)?);
+            }
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+/// `history.jsonl.age`: the log encrypted with a passphrase.
+pub fn encrypted_log_path() -> Result<PathBuf> {
+    Ok(user_dir(data_dir()?).join("history.jsonl.age"))
+}
+
+// asked for once per run, unless it's in WORKOUT_PASSPHRASE
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+fn passphrase(new: bool) -> Result<SecretString> {
+    if let Some(passphrase) = PASSPHRASE.get() {
+        return Ok(passphrase.clone().into());
+    }
+    let passphrase = match env::var("WORKOUT_PASSPHRASE") {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let passphrase = rpassword::prompt_password("History passphrase: ")?;
+            if new && rpassword::prompt_password("Again: ")? != passphrase {
+                return Err(anyhow::Error::msg("The passphrases don't match"));
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        return Err(anyhow::Error::msg("Empty passphrase"));
+    }
+    Ok(PASSPHRASE.get_or_init(|| passphrase).clone().into())
+}
+
+fn decrypt(path: &Path) -> Result<Vec<u8>> {
+    let identity = age::scrypt::Identity::new(passphrase(false)?);
+    age::decrypt(&identity, &fs::read(path)?)
+        .map_err(|e| anyhow::Error::msg(format!("Couldn't decrypt {}: {e}", path.display())))
+}
+
+/// Where sessions are saved: the JSONL log, the SQLite database or the
+/// encrypted log, once the history has been migrated to one of those.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Jsonl,
+    Sqlite,
+    Age,
+}
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "jsonl" => Ok(Self::Jsonl),
+            "sqlite" => Ok(Self::Sqlite),
+            "age" => Ok(Self::Age),
+            _ => Err(anyhow::Error::msg(format!(
+                "Unknown history backend {s} (jsonl, sqlite or age)"
+            ))),
+        }
+    }
+}
+impl Backend {
+    pub fn current() -> Result<Self> {
+        Ok(if db_path()?.exists() {
+            Self::Sqlite
+        } else if encrypted_log_path()?.exists() {
+            Self::Age
+        } else {
+            Self::Jsonl
+        })
+    }
+
+    pub fn path(self) -> Result<PathBuf> {
+        match self {
+            Self::Jsonl => log_path(),
+            Self::Sqlite => db_path(),
+            Self::Age => encrypted_log_path(),
+        }
+    }
+
+    fn load(self) -> Result<Vec<SessionRecord>> {
+        let path = self.path()?;
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        match self {
+            Self::Jsonl => HistoryReader::open(&path)?.collect(),
+            Self::Sqlite => HistoryDb::open(&path)?.load(),
+            Self::Age => HistoryReader::new(&decrypt(&path)?[..]).collect(),
+        }
+    }
+
+    fn append(self, records: &[SessionRecord]) -> Result<()> {
+        let path = self.path()?;
+        match self {
+            Self::Jsonl => {
+                let mut writer = HistoryWriter::open(&path)?;
+                for record in records {
+                    writer.append(record)?;
+                }
+            }
+            Self::Sqlite => {
+                let mut db = HistoryDb::open(&path)?;
+                for record in records {
+                    db.append(record)?;
+                }
+            }
+            Self::Age => {
+                let exists = path.exists();
+                let mut log = if exists { decrypt(&path)? } else { vec![] };
+                let mut writer = HistoryWriter::new(&mut log);
+                for record in records {
+                    writer.append(record)?;
+                }
+
+                let recipient = age::scrypt::Recipient::new(passphrase(!exists)?);
+                let encrypted = age::encrypt(&recipient, &log)?;
+                // rewritten as a whole, so not in place
+                let temp = path.with_extension("age.tmp");
+                fs::write(&temp, encrypted)?;
+                fs::rename(temp, &path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Moves every session from one backend to the other, checking they all
+/// read back the same. The old file is kept with a `.bak` extension added.
+/// Returns the number of sessions and the backup's path.
+pub fn migrate(from: Backend, to: Backend) -> Result<(usize, PathBuf)> {
+    if from == to {
+        return Err(anyhow::Error::msg("Nothing to migrate"));
+    }
+    let (source, target) = (from.path()?, to.path()?);
+    if !source.exists() {
+        return Err(anyhow::Error::msg(format!(
+            "There's no {}",
+            source.display()
+        )));
+    }
+    if target.exists() {
+        return Err(anyhow::Error::msg(format!(
+            "{} already exists",
+            target.display()
+        )));
+    }
+
+    let records = from.load()?;
+    to.append(&records)?;
+    let written = to.load()?;
+    let same = written.len() == records.len()
+        && records
+            .iter()
+            .zip(&written)
+            .all(|(a, b)| serde_json::to_value(a).ok() == serde_json::to_value(b).ok());
+    if !same {
+        fs::remove_file(&target)?;
+        return Err(anyhow::Error::msg(format!(
+            "{} didn't read back the same, left the history as it was",
+            target.display()
+        )));
+    }
+
+    let mut backup = source.clone().into_os_string();
+    backup.push(".bak");
+    fs::rename(&source, &backup)?;
+    Ok((records.len(), backup.into()))
+}
+
+pub fn save(record: &SessionRecord) -> Result<PathBuf> {
+    let backend = Backend::current()?;
+    backend.append(std::slice::from_ref(record))?;
+    backend.path()
+}
+
+pub fn load_all() -> Result<Vec<SessionRecord>> {
+    let mut records = Backend::Jsonl.load()?;
+    records.extend(Backend::Sqlite.load()?);
+    records.extend(Backend::Age.load()?);
+
+    // one file per session, from before the log (and users)
+    let dir = history_dir()?;
+    if user().is_none() && dir.exists() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                records.push(serde_json::from_str(&fs::read_to_string(path)?)?);
+            }
+        }
+    }
+
+    records.sort_by_key(|r: &SessionRecord| r.start);
+    Ok(records)
+}
+
+pub fn last_session(workout: &str) -> Result<Option<SessionRecord>> {
+    Ok(load_all()?
+        .into_iter()
+        .rev()
+        .find(|r| r.workout == workout && !r.steps.is_empty()))
+}
@@ -0,0 +1,418 @@
+use std::{fmt::Write, time::Duration};
+
+use anyhow::Result;
+
+use crate::{
+    locale,
+    session::{schedule, StepKind},
+    workout::{format_duration, BeepLevel, ExcerciseAmout, Sound, Workout, WorkoutSetElement},
+};
+
+pub(crate) fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+const CARD_STYLE: &str = "
+@page { size: A4; margin: 12mm; }
+body { font-family: sans-serif; margin: 0 auto; max-width: 180mm; color: #111; }
+h1 { font-size: 20pt; margin: 0 0 2mm; }
+.total { font-size: 12pt; color: #444; margin-bottom: 6mm; }
+.set { border: 1px solid #999; border-radius: 2mm; padding: 3mm 4mm; margin-bottom: 4mm; break-inside: avoid; }
+.set h2 { font-size: 14pt; margin: 0 0 2mm; }
+table { width: 100%; border-collapse: collapse; font-size: 11pt; }
+td { padding: 1mm 0; border-bottom: 1px dotted #ccc; }
+td.amount { text-align: right; white-space: nowrap; font-variant-numeric: tabular-nums; }
+tr.rest td { color: #666; font-style: italic; }
+.details { font-size: 9pt; color: #555; }
+.set-rest { margin-top: 2mm; color: #666; font-size: 10pt; }
+";
+
+// nested parts are indented under their circuit
+fn card_rows(out: &mut String, parts: &[WorkoutSetElement], depth: usize) {
+    let pad = if depth > 0 {
+        format!(" style=\"padding-left: {}mm\"", depth * 5)
+    } else {
+        String::new()
+    };
+
+    for p in parts {
+        match p {
+            WorkoutSetElement::Excercise {
+                name,
+                amount,
+                options,
+            } => {
+                if let Some(prep) = options.prep {
+                    let _ = writeln!(
+                        out,
+                        "<tr class=\"rest\"><td{pad}>Prep</td><td class=\"amount\">{}</td></tr>",
+                        format_duration(prep)
+                    );
+                }
+                let amount = match amount {
+                    ExcerciseAmout::Time { duration, midbeep } => {
+                        let mut s = format_duration(*duration);
+                        if *midbeep {
+                            s.push_str(" (switch halfway)");
+                        }
+                        s
+                    }
+                    ExcerciseAmout::Reps(r) => format!("&times;{r}"),
+                    ExcerciseAmout::Failure => "to failure".to_owned(),
+                    ExcerciseAmout::Calories(c) => format!("{c} cal"),
+                    ExcerciseAmout::Meters(m) => format!("{m} m"),
+                };
+                let optional = if options.optional { " (optional)" } else { "" };
+                let details = &options.details;
+                let mut notes: Vec<_> = [
+                    details.weight.map(|w| w.to_string()),
+                    details.tempo.as_ref().map(|t| format!("tempo {t}")),
+                    details.equipment.clone(),
+                    details.note.clone(),
+                ]
+                .into_iter()
+                .flatten()
+                .map(|n| escape(&n))
+                .collect();
+                if let Some(demo) = &details.demo {
+                    notes.push(format!("<a href=\"{}\">demo</a>", escape(demo)));
+                }
+                let notes = if notes.is_empty() {
+                    String::new()
+                } else {
+                    format!("<div class=\"details\">{}</div>", notes.join(" &middot; "))
+                };
+                let _ = writeln!(
+                    out,
+                    "<tr><td{pad}>{}{optional}{notes}</td><td class=\"amount\">{amount}</td></tr>",
+                    escape(name)
+                );
+            }
+            WorkoutSetElement::Rest { duration } => {
+                let _ = writeln!(
+                    out,
+                    "<tr class=\"rest\"><td{pad}>Rest</td><td class=\"amount\">{}</td></tr>",
+                    format_duration(*duration)
+                );
+            }
+            WorkoutSetElement::Intervals {
+                name,
+                rounds,
+                work,
+                rest,
+            } => {
+                let _ = writeln!(
+                    out,
+                    "<tr><td{pad}>{}</td><td class=\"amount\">{rounds} &times; ({} / {} rest)</td></tr>",
+                    escape(name),
+                    format_duration(*work),
+                    format_duration(*rest)
+                );
+            }
+            WorkoutSetElement::Circuit { reps, parts, rest } => {
+                let rest = rest.map(|rest| format!(", {rest}")).unwrap_or_default();
+                let _ = writeln!(
+                    out,
+                    "<tr><td{pad}><b>Circuit</b></td><td class=\"amount\">&times;{reps}{rest}</td></tr>"
+                );
+                card_rows(out, parts, depth + 1);
+            }
+        }
+    }
+}
+
+pub fn card(workout: &Workout) -> String {
+    let mut out = String::new();
+    let name = escape(&workout.name);
+
+    // writing to a String can't fail
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{name}</title>\n<style>{CARD_STYLE}</style>\n</head>\n<body>\n"
+    );
+    let _ = writeln!(out, "<h1>{name}</h1>");
+    let _ = writeln!(
+        out,
+        "<div class=\"total\">Total: ~{}</div>",
+        locale::minutes(workout.length())
+    );
+
+    for set in &workout.sections {
+        let _ = writeln!(out, "<div class=\"set\">");
+        let _ = write!(out, "<h2>{}", escape(set.name.as_deref().unwrap_or("Set")));
+        if set.reps > 1 {
+            let _ = write!(out, " &times;{}", set.reps);
+        }
+        let _ = writeln!(out, "</h2>\n<table>");
+
+        card_rows(&mut out, &set.parts, 0);
+
+        let _ = writeln!(out, "</table>");
+        if let (Some(rest), true) = (set.set_rest, set.reps > 1) {
+            let _ = writeln!(
+                out,
+                "<div class=\"set-rest\">Rest {} between rounds</div>",
+                format_duration(rest)
+            );
+        }
+        if let Some(rest) = set.rest_after {
+            let _ = writeln!(
+                out,
+                "<div class=\"set-rest\">Rest {} before the next set</div>",
+                format_duration(rest)
+            );
+        }
+        let _ = writeln!(out, "</div>");
+    }
+
+    let _ = writeln!(out, "</body>\n</html>");
+    out
+}
+
+fn js_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '<' => out.push_str("\\u003c"),
+            '>' => out.push_str("\\u003e"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct TimerStep {
+    label: String,
+    excercise: bool,
+    duration: Option<Duration>,
+    cues: Vec<(Duration, BeepLevel)>,
+}
+
+// same steps and cues a session goes through, so the page beeps the same way
+fn page_level(sound: &Sound) -> Option<BeepLevel> {
+    match sound {
+        Sound::Beep(level) | Sound::Relax(level) => Some(*level),
+        // the page can't load local files, beep instead
+        Sound::File(_) => Some(BeepLevel::High),
+        Sound::Panned(sound, _) => page_level(sound),
+        // the excercise's own beeps go with it
+        Sound::Speech(_) => None,
+        // the page has its own
+        Sound::Start | Sound::Complete => None,
+        // no clicks on the page
+        Sound::Metronome(_) | Sound::MetronomeStop | Sound::Mute(_) | Sound::Preload(_) => None,
+    }
+}
+
+fn timer_steps(workout: &Workout) -> Result<Vec<TimerStep>> {
+    let mut steps: Vec<TimerStep> = schedule(workout, (0, 0, 0))?
+        .into_iter()
+        // there's no way to answer on the page, optional excercises are just done
+        .filter(|step| !matches!(step.kind, StepKind::Optional { .. }))
+        .enumerate()
+        .map(|(i, step)| {
+            let set = &workout.sections[step.set];
+            let (label, excercise) = match &step.kind {
+                StepKind::Countdown if i == 0 => ("Get ready".to_owned(), false),
+                StepKind::Countdown if step.set_rep > 0 => (
+                    format!("Section {set} ({} / {})", step.set_rep + 1, set.reps),
+                    false,
+                ),
+                StepKind::Countdown => (format!("Section {set}"), false),
+                StepKind::Excercise { name, amount, .. } => match amount {
+                    ExcerciseAmout::Time { .. } => (name.clone(), true),
+                    amount => (format!("{name} {amount}"), true),
+                },
+                StepKind::Rest { .. } => ("Rest".to_owned(), false),
+                StepKind::Prep { name } => (format!("Get ready for {name}"), false),
+                StepKind::Optional { name } => (format!("Optional: {name}"), false),
+                StepKind::Acknowledge { name } => (format!("Done with {name}?"), false),
+                StepKind::SetRest => ("Set rest".to_owned(), false),
+            };
+
+            TimerStep {
+                label,
+                excercise,
+                duration: step.duration,
+                cues: step
+                    .cues
+                    .iter()
+                    .filter_map(|c| Some((c.at, page_level(c.sound.as_ref()?)?)))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    if let Some(first) = steps.first_mut() {
+        first.cues.splice(
+            0..0,
+            [BeepLevel::High, BeepLevel::Mid, BeepLevel::Low].map(|l| (Duration::ZERO, l)),
+        );
+    }
+    steps.push(TimerStep {
+        label: "Reached the end. Good job!".to_owned(),
+        excercise: false,
+        duration: Some(Duration::from_secs(2)),
+        cues: [BeepLevel::Low, BeepLevel::Mid, BeepLevel::High]
+            .map(|l| (Duration::from_secs(2), l))
+            .to_vec(),
+    });
+
+    Ok(steps)
+}
+
+const TIMER_STYLE: &str = "
+body { font-family: sans-serif; background: #111; color: #eee; text-align: center; margin: 0; padding: 4vh 2vw; }
+h1 { font-size: 5vw; margin: 0; color: #aaa; }
+#label { font-size: 8vw; margin: 4vh 0 2vh; min-height: 10vw; }
+#clock { font-size: 22vw; font-variant-numeric: tabular-nums; }
+#next { font-size: 4vw; color: #888; min-height: 5vw; }
+button { font-size: 5vw; margin: 3vh 1vw; padding: 1vh 4vw; border-radius: 2vw; border: none; }
+";
+
+const TIMER_SCRIPT: &str = r#"
+const $ = id => document.getElementById(id);
+let audio, beepQueue = 0;
+function beep(level) {
+    const t = Math.max(audio.currentTime, beepQueue);
+    const osc = audio.createOscillator(), gain = audio.createGain();
+    osc.frequency.value = FREQUENCIES[level];
+    gain.gain.setValueAtTime(0, t);
+    gain.gain.linearRampToValueAtTime(1, t + 0.1);
+    gain.gain.setValueAtTime(1, t + BEEP_LEN - 0.1);
+    gain.gain.linearRampToValueAtTime(0, t + BEEP_LEN);
+    osc.connect(gain).connect(audio.destination);
+    osc.start(t);
+    osc.stop(t + BEEP_LEN);
+    beepQueue = t + BEEP_LEN;
+}
+function fmt(s) {
+    s = Math.max(0, Math.ceil(s));
+    return String(Math.floor(s / 60)).padStart(2, "0") + ":" + String(s % 60).padStart(2, "0");
+}
+
+let step = -1, stepStart = 0, fired = 0, pausedAt = null;
+function enter(i, at) {
+    step = i; stepStart = at; fired = 0;
+    const s = STEPS[i];
+    $("label").textContent = s.label;
+    const next = STEPS.slice(i + 1).find(n => n.excercise);
+    $("next").textContent = next ? "next: " + next.label : "";
+    $("done").hidden = s.duration !== null;
+}
+function fire(upTo) {
+    const cues = STEPS[step].cues;
+    while (fired < cues.length && cues[fired][0] <= upTo) beep(cues[fired++][1]);
+}
+function tick() {
+    if (pausedAt !== null) return;
+    const now = performance.now() / 1000;
+    let s = STEPS[step];
+    while (s.duration !== null && now - stepStart >= s.duration) {
+        fire(s.duration);
+        if (step + 1 >= STEPS.length) {
+            $("clock").textContent = "00:00";
+            $("pause").hidden = true;
+            return;
+        }
+        enter(step + 1, stepStart + s.duration);
+        s = STEPS[step];
+    }
+    fire(now - stepStart);
+    $("clock").textContent = s.duration === null ? fmt(now - stepStart) : fmt(s.duration - (now - stepStart));
+    requestAnimationFrame(tick);
+}
+$("start").onclick = () => {
+    audio = new AudioContext();
+    $("start").hidden = true;
+    $("pause").hidden = false;
+    enter(0, performance.now() / 1000);
+    tick();
+};
+$("pause").onclick = () => {
+    if (pausedAt === null) {
+        pausedAt = performance.now() / 1000;
+        $("pause").textContent = "Resume";
+    } else {
+        stepStart += performance.now() / 1000 - pausedAt;
+        pausedAt = null;
+        $("pause").textContent = "Pause";
+        tick();
+    }
+};
+$("done").onclick = () => {
+    enter(step + 1, performance.now() / 1000);
+};
+document.onkeydown = e => {
+    if (e.code === "Space" && !$("done").hidden) $("done").click();
+};
+"#;
+
+pub fn timer(workout: &Workout) -> Result<String> {
+    let mut steps = String::from("[\n");
+    for s in timer_steps(workout)? {
+        let duration = s
+            .duration
+            .map_or("null".to_owned(), |d| d.as_secs_f64().to_string());
+        let cues = s
+            .cues
+            .iter()
+            .map(|(at, level)| format!("[{}, {}]", at.as_secs_f64(), *level as usize))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = writeln!(
+            steps,
+            "  {{ label: {}, excercise: {}, duration: {duration}, cues: [{cues}] }},",
+            js_string(&s.label),
+            s.excercise
+        );
+    }
+    steps.push(']');
+
+    let name = escape(&workout.name);
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{name}</title>\n<style>{TIMER_STYLE}</style>\n</head>\n<body>\n"
+    );
+    let _ = writeln!(
+        out,
+        "<h1>{name} [~{}]</h1>",
+        locale::minutes(workout.length())
+    );
+    let _ = writeln!(
+        out,
+        "<div id=\"label\"></div>\n<div id=\"clock\">00:00</div>\n<div id=\"next\"></div>"
+    );
+    let _ = writeln!(
+        out,
+        "<button id=\"start\">Start</button><button id=\"pause\" hidden>Pause</button><button id=\"done\" hidden>Done</button>"
+    );
+    let _ = writeln!(
+        out,
+        "<script>\nconst STEPS = {steps};\nconst FREQUENCIES = [{}, {}, {}];\nconst BEEP_LEN = 0.5;\n{TIMER_SCRIPT}</script>\n</body>\n</html>",
+        BeepLevel::High.get_frequency(),
+        BeepLevel::Mid.get_frequency(),
+        BeepLevel::Low.get_frequency(),
+    );
+    Ok(out)
+}
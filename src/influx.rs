@@ -0,0 +1,61 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::{record::SessionRecord, session::StepKind, stats::reps_of};
+
+// InfluxDB line protocol, e.g. for Telegraf to pick up:
+// workout_session,workout=Push,completed=true duration=1800.0,sets=5i,reps=120i 1697000000000000000
+
+fn tag(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, ',' | '=' | ' ' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// A `workout_session` line, then a `workout_excercise` line for every
+/// excercise done.
+pub fn session_lines(record: &SessionRecord) -> String {
+    let timestamp = record.start.timestamp_nanos_opt().unwrap_or_default();
+    let workout = tag(&record.workout);
+
+    let mut sets = BTreeSet::new();
+    let mut reps = 0;
+    let mut excercises: Vec<(&str, f64, u32)> = vec![];
+    for step in &record.steps {
+        let (StepKind::Excercise { name, .. }, false) = (&step.kind, step.skipped) else {
+            continue;
+        };
+        sets.insert((step.set, step.set_rep));
+        let step_reps = reps_of(step).unwrap_or(0) as u32;
+        reps += step_reps;
+
+        match excercises.iter_mut().find(|(n, _, _)| n == name) {
+            Some((_, duration, r)) => {
+                *duration += step.duration.as_secs_f64();
+                *r += step_reps;
+            }
+            None => excercises.push((name, step.duration.as_secs_f64(), step_reps)),
+        }
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "workout_session,workout={workout},completed={} duration={:.1},sets={}i,reps={reps}i {timestamp}",
+        record.completed,
+        record.duration().as_secs_f64(),
+        sets.len(),
+    );
+    for (name, duration, reps) in excercises {
+        let _ = writeln!(
+            out,
+            "workout_excercise,workout={workout},excercise={} duration={duration:.1},reps={reps}i {timestamp}",
+            tag(name),
+        );
+    }
+    out
+}
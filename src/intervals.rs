@@ -0,0 +1,169 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::{
+    config::Units,
+    workout::{
+        format_duration, parse_duration, ExcerciseAmout, ExcerciseDetails, ExcerciseOptions, Say,
+        Workout, WorkoutSet, WorkoutSetElement,
+    },
+};
+
+/// What to aim for, read out loud along the way.
+#[derive(Default)]
+pub struct Targets {
+    /// Per kilometer, or per mile in imperial units.
+    pub pace: Option<Duration>,
+    /// The heart rate zone of the hard parts, checked halfway through them.
+    pub heart_rate: Option<(u16, u16)>,
+}
+impl Targets {
+    /// `LOW-HIGH` beats per minute.
+    pub fn parse_heart_rate(s: &str) -> Result<(u16, u16)> {
+        let error =
+            || anyhow::Error::msg(format!("Expected a heart rate zone like 140-160, got: {s}"));
+        let (low, high) = s.split_once('-').ok_or_else(error)?;
+        let low: u16 = low.trim().parse().map_err(|_| error())?;
+        let high: u16 = high.trim().parse().map_err(|_| error())?;
+        if low > high {
+            return Err(error());
+        }
+        Ok((low, high))
+    }
+}
+
+// e.g. "1 minute 30", for reading out loud
+fn spoken(d: Duration) -> String {
+    match (d.as_secs() / 60, d.as_secs() % 60) {
+        (0, s) => format!("{s} seconds"),
+        (1, 0) => "1 minute".to_owned(),
+        (m, 0) => format!("{m} minutes"),
+        (1, s) => format!("1 minute {s}"),
+        (m, s) => format!("{m} minutes {s}"),
+    }
+}
+
+// `MM:SS label`, the label capitalized
+fn parse_part(s: &str) -> Option<(Duration, String)> {
+    let (duration, label) = s.trim().split_once(' ')?;
+    let mut chars = label.trim().chars();
+    let first = chars.next()?;
+    Some((
+        parse_duration(duration).ok()?,
+        first.to_uppercase().chain(chars).collect(),
+    ))
+}
+
+fn part(name: &str, duration: Duration, say: Vec<Say>) -> WorkoutSetElement {
+    WorkoutSetElement::Excercise {
+        name: name.to_owned(),
+        amount: ExcerciseAmout::Time {
+            duration,
+            midbeep: false,
+        },
+        options: ExcerciseOptions {
+            details: Box::new(ExcerciseDetails {
+                say,
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    }
+}
+
+/// `ROUNDSx(MM:SS run / MM:SS walk)` as a workout that says what's next at
+/// the start of every part, with the targets, so it can be followed with
+/// earbuds in and the screen off.
+pub fn generate(spec: &str, targets: &Targets, units: Units) -> Result<Workout> {
+    let error = || {
+        anyhow::Error::msg(format!(
+            "Expected `ROUNDSx(MM:SS run / MM:SS walk)`, got: {spec}"
+        ))
+    };
+
+    let (rounds, body) = spec.split_once('x').ok_or_else(error)?;
+    let rounds: u16 = rounds.trim().parse().map_err(|_| error())?;
+    let (hard, easy) = body
+        .trim()
+        .strip_prefix('(')
+        .and_then(|b| b.strip_suffix(')'))
+        .and_then(|b| b.split_once('/'))
+        .ok_or_else(error)?;
+    let ((hard, hard_name), (easy, easy_name)) =
+        parse_part(hard).zip(parse_part(easy)).ok_or_else(error)?;
+    if rounds == 0 || hard.is_zero() {
+        return Err(error());
+    }
+
+    let pace = targets.pace.map(|pace| {
+        let per = match units {
+            Units::Metric => "kilometer",
+            Units::Imperial => "mile",
+        };
+        let secs = pace.as_secs();
+        format!(", at {}:{:02} per {per}", secs / 60, secs % 60)
+    });
+
+    let mut parts = vec![];
+    for round in 1..=rounds {
+        let last = round == rounds;
+        let mut say = vec![Say {
+            at: Duration::ZERO,
+            text: format!(
+                "{}{hard_name} for {}{}",
+                if last {
+                    "Last one. ".to_owned()
+                } else {
+                    format!("Round {round} of {rounds}. ")
+                },
+                spoken(hard),
+                pace.as_deref().unwrap_or_default()
+            ),
+        }];
+        if let Some((low, high)) = targets.heart_rate {
+            say.push(Say {
+                at: hard / 2,
+                text: format!("Heart rate check, {low} to {high}"),
+            });
+        }
+        parts.push(part(&hard_name, hard, say));
+
+        if easy.is_zero() {
+            continue;
+        }
+        let mut say = vec![Say {
+            at: Duration::ZERO,
+            text: format!("{easy_name} for {}", spoken(easy)),
+        }];
+        if let (Some((low, _)), false) = (targets.heart_rate, last) {
+            say.push(Say {
+                at: easy / 2,
+                text: format!("Heart rate check, under {low} before the next one"),
+            });
+        }
+        parts.push(part(&easy_name, easy, say));
+    }
+
+    Ok(Workout {
+        name: format!(
+            "Intervals {rounds}x({} {} / {} {})",
+            format_duration(hard),
+            hard_name.to_lowercase(),
+            format_duration(easy),
+            easy_name.to_lowercase()
+        ),
+        sections: vec![WorkoutSet {
+            name: Some("Intervals".to_owned()),
+            parts,
+            reps: 1,
+            set_rest: None,
+            rest_after: None,
+            lead_in: None,
+            groups: vec![],
+        }],
+        lead_in: None,
+        defines: vec![],
+        audio: None,
+    })
+}
@@ -0,0 +1,247 @@
+use std::{
+    fmt,
+    io::{self, IsTerminal},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Mutex, Once,
+    },
+    thread,
+};
+
+// one thread reads stdin for as long as the program runs and hands it to
+// the latest `Keys`, so a key pressed between two of them isn't lost to a
+// thread nobody listens to anymore
+static SENDER: Mutex<Option<Sender<String>>> = Mutex::new(None);
+static READER: Once = Once::new();
+// stdin is done, later channels are dropped right away so they disconnect
+static ENDED: AtomicBool = AtomicBool::new(false);
+static LINES: AtomicBool = AtomicBool::new(false);
+static HOLD: AtomicBool = AtomicBool::new(false);
+
+/// The line sent for ctrl-c, after which nothing more is read: whoever's
+/// reading stops with [`Interrupted`].
+pub const INTERRUPT: &str = "\x03";
+
+/// Ctrl-c was pressed while keys were read one by one, up to the program
+/// what stopping means (the terminal's already put back).
+#[derive(Debug)]
+pub struct Interrupted;
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Interrupted")
+    }
+}
+impl std::error::Error for Interrupted {}
+
+/// How long `f` and ctrl-c have to be held down with [`hold_to_quit`].
+#[cfg(unix)]
+const HOLD_FOR: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Reads stdin into lines on a thread. On a terminal every key press goes
+/// through on its own without waiting for enter, except for numbers, which
+/// are typed out, and while `set_lines` says whole lines are wanted.
+pub struct Keys {
+    #[cfg(unix)]
+    saved: Option<rustix::termios::Termios>,
+}
+impl Keys {
+    pub fn spawn(tx: Sender<String>) -> Self {
+        LINES.store(false, Ordering::Relaxed);
+        if !ENDED.load(Ordering::Relaxed) {
+            if let Ok(mut sender) = SENDER.lock() {
+                *sender = Some(tx);
+            }
+        }
+
+        #[cfg(unix)]
+        if let Some(saved) = single_keys() {
+            let restore = saved.clone();
+            READER.call_once(|| {
+                thread::spawn(move || read_keys(&restore));
+            });
+            return Self { saved: Some(saved) };
+        }
+
+        READER.call_once(|| {
+            thread::spawn(|| {
+                for line in io::stdin().lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    send(line);
+                }
+                end();
+            });
+        });
+        Self {
+            #[cfg(unix)]
+            saved: None,
+        }
+    }
+
+    /// Whether a key press is enough.
+    pub fn single(&self) -> bool {
+        #[cfg(unix)]
+        return self.saved.is_some() && !LINES.load(Ordering::Relaxed);
+        #[cfg(not(unix))]
+        false
+    }
+
+    /// Waits for enter, for commands and answers.
+    pub fn set_lines(&self, lines: bool) {
+        LINES.store(lines, Ordering::Relaxed);
+    }
+}
+impl Drop for Keys {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(saved) = &self.saved {
+            restore(saved);
+        }
+        if let Ok(mut sender) = SENDER.lock() {
+            *sender = None;
+        }
+    }
+}
+
+/// Only lets `f` and ctrl-c through once they're held down for a while, so
+/// a stray press doesn't end anything.
+pub fn hold_to_quit(hold: bool) {
+    HOLD.store(hold, Ordering::Relaxed);
+}
+
+fn send(line: String) {
+    if let Ok(mut sender) = SENDER.lock() {
+        if sender.as_ref().is_some_and(|tx| tx.send(line).is_err()) {
+            *sender = None;
+        }
+    }
+}
+
+fn end() {
+    ENDED.store(true, Ordering::Relaxed);
+    if let Ok(mut sender) = SENDER.lock() {
+        *sender = None;
+    }
+}
+
+// turns off line buffering and echo, returns what to put back
+#[cfg(unix)]
+fn single_keys() -> Option<rustix::termios::Termios> {
+    use rustix::termios::{tcgetattr, tcsetattr, LocalModes, OptionalActions, SpecialCodeIndex};
+
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return None;
+    }
+    let saved = tcgetattr(&stdin).ok()?;
+    let mut raw = saved.clone();
+    // ctrl-c is read as a key, so the terminal is put back before exiting
+    raw.local_modes &= !(LocalModes::ICANON | LocalModes::ECHO | LocalModes::ISIG);
+    raw.special_codes[SpecialCodeIndex::VMIN] = 1;
+    raw.special_codes[SpecialCodeIndex::VTIME] = 0;
+    tcsetattr(&stdin, OptionalActions::Now, &raw).ok()?;
+    Some(saved)
+}
+
+#[cfg(unix)]
+fn restore(saved: &rustix::termios::Termios) {
+    use rustix::termios::{tcsetattr, OptionalActions};
+
+    let _ = tcsetattr(io::stdin(), OptionalActions::Now, saved);
+}
+
+#[cfg(unix)]
+fn read_keys(saved: &rustix::termios::Termios) {
+    use std::{
+        io::{Read, Write},
+        time::{Duration, Instant},
+    };
+
+    let echo = |s: &str| {
+        print!("{s}");
+        let _ = io::stdout().flush();
+    };
+
+    let mut typed = String::new();
+    // skipping an escape sequence (arrow keys and such)
+    let mut escape = false;
+    // the key being held down, since when and when it last repeated
+    let mut held: Option<(u8, Instant, Instant)> = None;
+    for byte in io::stdin().lock().bytes() {
+        let Ok(byte) = byte else {
+            break;
+        };
+        if escape {
+            escape = matches!(byte, b'[' | b'O') || !(0x40..=0x7e).contains(&byte);
+            continue;
+        }
+
+        let lines = LINES.load(Ordering::Relaxed);
+        if matches!(byte, 0x03 | b'f') && typed.is_empty() && !lines && HOLD.load(Ordering::Relaxed)
+        {
+            // the key repeating while it's down
+            let now = Instant::now();
+            let since = match held {
+                Some((key, since, last)) if key == byte && now - last < Duration::from_secs(1) => {
+                    since
+                }
+                _ => {
+                    echo(match byte {
+                        0x03 => "\n    (hold ctrl-c to quit)\n",
+                        _ => "\n    (hold f to finish early)\n",
+                    });
+                    now
+                }
+            };
+            held = Some((byte, since, now));
+            if now - since < HOLD_FOR {
+                continue;
+            }
+            held = None;
+        }
+
+        let line = match byte {
+            // ctrl-c
+            0x03 => {
+                restore(saved);
+                echo("\n");
+                send(INTERRUPT.to_owned());
+                break;
+            }
+            // ctrl-d
+            0x04 if typed.is_empty() => break,
+            0x1b => {
+                escape = true;
+                continue;
+            }
+            b'\n' | b'\r' => {
+                echo("\n");
+                std::mem::take(&mut typed)
+            }
+            0x7f | 0x08 => {
+                if typed.pop().is_some() {
+                    echo("\x08 \x08");
+                }
+                continue;
+            }
+            // the rest of a multibyte character
+            0x80..=0xbf => continue,
+            _ if byte.is_ascii_control() => continue,
+            _ => {
+                let key = if byte.is_ascii() { byte as char } else { '?' };
+                if typed.is_empty() && !key.is_ascii_digit() && !lines {
+                    echo(&format!("{key}\n"));
+                    key.to_string()
+                } else {
+                    echo(&key.to_string());
+                    typed.push(key);
+                    continue;
+                }
+            }
+        };
+        send(line);
+    }
+    end();
+}
@@ -0,0 +1,139 @@
+use std::{
+    cell::RefCell,
+    env,
+    io::{self, Write},
+};
+
+use crate::font::glyph;
+
+/// The terminal turned into a big display: what's going on and the time in
+/// huge letters at the top, the usual lines scrolling underneath, for a
+/// screen across the room.
+pub struct Screen {
+    columns: usize,
+    /// Of the big letters at the top.
+    rows: usize,
+    shown: RefCell<(String, String)>,
+}
+impl Screen {
+    pub fn open() -> Self {
+        let (columns, lines) = size();
+        // a third is left for the lines scrolling underneath
+        let rows = (lines * 2 / 3).max(4).min(lines.saturating_sub(2));
+        let mut out = io::stdout().lock();
+        // clears it, and keeps the scrolling below the big letters
+        let _ = write!(out, "\x1b[2J\x1b[{};{lines}r\x1b[{lines};1H", rows + 1);
+        let _ = out.flush();
+        Self {
+            columns,
+            rows,
+            shown: RefCell::new((String::new(), String::new())),
+        }
+    }
+
+    /// Draws the label over the time, if they changed.
+    pub fn show(&self, label: &str, time: &str) {
+        let mut shown = self.shown.borrow_mut();
+        if shown.0 == label && shown.1 == time {
+            return;
+        }
+        *shown = (label.to_owned(), time.to_owned());
+
+        // the label as big as fits in a row of letters, at most twice the
+        // font's size, the time as big as fits in what's left
+        let label_scale = scale(label, self.columns, usize::MAX).min(2);
+        let label_lines = lines(label_scale);
+        let time_scale = scale(
+            time,
+            self.columns,
+            self.rows.saturating_sub(label_lines + 1),
+        );
+        let mut text = render(label, label_scale, self.columns);
+        text.push(String::new());
+        text.extend(render(time, time_scale, self.columns));
+
+        let mut out = io::stdout().lock();
+        // back to where the scrolling lines were after
+        let _ = write!(out, "\x1b7");
+        for row in 0..self.rows {
+            let line = text.get(row).map_or("", String::as_str);
+            let _ = write!(out, "\x1b[{};1H\x1b[2K{line}", row + 1);
+        }
+        let _ = write!(out, "\x1b8");
+        let _ = out.flush();
+    }
+}
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = write!(io::stdout(), "\x1b[r\x1b[2J\x1b[H");
+        let _ = io::stdout().flush();
+    }
+}
+
+// columns and lines of the terminal
+fn size() -> (usize, usize) {
+    #[cfg(unix)]
+    if let Ok(size) = rustix::termios::tcgetwinsize(io::stdout()) {
+        if size.ws_col > 0 && size.ws_row > 0 {
+            return (size.ws_col as usize, size.ws_row as usize);
+        }
+    }
+    let var = |name: &str, default: usize| {
+        env::var(name)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    };
+    (var("COLUMNS", 80), var("LINES", 24))
+}
+
+// lines of text the font takes at `scale`, two pixels to a line
+fn lines(scale: usize) -> usize {
+    (7 * scale).div_ceil(2)
+}
+
+// the biggest the text fits in at, 1 if it doesn't
+fn scale(text: &str, columns: usize, rows: usize) -> usize {
+    let width = 6 * text.chars().count().max(1);
+    let mut scale = (columns / width).max(1);
+    while scale > 1 && lines(scale) > rows {
+        scale -= 1;
+    }
+    scale
+}
+
+// centered, with half blocks for two rows of pixels in every line
+fn render(text: &str, scale: usize, columns: usize) -> Vec<String> {
+    let advance = 6 * scale;
+    let text: Vec<char> = text
+        .to_uppercase()
+        .chars()
+        .take(columns / advance)
+        .collect();
+    let width = (advance * text.len()).saturating_sub(scale);
+    let left = columns.saturating_sub(width) / 2;
+
+    let pixel = |x: usize, y: usize| {
+        let (x, y) = (x.checked_sub(left)? / scale, y / scale);
+        let (c, col) = (text.get(x / 6)?, x % 6);
+        let row = *glyph(*c).get(y)?;
+        Some(col < 5 && row & (0b10000 >> col) != 0)
+    };
+    (0..lines(scale))
+        .map(|line| {
+            let row: String = (0..left + width)
+                .map(|x| {
+                    let top = pixel(x, 2 * line).unwrap_or(false);
+                    let bottom = pixel(x, 2 * line + 1).unwrap_or(false);
+                    match (top, bottom) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    }
+                })
+                .collect();
+            row.trim_end().to_owned()
+        })
+        .collect()
+}
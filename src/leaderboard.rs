@@ -0,0 +1,99 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{record::SessionRecord, workout::format_duration};
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    #[serde(with = "crate::record::secs")]
+    pub fastest: Duration,
+    pub date: DateTime<Local>,
+    pub completions: u32,
+}
+
+/// Best times per profile for one workout file, kept next to it.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Leaderboard {
+    pub entries: BTreeMap<String, Entry>,
+}
+impl Leaderboard {
+    pub fn path(workout_file: &str) -> PathBuf {
+        Path::new(workout_file).with_extension("leaderboard.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn ranking(&self) -> Vec<(&str, &Entry)> {
+        let mut ranking: Vec<_> = self.entries.iter().map(|(n, e)| (n.as_str(), e)).collect();
+        ranking.sort_by_key(|(_, e)| e.fastest);
+        ranking
+    }
+
+    /// Returns whether it's a new personal best.
+    pub fn record(&mut self, profile: &str, session: &SessionRecord) -> bool {
+        if !session.completed {
+            return false;
+        }
+
+        let time = session.duration();
+        match self.entries.get_mut(profile) {
+            Some(entry) => {
+                entry.completions += 1;
+                if time < entry.fastest {
+                    entry.fastest = time;
+                    entry.date = session.start;
+                    true
+                } else {
+                    false
+                }
+            }
+            None => {
+                self.entries.insert(
+                    profile.to_owned(),
+                    Entry {
+                        fastest: time,
+                        date: session.start,
+                        completions: 1,
+                    },
+                );
+                true
+            }
+        }
+    }
+
+    pub fn print(&self) {
+        if self.entries.is_empty() {
+            println!("Leaderboard is empty, be the first!");
+            return;
+        }
+
+        println!("Leaderboard:");
+        for (i, (name, entry)) in self.ranking().into_iter().enumerate() {
+            println!(
+                "  {}. {name} {} ({}, {} completions)",
+                i + 1,
+                format_duration(entry.fastest),
+                entry.date.format("%Y-%m-%d"),
+                entry.completions
+            );
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Parses workouts and runs them with a countdown and beeps, what the
+//! `workout` command is built on. [`Workout::parse`] reads one,
+//! [`Workout::run`] runs it in the terminal.
+
+pub mod audio;
+pub mod backup;
+pub mod builtin;
+pub mod captions;
+pub mod coach;
+pub mod config;
+pub mod dirs;
+pub mod fit;
+pub mod font;
+pub mod frontend;
+#[cfg(feature = "garmin")]
+pub mod garmin;
+pub mod health;
+pub mod history;
+pub mod html;
+pub mod influx;
+pub mod intervals;
+pub mod keys;
+pub mod kiosk;
+pub mod leaderboard;
+pub mod locale;
+pub mod markdown;
+pub mod measured;
+pub mod motivation;
+#[cfg(feature = "overlay")]
+pub mod overlay;
+pub mod preprocess;
+pub mod presets;
+pub mod program;
+pub mod progression;
+pub mod record;
+pub mod recovery;
+pub mod session;
+pub mod stats;
+pub mod toml_format;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod workout;
+
+pub use workout::{RunOptions, Workout};
@@ -0,0 +1,135 @@
+use std::{env, sync::OnceLock, time::Duration};
+
+use chrono::{DateTime, Local};
+
+use crate::workout::Weight;
+
+// set from the config, the environment's until then
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+/// How numbers and times of day are written for whoever reads them. Files,
+/// logs and exports for other programs are left alone.
+#[derive(Clone, Copy, Default)]
+pub struct Locale {
+    /// `2,5` instead of `2.5`.
+    pub decimal_comma: bool,
+    /// `6:45 PM` instead of `18:45`.
+    pub twelve_hour: bool,
+}
+impl Locale {
+    /// From a tag like `de_DE`, `en-US` or `fr_FR.UTF-8`.
+    pub fn parse(tag: &str) -> Self {
+        Self {
+            decimal_comma: decimal_comma(tag),
+            twelve_hour: twelve_hour(tag),
+        }
+    }
+
+    /// What `LC_ALL`, `LC_NUMERIC` / `LC_TIME` or `LANG` say.
+    pub fn from_env() -> Self {
+        let tag = |category: &str| {
+            ["LC_ALL", category, "LANG"]
+                .into_iter()
+                .find_map(|name| env::var(name).ok().filter(|v| !v.is_empty()))
+                .unwrap_or_default()
+        };
+        Self {
+            decimal_comma: decimal_comma(&tag("LC_NUMERIC")),
+            twelve_hour: twelve_hour(&tag("LC_TIME")),
+        }
+    }
+}
+
+// the language and the region of `de_DE.UTF-8`
+fn split(tag: &str) -> (String, String) {
+    let tag = tag.split(['.', '@']).next().unwrap_or_default();
+    let (language, region) = tag.split_once(['_', '-']).unwrap_or((tag, ""));
+    (language.to_lowercase(), region.to_uppercase())
+}
+
+fn decimal_comma(tag: &str) -> bool {
+    let (language, region) = split(tag);
+    // the Swiss write a point
+    if region == "CH" {
+        return false;
+    }
+    matches!(
+        language.as_str(),
+        "bg" | "cs"
+            | "da"
+            | "de"
+            | "el"
+            | "es"
+            | "et"
+            | "fi"
+            | "fr"
+            | "hr"
+            | "hu"
+            | "id"
+            | "it"
+            | "lt"
+            | "lv"
+            | "nb"
+            | "nl"
+            | "nn"
+            | "pl"
+            | "pt"
+            | "ro"
+            | "ru"
+            | "sk"
+            | "sl"
+            | "sv"
+            | "tr"
+            | "uk"
+            | "vi"
+    )
+}
+
+fn twelve_hour(tag: &str) -> bool {
+    let (language, region) = split(tag);
+    language == "en" && matches!(region.as_str(), "US" | "CA" | "AU" | "NZ" | "IN" | "PH")
+}
+
+/// For the rest of the run, the first one set stays.
+pub fn set(locale: Locale) {
+    let _ = LOCALE.set(locale);
+}
+
+pub fn current() -> Locale {
+    *LOCALE.get_or_init(Locale::from_env)
+}
+
+fn localize(s: String) -> String {
+    match current().decimal_comma {
+        true => s.replace('.', ","),
+        false => s,
+    }
+}
+
+/// With `places` decimals.
+pub fn decimal(value: f64, places: usize) -> String {
+    localize(format!("{value:.places$}"))
+}
+
+/// With as many decimals as it has, like an RPE of `7.5`.
+pub fn number(value: f32) -> String {
+    localize(value.to_string())
+}
+
+/// Like `32.5 mins`.
+pub fn minutes(d: Duration) -> String {
+    format!("{} mins", decimal(d.as_secs_f64() / 60., 1))
+}
+
+/// Like `62.5kg`.
+pub fn weight(weight: &Weight) -> String {
+    localize(weight.to_string())
+}
+
+/// Like `18:45` or `6:45 PM`.
+pub fn time_of_day(time: DateTime<Local>) -> String {
+    match current().twelve_hour {
+        true => time.format("%-I:%M %p").to_string(),
+        false => time.format("%H:%M").to_string(),
+    }
+}
@@ -1,79 +1,1255 @@
-pub mod workout;
+mod cli;
 
 use anyhow::Result;
-use rodio::{
-    queue::queue,
-    source::{SineWave, Source, Zero},
-    OutputStream,
+use chrono::{Datelike, Local, NaiveDate};
+use cli::{Cli, Command, RunArgs};
+use std::{
+    cell::RefCell,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, IsTerminal, Write},
+    path::Path,
+    process,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
 };
-use std::{env, time::Duration};
-use workout::{do_workout, load_workout, BeepLevel};
+use workout::config::Config;
+use workout::frontend::{Frontend, StatusLine};
+#[cfg(feature = "garmin")]
+use workout::garmin;
+use workout::history::HistoryReader;
+use workout::keys::{Interrupted, Keys};
+use workout::leaderboard::Leaderboard;
+use workout::measured::Measured;
+use workout::motivation::Motivation;
+#[cfg(feature = "overlay")]
+use workout::overlay;
+use workout::program::{Due, Program, ProgramDay};
+use workout::record::SessionRecord;
+use workout::session::{Session, StepKind};
+#[cfg(feature = "tui")]
+use workout::tui;
+use workout::workout::{
+    add_set_rests, format_duration, format_workout, run_session, split_format, upgrade, BeepLevel,
+    CueOverride, ExcerciseOptions, FileFormat, Pan, Sound, Workout, WorkoutSetElement,
+    DEFAULT_SET_REST, FORMAT,
+};
+use workout::{
+    audio::with_audio, backup, builtin, captions, coach, dirs, fit, health, history, html, influx,
+    intervals, keys, kiosk, locale, presets, progression, recovery, stats, toml_format,
+};
+
+fn load(file: &str, source: &str, flags: &[String]) -> Result<Workout> {
+    load_as(file, source, flags, None)
+}
 
-// TODO: better errors
+// in `format` instead of what the extension says
+fn load_as(
+    file: &str,
+    source: &str,
+    flags: &[String],
+    format: Option<FileFormat>,
+) -> Result<Workout> {
+    let format = format.unwrap_or(FileFormat::of(file));
+    let mut workout = Workout::load_as(file, source, flags, format)?;
+    // here, so the previews show the ramp-up sets too
+    let mut config = Config::load()?;
+    config.apply_env()?;
+    workout.expand_ramps(config.ramp_percentages());
+    Ok(workout)
+}
+
+fn write_output(out: Option<String>, contents: &str) -> Result<()> {
+    match out {
+        Some(path) => fs::write(path, contents)?,
+        None => print!("{contents}"),
+    }
+    Ok(())
+}
+
+fn card(file: &str, out: Option<String>) -> Result<()> {
+    let source = builtin::read(file)?;
+    let workout = load(file, &source, &[])?;
+    write_output(out, &html::card(&workout))
+}
+
+fn export_html(file: &str, out: Option<String>) -> Result<()> {
+    let source = builtin::read(file)?;
+    let workout = load(file, &source, &[])?;
+    write_output(out, &html::timer(&workout)?)
+}
 
-fn parse_from(s: &str) -> Result<(u16, u16, u16)> {
-    let Some((mut set, excercise)) = s.split_once('.') else {
-        return Err(anyhow::Error::msg("Starting position format: SET[/SET_REP].EXCERCISE"));
+fn convert(file: &str, out: &str) -> Result<()> {
+    let source = builtin::read(file)?;
+    // as written, `ramp` and all
+    let workout = Workout::load(file, &source, &[])?;
+    write_as(&workout, out)
+}
+
+// in the format `out`'s extension says, there's no writing Markdown
+fn write_as(workout: &Workout, out: &str) -> Result<()> {
+    let extension = Path::new(out).extension().and_then(|e| e.to_str());
+    let converted = match (FileFormat::of(out), extension) {
+        (FileFormat::Toml, _) => toml_format::format_toml(workout)?,
+        (FileFormat::Json, _) => toml_format::format_json(workout)?,
+        (FileFormat::Yaml, _) => toml_format::format_yaml(workout)?,
+        (FileFormat::Text, None | Some("txt" | "wk")) => format_workout(workout),
+        _ => {
+            return Err(anyhow::Error::msg(format!(
+                "Expected OUT to end in .txt, .wk, .toml, .json or .yaml, got: {out}"
+            )))
+        }
     };
+    fs::write(out, converted)?;
+    Ok(())
+}
+
+fn import(file: &str, out: &str) -> Result<()> {
+    let source = fs::read_to_string(file)
+        .map_err(|e| anyhow::Error::msg(format!("Couldn't read {file}: {e}")))?;
+    let workout =
+        presets::import(&source).map_err(|e| anyhow::Error::msg(format!("{file}: {e}")))?;
+    write_as(&workout, out)?;
+    println!("Imported {} into {out}", workout.name);
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    if let Err(e) = dirs::migrate() {
+        eprintln!("Couldn't move the config and history to their new place: {e}");
+    }
 
-    let set_rep;
-    if let Some((seti, srep)) = set.split_once('/') {
-        set = seti;
-        set_rep = srep.parse::<u16>()?.saturating_sub(1);
+    let cli = Cli::parse_args(env::args());
+    // --user goes for every command
+    if let Some(user) = cli.user.or(env::var("WORKOUT_USER").ok()) {
+        history::set_user(user)?;
+    }
+    // a broken config is complained about by whatever reads it next
+    if let Ok(mut config) = Config::load() {
+        if config.apply_env().is_ok() {
+            locale::set(config.locale());
+        }
+    }
+
+    let result = match cli.command {
+        Command::Run { file, options } => run(&file, options),
+        Command::Show {
+            file,
+            flags,
+            profile,
+            format,
+        } => show(&file, &flags, profile.as_deref(), format),
+        Command::Validate {
+            file,
+            flags,
+            profile,
+            format,
+        } => validate(&file, &flags, profile.as_deref(), format),
+        Command::List => list(),
+        Command::Today { program, options } => today(&program, options),
+        Command::Recovery { duration, options } => recovery(duration, options),
+        Command::RunIntervals {
+            spec,
+            pace,
+            heart_rate,
+            options,
+        } => run_intervals(&spec, intervals::Targets { pace, heart_rate }, options),
+        Command::Stats {
+            workout,
+            chart,
+            heatmap,
+            influx,
+            json,
+            tags,
+            without_tags,
+        } => stats(workout, chart, heatmap, influx, json, tags, without_tags),
+        Command::Review { file, results } => review(&file, &results),
+        Command::Card { file, out } => card(&file, out),
+        Command::ExportHtml { file, out } => export_html(&file, out),
+        Command::Convert { file, out } => convert(&file, &out),
+        Command::Import { file, out } => import(&file, &out),
+        Command::Fmt {
+            upgrade,
+            fix,
+            set_rest,
+            files,
+        } => fmt(upgrade, fix, set_rest, files),
+        Command::Replay { log, profile } => replay(&log, profile.as_deref()),
+        Command::TestAudio { file, profile } => test_audio(file.as_deref(), profile.as_deref()),
+        Command::Migrate { from, to } => migrate(from, to),
+        Command::Backup { out } => backup(&out),
+        Command::Restore { archive, force } => restore(&archive, force),
+    };
+    // ctrl-c, the terminal's already put back
+    if result.as_ref().is_err_and(|e| e.is::<Interrupted>()) {
+        process::exit(130);
+    }
+    result
+}
+
+// runs the setup wizard the first time, if there's someone to answer it
+fn load_config(profile: Option<&str>) -> Result<Config> {
+    let mut config = if Config::path()?.exists() || !io::stdin().is_terminal() {
+        Config::load()?
     } else {
-        set_rep = 0;
+        Config::wizard(|volume| {
+            let config = Config {
+                volume,
+                ..Config::default()
+            };
+            with_audio(&config, |beep| {
+                beep(&Sound::Beep(BeepLevel::High));
+                thread::sleep(Duration::from_secs(1));
+                Ok(())
+            })
+        })?
+    };
+    if let Some(profile) = profile
+        .map(str::to_owned)
+        .or(env::var("WORKOUT_PROFILE").ok())
+    {
+        config.use_profile(&profile)?;
     }
+    config.apply_env()?;
+    Ok(config)
+}
 
-    let set = set.parse::<u16>()?.saturating_sub(1);
-    let excercise = excercise.parse::<u16>()?.saturating_sub(1);
-    Ok((set, set_rep, excercise))
+// sound cues are relative to the workout file
+fn resolve_sounds(workout: &mut Workout, file: &str) {
+    let Some(dir) = Path::new(file).parent() else {
+        return;
+    };
+    workout.visit_elements_mut(|part| {
+        if let WorkoutSetElement::Excercise {
+            options:
+                ExcerciseOptions {
+                    cue: Some(CueOverride::Sound(sound)),
+                    ..
+                },
+            ..
+        } = part
+        {
+            *sound = dir.join(&*sound).to_string_lossy().into_owned();
+        }
+    });
 }
 
-fn main() -> Result<()> {
-    let Some(file) = env::args().nth(1) else {
-        return Err(anyhow::Error::msg("No file provided"));
+fn print_summary(record: &SessionRecord) {
+    print!(
+        "{} in {}",
+        match (record.completed, record.abort_reason) {
+            (true, _) => "Completed".to_owned(),
+            (false, Some(reason)) => format!("Finished early ({})", reason.name()),
+            (false, None) => "Finished early".to_owned(),
+        },
+        locale::minutes(record.duration())
+    );
+    let prep: Duration = record
+        .steps
+        .iter()
+        .filter(|s| matches!(s.kind, StepKind::Prep { .. }))
+        .map(|s| s.duration)
+        .sum();
+    if !prep.is_zero() {
+        print!(
+            " ({} of it prep)",
+            locale::decimal(prep.as_secs_f64() / 60., 1)
+        );
+    }
+    println!();
+
+    if !record.tags.is_empty() {
+        println!("Tagged {}", record.tags.join(", "));
+    }
+    for (group, time) in record.group_times() {
+        println!("  {group}: {}", locale::minutes(time));
+    }
+    let asked: Vec<_> = record.steps.iter().filter_map(|s| s.acknowledged).collect();
+    if !asked.is_empty() {
+        println!(
+            "Acknowledged {} of {} excercises",
+            asked.iter().filter(|a| **a).count(),
+            asked.len()
+        );
+    }
+    if !record.edits.is_empty() {
+        println!("Changed along the way:");
+        for edit in &record.edits {
+            println!("  {edit}");
+        }
+    }
+}
+
+fn save_history(record: &SessionRecord) {
+    if let Err(e) = history::save(record) {
+        eprintln!("Couldn't save the session to the history: {e}");
+    }
+}
+
+// every sound a session makes, with what it's for and time to hear it out
+fn test_audio(file: Option<&str>, profile: Option<&str>) -> Result<()> {
+    let config = load_config(profile)?;
+    if config.no_audio {
+        println!("The audio device is turned off (no_audio), only the other sinks get them");
+    }
+    let mut cues: Vec<(String, Sound, u64)> = [
+        ("Start of the workout", Sound::Start, 3),
+        (
+            "High beep, an excercise starts",
+            Sound::Beep(BeepLevel::High),
+            2,
+        ),
+        (
+            "Mid beep, halfway, rest ending, countdowns",
+            Sound::Beep(BeepLevel::Mid),
+            2,
+        ),
+        (
+            "Low beep, an excercise ends",
+            Sound::Beep(BeepLevel::Low),
+            2,
+        ),
+        (
+            "Relax beeps, for `relax` excercises",
+            Sound::Relax(BeepLevel::Mid),
+            3,
+        ),
+        (
+            "Left side",
+            Sound::Panned(Box::new(Sound::Beep(BeepLevel::High)), Pan::Left),
+            2,
+        ),
+        (
+            "Right side",
+            Sound::Panned(Box::new(Sound::Beep(BeepLevel::High)), Pan::Right),
+            2,
+        ),
+        (
+            "Read out loud, for `say`",
+            Sound::Speech("Keep the hips up".to_owned()),
+            3,
+        ),
+    ]
+    .into_iter()
+    .map(|(what, sound, secs)| (what.to_owned(), sound, secs))
+    .collect();
+    if let Some(file) = file {
+        let mut workout = load(file, &builtin::read(file)?, &[])?;
+        resolve_sounds(&mut workout, file);
+        let mut sounds = vec![];
+        for set in &workout.sections {
+            for part in &set.parts {
+                part.visit(&mut |p| {
+                    let WorkoutSetElement::Excercise { options, .. } = p else {
+                        return;
+                    };
+                    if let Some(CueOverride::Sound(path)) = &options.cue {
+                        sounds.push(Sound::File(path.clone()));
+                    }
+                    for say in &options.details.say {
+                        sounds.push(Sound::Speech(say.text.clone()));
+                    }
+                });
+            }
+        }
+        for sound in sounds {
+            let what = format!("{file}: {}", sound.name());
+            if !cues.iter().any(|(w, _, _)| *w == what) {
+                cues.push((what, sound, 3));
+            }
+        }
+    }
+
+    with_audio(&config, |beep| {
+        for (what, sound, secs) in &cues {
+            println!("{what}");
+            beep(sound);
+            thread::sleep(Duration::from_secs(*secs));
+        }
+        println!("The countdown before an excercise");
+        for level in [
+            BeepLevel::Mid,
+            BeepLevel::Mid,
+            BeepLevel::Mid,
+            BeepLevel::High,
+        ] {
+            beep(&Sound::Beep(level));
+            thread::sleep(Duration::from_secs(1));
+        }
+        thread::sleep(Duration::from_secs(1));
+        println!("Metronome, for `metronome 3-1-2`");
+        beep(&Sound::Metronome(
+            [3, 1, 2].map(Duration::from_secs).to_vec(),
+        ));
+        thread::sleep(Duration::from_secs(12));
+        beep(&Sound::MetronomeStop);
+        println!("End of the workout");
+        beep(&Sound::Complete);
+        thread::sleep(Duration::from_secs(3));
+        Ok(())
+    })
+}
+
+fn replay(log: &str, profile: Option<&str>) -> Result<()> {
+    // the last session of a JSONL history, or a single JSON session
+    let recorded: SessionRecord = if log.ends_with(".jsonl") {
+        let mut last = None;
+        for record in HistoryReader::open(Path::new(log))? {
+            last = Some(record?);
+        }
+        last.ok_or_else(|| anyhow::Error::msg("The history is empty"))?
+    } else {
+        serde_json::from_str(&fs::read_to_string(log)?)?
+    };
+    if recorded.steps.is_empty() {
+        return Err(anyhow::Error::msg(
+            "The log has no recorded timings to replay",
+        ));
+    }
+
+    let title = format!(
+        "replay of {} from {}",
+        recorded.workout,
+        recorded.start.format("%Y-%m-%d %H:%M")
+    );
+    let config = load_config(profile)?;
+    let record = with_audio(&config, |beep| {
+        let mut session = Session::replay(&recorded, beep);
+        session.set_units(config.units);
+        session.set_panning(config.pan.clone());
+        if let Some(seconds) = config.countdown_beeps {
+            session.set_countdown_beeps(seconds);
+        }
+        session.set_speed(config.speed);
+        if let Some(adaptive) = config.adaptive_rest {
+            session.set_adaptive_rest(adaptive);
+        }
+        run_session(&title, recorded.workout.clone(), session, beep, &[])
+    })?;
+
+    print_summary(&record);
+    save_history(&record);
+    Ok(())
+}
+
+fn stats(
+    workout: Option<String>,
+    chart: Option<String>,
+    heatmap: bool,
+    influx: bool,
+    json: bool,
+    tags: Vec<String>,
+    without_tags: Vec<String>,
+) -> Result<()> {
+    let records: Vec<_> = history::load_all()?
+        .into_iter()
+        .filter(|r| workout.as_ref().is_none_or(|w| &r.workout == w))
+        .filter(|r| tags.iter().all(|t| r.tags.contains(t)))
+        .filter(|r| !without_tags.iter().any(|t| r.tags.contains(t)))
+        .collect();
+    // other programs get an empty summary
+    if records.is_empty() && !json {
+        println!("No sessions yet");
+        return Ok(());
+    }
+
+    let today = Local::now().date_naive();
+    if let Some(path) = chart {
+        if !path.ends_with(".svg") {
+            return Err(anyhow::Error::msg("Charts can only be written as .svg"));
+        }
+        fs::write(path, stats::chart_svg(&records, today)?)?;
+        return Ok(());
+    }
+    if heatmap {
+        print!("{}", stats::heatmap(&records, today));
+        return Ok(());
+    }
+    if influx {
+        for record in &records {
+            print!("{}", influx::session_lines(record));
+        }
+        return Ok(());
+    }
+    if json {
+        let summary = stats::Summary::new(&records, today);
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+    print!("{}", stats::stats(&records, today));
+    Ok(())
+}
+
+fn backup(out: &str) -> Result<()> {
+    let files = backup::backup(Path::new(out), &load_config(None)?)?;
+    println!("Backed up {files} files to {out}");
+    Ok(())
+}
+
+fn restore(archive: &str, force: bool) -> Result<()> {
+    let (restored, skipped) = backup::restore(Path::new(archive), force)?;
+    println!("Restored {restored} files");
+    if skipped > 0 {
+        println!("Left {skipped} existing files alone (--force to overwrite them)");
+    }
+    Ok(())
+}
+
+fn migrate(from: history::Backend, to: history::Backend) -> Result<()> {
+    let (sessions, backup) = history::migrate(from, to)?;
+    println!(
+        "Moved {sessions} sessions to {}, the old history is kept as {}",
+        to.path()?.display(),
+        backup.display()
+    );
+    if to == history::Backend::Age {
+        println!("It isn't encrypted, delete it once you've checked the new one works");
+    }
+    Ok(())
+}
+
+fn fmt(
+    upgrade_files: bool,
+    fix: bool,
+    set_rest: Option<Duration>,
+    files: Vec<String>,
+) -> Result<()> {
+    let set_rest = match (fix, set_rest) {
+        (false, _) => None,
+        (true, Some(rest)) => Some(rest),
+        (true, None) => Some(load_config(None)?.set_rest.unwrap_or(DEFAULT_SET_REST)),
+    };
+    for file in files {
+        let source = fs::read_to_string(&file)?;
+        // only the text and Markdown syntax have versions
+        match FileFormat::of(&file) {
+            FileFormat::Text | FileFormat::Markdown => upgrade_file(&file, &source, upgrade_files)?,
+            _ => println!("{file}: format n/a, only text and Markdown files have one"),
+        }
+        if let Some(rest) = set_rest {
+            fix_set_rests(&file, rest)?;
+        }
+    }
+    Ok(())
+}
+
+fn upgrade_file(file: &str, source: &str, upgrade_files: bool) -> Result<()> {
+    let (format, _) = split_format(source)?;
+    match upgrade(source)? {
+        None => println!("{file}: format {format}, up to date"),
+        Some(upgraded) if upgrade_files => {
+            // it has to still load before it's written over
+            if Program::is_program(&upgraded) {
+                Program::parse(&upgraded)?;
+            } else {
+                Workout::load(file, &upgraded, &[])?;
+            }
+            fs::write(file, upgraded)?;
+            println!("{file}: format {format} -> {FORMAT}");
+        }
+        Some(_) => println!("{file}: format {format}, --upgrade brings it to {FORMAT}"),
+    }
+    Ok(())
+}
+
+fn fix_set_rests(file: &str, rest: Duration) -> Result<()> {
+    let source = fs::read_to_string(file)?;
+    if FileFormat::of(file) != FileFormat::Text || Program::is_program(&source) {
+        println!("{file}: --fix only works on text format workouts");
+        return Ok(());
+    }
+    let (fixed, added) = add_set_rests(&source, rest);
+    if added == 0 {
+        println!("{file}: every set done more than once has a `Set rest`");
+        return Ok(());
+    }
+    // it has to still load before it's written over
+    Workout::load(file, &fixed, &[])?;
+    fs::write(file, fixed)?;
+    println!(
+        "{file}: put in {added} `Set rest {}`",
+        format_duration(rest)
+    );
+    Ok(())
+}
+
+fn review(file: &str, results: &str) -> Result<()> {
+    let file = load_config(None)?.find_workout(file);
+    let workout = load(&file, &builtin::read(&file)?, &[])?;
+    let results = serde_json::from_str(&fs::read_to_string(results)?)?;
+    print!("{}", coach::review(&workout, &results)?);
+    Ok(())
+}
+
+fn show(
+    file: &str,
+    flags: &[String],
+    profile: Option<&str>,
+    format: Option<FileFormat>,
+) -> Result<()> {
+    let config = load_config(profile)?;
+    let file = config.find_workout(file);
+    let mut workout = load_as(&file, &builtin::read(&file)?, flags, format)?;
+    config.apply(&mut workout);
+
+    println!("{}", workout.title(config.measured()?.as_ref()));
+    // sets done 0 times and what has nothing to it aren't run
+    let left_out = |out: bool| if out { " (left out)" } else { "" };
+    for (i, set) in workout.sections.iter().enumerate() {
+        println!("Set {}: {set}{}", i + 1, left_out(set.reps == 0));
+        for part in &set.parts {
+            println!("  {part}{}", left_out(part.is_empty()));
+            if let WorkoutSetElement::Circuit { parts, .. } = part {
+                for part in parts {
+                    println!("    {part}{}", left_out(part.is_empty()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn list() -> Result<()> {
+    let config = load_config(None)?;
+    let measured = config.measured()?;
+    // a line for every file that loads, and why the others don't
+    let describe = |file: &str, source: &str| match Program::is_program(source) {
+        true => Program::parse(source).map(|p| format!("program {}", p.name)),
+        false => load(file, source, &[]).map(|w| w.title(measured.as_ref())),
+    };
+
+    let mut files: Vec<_> = match config.workouts_dir() {
+        Some(dir) if dir.exists() => fs::read_dir(&dir)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_file())
+            .collect(),
+        _ => vec![],
+    };
+    files.sort();
+    for path in files {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let described = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| describe(&path.to_string_lossy(), &source));
+        match described {
+            Ok(description) => println!("{name}: {description}"),
+            Err(e) => println!("{name}: doesn't load: {e}"),
+        }
+    }
+    for name in builtin::names() {
+        let file = format!("builtin:{name}");
+        println!("{file}: {}", describe(&file, &builtin::read(&file)?)?);
+    }
+    Ok(())
+}
+
+fn validate(
+    file: &str,
+    flags: &[String],
+    profile: Option<&str>,
+    mut format: Option<FileFormat>,
+) -> Result<()> {
+    let config = load_config(profile)?;
+    let file = config.find_workout(file);
+    let source = builtin::read(&file)?;
+    // every day of a program, alternates included
+    let mut workouts = vec![];
+    if Program::is_program(&source) {
+        // the days are in whatever their files are
+        format = None;
+        let program = Program::parse(&source)?;
+        let dir = Path::new(&file).parent().unwrap_or(Path::new(""));
+        for (i, day) in program.days.iter().enumerate() {
+            if let Some(source) = &day.source {
+                workouts.push((program.day_name(i), file.clone(), source.clone()));
+            }
+            for alternate in &day.alternates {
+                let path = dir.join(alternate).to_string_lossy().into_owned();
+                let source = fs::read_to_string(&path)
+                    .map_err(|e| anyhow::Error::msg(format!("Couldn't read {path}: {e}")))?;
+                workouts.push((program.day_name(i), path, source));
+            }
+        }
+    } else {
+        workouts.push((file.clone(), file.clone(), source));
+    }
+
+    for (label, file, source) in workouts {
+        let mut workout = load_as(&file, &source, flags, format)?;
+        config.apply(&mut workout);
+        println!("{label}: {workout}");
+        for warning in workout
+            .warnings()
+            .into_iter()
+            .chain(config.over_caps(&workout))
+        {
+            println!("  warning: {warning}");
+        }
+    }
+    Ok(())
+}
+
+fn recovery(duration: Duration, options: RunArgs) -> Result<()> {
+    // saved, so it can be resumed like any other
+    let workout = recovery::generate(duration, Local::now().ordinal() as usize)?;
+    let dir = dirs::state_dir()?;
+    fs::create_dir_all(&dir)?;
+    let file = dir.join("recovery.wk");
+    fs::write(&file, format_workout(&workout))?;
+    run(&file.to_string_lossy(), options)
+}
+
+fn run_intervals(spec: &str, targets: intervals::Targets, options: RunArgs) -> Result<()> {
+    // saved, so it can be resumed like any other
+    let units = load_config(None)?.units;
+    let workout = intervals::generate(spec, &targets, units)?;
+    let dir = dirs::state_dir()?;
+    fs::create_dir_all(&dir)?;
+    let file = dir.join("intervals.wk");
+    fs::write(&file, format_workout(&workout))?;
+    run(&file.to_string_lossy(), options)
+}
+
+/// The workout file and source of a program's day, `None` on a rest day. Of
+/// alternating workouts it's the one after the last done on that day.
+fn program_day(
+    file: &str,
+    program: &Program,
+    index: usize,
+    flags: &[String],
+) -> Result<Option<(String, String)>> {
+    let day = &program.days[index];
+    if let Some(source) = &day.source {
+        return Ok(Some((file.to_owned(), source.clone())));
+    }
+    if day.alternates.is_empty() {
+        return Ok(None);
+    }
+
+    let dir = Path::new(file).parent().unwrap_or(Path::new(""));
+    let mut alternates = vec![];
+    for alternate in &day.alternates {
+        let path = dir.join(alternate).to_string_lossy().into_owned();
+        let source = fs::read_to_string(&path)
+            .map_err(|e| anyhow::Error::msg(format!("Couldn't read {path}: {e}")))?;
+        let name = load(&path, &source, flags)?.name;
+        alternates.push((path, source, name));
+    }
+
+    let last = history::load_all()?
+        .into_iter()
+        .rev()
+        .find(|r| r.completed && r.program.as_ref() == Some(&program.name) && r.day == Some(index));
+    let names: Vec<_> = alternates.iter().map(|(_, _, n)| n.clone()).collect();
+    let next = ProgramDay::next_alternate(&names, last.as_ref().map(|r| r.workout.as_str()));
+    let (path, source, _) = alternates.swap_remove(next);
+    Ok(Some((path, source)))
+}
+
+// the workout of a day that's due, which can't be a rest day
+fn due_day(file: &str, program: &Program, index: usize) -> Result<(String, String)> {
+    program_day(file, program, index, &[])?.ok_or_else(|| {
+        anyhow::Error::msg(format!(
+            "{} of {} has no workout",
+            program.day_name(index),
+            program.name
+        ))
+    })
+}
+
+// the days of the program done, by date
+fn done_days(program: &Program) -> Result<Vec<(NaiveDate, usize)>> {
+    Ok(history::load_all()?
+        .into_iter()
+        .filter(|r| r.completed && r.program.as_ref() == Some(&program.name))
+        .filter_map(|r| Some((r.start.date_naive(), r.day?)))
+        .collect())
+}
+
+fn today(file: &str, options: RunArgs) -> Result<()> {
+    let config = load_config(None)?;
+    let file = config.find_workout(file);
+    let program = Program::parse(&fs::read_to_string(&file)?)?;
+    let day = match program.due(&done_days(&program)?, Local::now().date_naive()) {
+        Due::Today(day) => day,
+        Due::Rest(next) => {
+            println!("Rest day, next up is {}", program.day_name(next));
+            return Ok(());
+        }
+        Due::Done(next) => {
+            println!(
+                "Already trained today, next up is {}",
+                program.day_name(next)
+            );
+            return Ok(());
+        }
+    };
+
+    let (workout_file, source) = due_day(&file, &program, day)?;
+    let workout = load(&workout_file, &source, &[])?;
+    println!(
+        "Today is {} of {}: {}",
+        program.day_name(day),
+        program.name,
+        workout.title(config.measured()?.as_ref())
+    );
+    println!("Start it? [Y/n]");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if matches!(answer.trim(), "n" | "N" | "no") {
+        return Ok(());
+    }
+
+    let day = Some((day + 1).to_string());
+    run(&file, RunArgs { day, ..options })
+}
+
+fn run(file: &str, options: RunArgs) -> Result<()> {
+    if options.kiosk {
+        return run_kiosk(file, options);
+    }
+    run_once(file, options, None)
+}
+
+// the day of the program that's due (or the workout) over and over, waiting
+// for a key press in between, for a screen that's always on in the gym
+fn run_kiosk(file: &str, options: RunArgs) -> Result<()> {
+    keys::hold_to_quit(true);
+    let screen = kiosk::Screen::open();
+    loop {
+        let config = load_config(None)?;
+        let path = config.find_workout(file);
+        let source = builtin::read(&path)?;
+        let (tx, input) = mpsc::channel();
+        let keys = Keys::spawn(tx);
+
+        let mut day = None;
+        let next = if Program::is_program(&source) {
+            let program = Program::parse(&source)?;
+            match program.due(&done_days(&program)?, Local::now().date_naive()) {
+                Due::Today(index) => {
+                    day = Some((index + 1).to_string());
+                    let (day_file, day_source) = due_day(&path, &program, index)?;
+                    load(&day_file, &day_source, &[])?.name
+                }
+                // checked again every minute, until it's due
+                Due::Rest(next) | Due::Done(next) => {
+                    screen.show(&format!("Next up is {}", program.day_name(next)), "Rest");
+                    match input.recv_timeout(Duration::from_secs(60)) {
+                        Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                        Ok(line) if line == keys::INTERRUPT => return Err(Interrupted.into()),
+                        _ => continue,
+                    }
+                }
+            }
+        } else {
+            load(&path, &source, &[])?.name
+        };
+
+        screen.show(&next, "Ready");
+        println!("Press any key to start {next}");
+        match input.recv() {
+            Err(_) => return Ok(()),
+            Ok(line) if line == keys::INTERRUPT => return Err(Interrupted.into()),
+            Ok(_) => {}
+        }
+        drop(keys);
+        let options = RunArgs {
+            day: day.or(options.day.clone()),
+            ..options.clone()
+        };
+        run_once(file, options, Some(&screen))?;
+    }
+}
+
+// over the config and the environment
+fn apply_run_args(config: &mut Config, options: &RunArgs) {
+    if options.no_sound {
+        config.no_audio = true;
+    }
+    if let Some(volume) = options.volume {
+        config.volume = volume;
+    }
+    if options.countdown.is_some() {
+        config.pre_roll = options.countdown;
+    }
+    if options.redraw_every.is_some() {
+        config.redraw_every = options.redraw_every;
+    }
+    if options.max_session.is_some() {
+        config.max_session = options.max_session;
+    }
+}
+
+fn run_once(file: &str, options: RunArgs, screen: Option<&kiosk::Screen>) -> Result<()> {
+    // and into the excercise there
+    let (mut from, mut offset) = options.from.unwrap_or_default();
+    let user = match history::user() {
+        Some(user) => user.to_owned(),
+        None => env::var("USER")
+            .or_else(|_| env::var("USERNAME"))
+            .unwrap_or_else(|_| "me".to_owned()),
     };
-    let from = if let Some(a) = env::args().nth(2) {
-        parse_from(&a)?
+
+    let mut config = load_config(options.profile.as_deref())?;
+    apply_run_args(&mut config, &options);
+    // it's all escape codes
+    if let (Some(_), Some(_)) = (screen, config.redraw_every) {
+        return Err(anyhow::Error::msg(
+            "The kiosk redraws the whole screen, it can't go with `redraw_every`",
+        ));
+    }
+    let file = &config.find_workout(file);
+    let mut source = builtin::read(file)?;
+    // where the workout itself comes from, an alternate of a program day
+    let mut workout_file = file.clone();
+    let program = if Program::is_program(&source) {
+        let Some(day) = &options.day else {
+            return Err(anyhow::Error::msg(format!(
+                "{file} is a program, pick a day with --day"
+            )));
+        };
+        let program = Program::parse(&source)?;
+        let index = program.find_day(day)?;
+        let Some((day_file, day_source)) = program_day(file, &program, index, &options.flags)?
+        else {
+            println!(
+                "{} of {} is a rest day",
+                program.day_name(index),
+                program.name
+            );
+            return Ok(());
+        };
+        let week = match options.week {
+            Some(w) if (1..=program.weeks.len()).contains(&w) => Some(w - 1),
+            Some(w) => {
+                return Err(anyhow::Error::msg(format!(
+                    "{} has no week {w} (it has {} weeks)",
+                    program.name,
+                    program.weeks.len()
+                )))
+            }
+            None => program.week_at(Local::now().date_naive()),
+        };
+        match week {
+            Some(w) => println!(
+                "{}, {}, {}",
+                program.name,
+                program.week_name(w),
+                program.day_name(index)
+            ),
+            None => println!("{}, {}", program.name, program.day_name(index)),
+        }
+        source = day_source;
+        workout_file = day_file;
+        Some((program, index, week))
     } else {
-        (0, 0, 0)
-    };
-
-    let source = std::fs::read_to_string(file)?;
-    let workout = load_workout(&source)?;
-
-    // FIXME: ALSA lib pcm.c:8570:(snd_pcm_recover) underrun occurred
-    let (queue_in, queue_out) = queue(true);
-    let (_stream, stream_handle) = OutputStream::try_default()?;
-    stream_handle.play_raw(queue_out)?;
-
-    let beep_len = Duration::from_secs_f64(0.5);
-    let beep_sample = |level: BeepLevel| {
-        SineWave::new(level.get_frequency())
-            .take_duration(beep_len)
-            .fade_in(Duration::from_secs_f64(0.1))
-            .take_crossfade_with(
-                Zero::<i16>::new(1, 1).take_duration(Duration::from_secs_f64(0.1)),
-                beep_len,
-            )
-    };
-
-    let presampled = [
-        beep_sample(BeepLevel::Low).buffered(),
-        beep_sample(BeepLevel::Mid).buffered(),
-        beep_sample(BeepLevel::High).buffered(),
-    ];
-
-    // TODO: handle pausing somehow
-    // thread::scope(|s| {
-    //  s.spawn(|| {
-    //          ...
-    //     });
-    // });
-
-    do_workout(workout, from, |level| {
-        queue_in.append(presampled[level as usize].clone())
+        None
+    };
+    // a kiosk always picks up where it was, e.g. before the power went out
+    if options.resume || screen.is_some() {
+        match history::load_resume(file, program.as_ref().map(|(_, day, _)| *day))? {
+            Some(position) => (from, offset) = position,
+            None if options.resume => println!("Nothing to resume, starting from the beginning"),
+            None => {}
+        }
+    }
+    // a program's days are in whatever their files are
+    let format = options.format.filter(|_| program.is_none());
+    let mut workout = load_as(&workout_file, &source, &options.flags, format)?;
+    // a profile picked for this run wins over the file's
+    if let Some(audio) = &workout.audio {
+        if options.profile.is_none() && env::var("WORKOUT_PROFILE").is_err() {
+            config.use_audio_profile(audio)?;
+            config.apply_env()?;
+            apply_run_args(&mut config, &options);
+        }
+    }
+    for change in progression::apply(&mut workout, history::load_all)? {
+        println!("Progressed {change}");
+    }
+    if let Some((program, _, Some(week))) = &program {
+        workout.scale(program.weeks[*week].intensity);
+    }
+    resolve_sounds(&mut workout, &workout_file);
+    config.apply(&mut workout);
+
+    let measured = config.measured()?;
+    let done_by = Local::now() + workout.length_with(measured.as_ref());
+    let title = format!(
+        "{}, done around {}",
+        workout.title(measured.as_ref()),
+        locale::time_of_day(done_by)
+    );
+
+    let over = config.over_caps(&workout);
+    if !over.is_empty() {
+        for warning in &over {
+            println!("{warning}");
+        }
+    }
+    // nobody to ask in a kiosk
+    if !over.is_empty() && screen.is_none() {
+        print!("Run it anyway? [y/N] ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            return Ok(());
+        }
+    }
+
+    let board = if options.leaderboard {
+        let path = Leaderboard::path(file);
+        let board = Leaderboard::load(&path)?;
+        board.print();
+        Some((path, board))
+    } else {
+        None
+    };
+
+    // cut off before it got to the end the last time
+    if let Some(record) = history::running()? {
+        match history::save(&record) {
+            Ok(_) => {
+                history::clear_running()?;
+                println!(
+                    "Saved the {} session cut off at {} to the history",
+                    record.workout,
+                    locale::time_of_day(record.end)
+                );
+            }
+            Err(e) => eprintln!("Couldn't save the session that was cut off: {e}"),
+        }
+    }
+    let history = history::load_all()?;
+    let last = if options.ghost {
+        let last = history::last_session(&workout.name)?;
+        if last.is_none() {
+            println!("No previous session of {} to race against", workout.name);
+        }
+        last
+    } else {
+        None
+    };
+
+    #[cfg(feature = "overlay")]
+    let overlay = match options.overlay {
+        true => Some(overlay::Overlay::open()?),
+        false => None,
+    };
+    #[cfg(feature = "overlay")]
+    let shown = overlay.is_some() || screen.is_some();
+    #[cfg(not(feature = "overlay"))]
+    let shown = screen.is_some();
+    let show = shown.then_some(|label: &str, time: &str| {
+        #[cfg(feature = "overlay")]
+        if let Some(overlay) = &overlay {
+            overlay.show(label, time);
+        }
+        if let Some(screen) = screen {
+            screen.show(label, time);
+        }
+    });
+    #[cfg(feature = "tui")]
+    let tui = match options.tui {
+        true => Some(tui::Tui::open()?),
+        false => None,
+    };
+    // the kiosk and the TUI have the time left up top
+    #[cfg(feature = "tui")]
+    let on_top = screen.is_some() || tui.is_some();
+    #[cfg(not(feature = "tui"))]
+    let on_top = screen.is_some();
+    let status_line = (!(on_top || options.no_status_line || config.no_status_line)
+        && io::stdout().is_terminal())
+    .then(StatusLine::default);
+    let mut frontends: Vec<&dyn Frontend> = vec![];
+    if let Some(show) = &show {
+        frontends.push(show);
+    }
+    if let Some(status_line) = &status_line {
+        frontends.push(status_line);
+    }
+    #[cfg(feature = "tui")]
+    if let Some(tui) = &tui {
+        frontends.push(tui);
+    }
+    let checkpoint = (file.clone(), program.as_ref().map(|(_, day, _)| *day));
+
+    // the first one is kept for the end
+    let mut motivation = if config.no_motivation || options.no_motivation {
+        None
+    } else {
+        Some(Motivation::load()?)
+    };
+    let closing = motivation
+        .as_mut()
+        .and_then(|m| m.line().map(str::to_owned));
+
+    let mut record = with_audio(&config, |beep| {
+        let mut session = Session::new(&workout, from, beep)?;
+        if let Some(motivation) = motivation {
+            session.set_motivation(motivation);
+        }
+        session.set_units(config.units);
+        session.set_panning(config.pan.clone());
+        if options.acknowledge || config.acknowledge {
+            session.require_acknowledgement();
+        }
+        session.set_tags(options.tags.clone());
+        if options.ask_tags || config.ask_tags {
+            session.ask_for_tags();
+        }
+        if let Some(pre_roll) = config.pre_roll {
+            session.set_pre_roll(pre_roll);
+        }
+        if let Some(seconds) = config.countdown_beeps {
+            session.set_countdown_beeps(seconds);
+        }
+        session.set_speed(config.speed);
+        if let Some(every) = config.redraw_every {
+            session.set_redraw_every(every);
+        }
+        if let Some(minutes) = config.max_session {
+            // from the config too, where it isn't checked
+            session.set_max_length(Duration::from_secs(minutes.saturating_mul(60)));
+        }
+        if options.mute {
+            session.mute(true);
+        }
+        if let Some(offset) = offset {
+            session.start_into(offset)?;
+        }
+        if let Some(adaptive) = config.adaptive_rest {
+            session.set_adaptive_rest(adaptive);
+        }
+        if let Some(last) = &last {
+            session.ghost(last);
+        }
+        session.last_time(&history);
+        if options.timing_report {
+            session.record_timings();
+        }
+        if screen.is_some() {
+            let (file, day) = checkpoint;
+            session.set_checkpoint(move |from| {
+                if let Err(e) = history::save_resume(&file, day, from, None) {
+                    eprintln!("Couldn't save where to resume from: {e}");
+                }
+            });
+        }
+        // kept at every step, only the new ones written
+        let running = history::Running::start(&SessionRecord {
+            workout: workout.name.clone(),
+            start: Local::now(),
+            end: Local::now(),
+            completed: false,
+            resume_from: None,
+            resume_offset: None,
+            abort_reason: None,
+            program: program.as_ref().map(|(p, _, _)| p.name.clone()),
+            day: program.as_ref().map(|(_, day, _)| *day),
+            week: program.as_ref().and_then(|(_, _, week)| *week),
+            tags: options.tags.clone(),
+            sets: session.sets().to_vec(),
+            steps: vec![],
+            edits: vec![],
+        })
+        .map_err(|e| eprintln!("Couldn't keep the session somewhere: {e}"))
+        .ok()
+        .flatten()
+        .map(RefCell::new);
+        if let Some(running) = running {
+            session.set_snapshot(move |steps, resume_from| {
+                if let Err(e) = running.borrow_mut().keep(steps, resume_from) {
+                    eprintln!("Couldn't keep the session somewhere: {e}");
+                }
+            });
+        }
+        // piped runs would stay paused for good
+        if let (Some(factor), true) = (config.idle_pause, io::stdin().is_terminal()) {
+            session.set_idle_pause(Measured::new(&history), factor);
+        }
+        run_session(&title, workout.name.clone(), session, beep, &frontends)
     })?;
+    #[cfg(feature = "tui")]
+    drop(tui);
+    if let Some((program, day, week)) = program {
+        record.program = Some(program.name);
+        record.day = Some(day);
+        record.week = week;
+    }
+
+    print_summary(&record);
+    if let Some(closing) = closing {
+        println!("{closing}");
+    }
+    if let Some((set, set_rep, excercise)) = record.resume_from {
+        println!(
+            "Resume with: workout {file}{} --from {}/{}.{}{} (or --resume)",
+            record
+                .day
+                .map(|d| format!(" --day {}", d + 1))
+                .unwrap_or_default(),
+            set + 1,
+            set_rep + 1,
+            excercise + 1,
+            record
+                .resume_offset
+                .map(|o| format!("+{}", format_duration(o)))
+                .unwrap_or_default()
+        );
+    }
+    if let Err(e) = history::save_resume(file, record.day, record.resume_from, record.resume_offset)
+    {
+        eprintln!("Couldn't save where to resume from: {e}");
+    }
+    save_history(&record);
+    if let Err(e) = history::clear_running() {
+        eprintln!("Couldn't clear the session kept while running: {e}");
+    }
+
+    if let Some((path, mut board)) = board {
+        if board.record(&user, &record) {
+            println!("New personal best for {user}!");
+        }
+        board.save(&path)?;
+    }
+
+    if let Some(path) = options.apple_health {
+        fs::write(path, health::apple_health_xml(&record))?;
+    }
+    if let Some(path) = options.google_fit {
+        fs::write(path, health::google_fit_json(&record)?)?;
+    }
+    if let Some(path) = options.fit {
+        fs::write(path, fit::session_fit(&record))?;
+    }
+    if let Some(path) = options.influx {
+        // appended, so a single file can collect every session
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(influx::session_lines(&record).as_bytes())?;
+    }
+    if let Some(path) = options.results {
+        fs::write(
+            path,
+            serde_json::to_string_pretty(&coach::Results::new(&record))?,
+        )?;
+    }
+    if let Some(path) = options.captions {
+        let vtt = path.ends_with(".vtt");
+        fs::write(path, captions::captions(&record, vtt))?;
+    }
+    #[cfg(feature = "garmin")]
+    if options.garmin {
+        garmin::upload(&fit::session_fit(&record))?;
+        println!("Uploaded to Garmin Connect");
+    }
 
     Ok(())
 }
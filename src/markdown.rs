@@ -0,0 +1,307 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::workout::{
+    at_line, parse_intervals, parse_set_header, parse_template, split_lead_in, split_options,
+    split_workout_header, unexpected, CircuitRest, ExcerciseAmout, Workout, WorkoutSet,
+    WorkoutSetElement, TEMPLATES,
+};
+
+const DURATION: &str = "a duration like 30s, 2m or 1m30s";
+
+// 30s, 2m, 1m30s, 90sec, 2min
+fn parse_md_dur(s: &str) -> Option<Duration> {
+    let mut secs = 0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        if digits == 0 {
+            return None;
+        }
+        let n: u64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        secs += match &rest[..unit_len] {
+            "h" => n * 3600,
+            "m" | "min" => n * 60,
+            "s" | "sec" => n,
+            _ => return None,
+        };
+        rest = &rest[unit_len..];
+    }
+    Some(Duration::from_secs(secs))
+}
+
+fn list_item(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    line.strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))
+        .or_else(|| {
+            let (n, rest) = line.split_once(". ")?;
+            n.parse::<u32>().ok().map(|_| rest)
+        })
+        .map(str::trim)
+}
+
+fn parse_item(item: &str) -> Result<WorkoutSetElement> {
+    if item.ends_with(')') && item.contains(" x (") {
+        return parse_intervals(item, parse_md_dur);
+    }
+    let (first, rest) = item.split_once(' ').unwrap_or((item, ""));
+    if TEMPLATES.iter().any(|(t, _)| *t == first) {
+        return parse_template(first, rest, parse_md_dur);
+    }
+
+    let (item, options) = split_options(item)?;
+    let Some((name, amount)) = item.rsplit_once(' ') else {
+        return Err(anyhow::Error::msg(format!(
+            "No amount provided for excercise: {item}"
+        )));
+    };
+
+    if name.eq_ignore_ascii_case("rest") {
+        return Ok(WorkoutSetElement::Rest {
+            duration: parse_md_dur(amount).ok_or_else(|| unexpected(DURATION, amount))?,
+        });
+    }
+
+    let amount = if amount == "xF" {
+        ExcerciseAmout::Failure
+    } else if let Some(reps) = amount.strip_prefix('x') {
+        ExcerciseAmout::Reps(
+            reps.parse()
+                .map_err(|_| unexpected("reps like x12", amount))?,
+        )
+    } else if let Some(cal) = amount.strip_suffix("cal") {
+        ExcerciseAmout::Calories(
+            cal.parse()
+                .map_err(|_| unexpected("calories like 20cal", amount))?,
+        )
+    } else if let Some(meters) = amount.strip_suffix("meters") {
+        // `m` is minutes here
+        ExcerciseAmout::Meters(
+            meters
+                .parse()
+                .map_err(|_| unexpected("meters like 500meters", amount))?,
+        )
+    } else {
+        let midbeep = amount.ends_with('"');
+        ExcerciseAmout::Time {
+            duration: parse_md_dur(amount.trim_end_matches('"'))
+                .ok_or_else(|| unexpected("an amount like 30s, x12, 20cal or 500meters", amount))?,
+            midbeep,
+        }
+    };
+    Ok(WorkoutSetElement::Excercise {
+        name: name.to_owned(),
+        amount,
+        options,
+    })
+}
+
+// (indent, reps, rest, parts) of the circuits still open, innermost last
+type OpenCircuits = Vec<(usize, u16, Option<CircuitRest>, Vec<WorkoutSetElement>)>;
+
+fn push_element(circuits: &mut OpenCircuits, set: &mut WorkoutSet, element: WorkoutSetElement) {
+    match circuits.last_mut() {
+        Some((_, _, _, parts)) => parts.push(element),
+        None => set.parts.push(element),
+    }
+}
+
+fn close_circuits(circuits: &mut OpenCircuits, set: &mut WorkoutSet, indent: usize) {
+    while circuits.last().is_some_and(|(i, _, _, _)| *i >= indent) {
+        let (_, reps, rest, parts) = circuits.pop().unwrap();
+        push_element(
+            circuits,
+            set,
+            WorkoutSetElement::Circuit { reps, parts, rest },
+        );
+    }
+}
+
+pub fn load_markdown(source: &str) -> Result<Workout> {
+    let mut workout_name = None;
+    let mut lead_in = None;
+    let mut audio = None;
+    // groups carry over into the next sets
+    let mut group: Option<String> = None;
+    let mut sections: Vec<WorkoutSet> = vec![];
+    // circuits are nested by indenting their items
+    let mut circuits: OpenCircuits = vec![];
+
+    for (i, line) in source.lines().enumerate() {
+        // errors are about the line they're on
+        let mut parse_line = || -> Result<()> {
+            let trimmed = line.trim();
+
+            if let Some(heading) = trimmed.strip_prefix('#') {
+                if let Some(set) = sections.last_mut() {
+                    close_circuits(&mut circuits, set, 0);
+                }
+
+                let level = 1 + heading.chars().take_while(|c| *c == '#').count();
+                let heading = heading.trim_start_matches('#').trim();
+
+                if level == 1 && workout_name.is_none() {
+                    let (name, heading_lead_in, heading_audio) = split_workout_header(heading)?;
+                    workout_name = Some(name);
+                    lead_in = heading_lead_in;
+                    audio = heading_audio;
+                } else {
+                    let (heading, heading_lead_in) = split_lead_in(heading)?;
+                    let (name, reps) = parse_set_header(heading);
+                    sections.push(WorkoutSet {
+                        name: name.map(str::to_owned),
+                        parts: vec![],
+                        reps,
+                        set_rest: None,
+                        rest_after: None,
+                        lead_in: heading_lead_in,
+                        groups: group.iter().map(|g| (0, g.clone())).collect(),
+                    });
+                }
+                return Ok(());
+            }
+
+            let Some(item) = list_item(line) else {
+                return Ok(());
+            };
+            let indent = line.len() - line.trim_start().len();
+
+            if sections.is_empty() {
+                sections.push(WorkoutSet {
+                    name: None,
+                    parts: vec![],
+                    reps: 1,
+                    set_rest: None,
+                    rest_after: None,
+                    lead_in: None,
+                    groups: group.iter().map(|g| (0, g.clone())).collect(),
+                });
+            }
+            let set = sections.last_mut().unwrap();
+            close_circuits(&mut circuits, set, indent);
+
+            if let Some(rest) = item.strip_prefix("Set rest ") {
+                set.set_rest = Some(
+                    parse_md_dur(rest.trim()).ok_or_else(|| unexpected(DURATION, rest.trim()))?,
+                );
+                return Ok(());
+            }
+            if let Some(rest) = item.strip_prefix("Rest after set ") {
+                set.rest_after = Some(
+                    parse_md_dur(rest.trim()).ok_or_else(|| unexpected(DURATION, rest.trim()))?,
+                );
+                return Ok(());
+            }
+
+            if let Some(label) = item.strip_prefix("Group ") {
+                if !circuits.is_empty() {
+                    return Err(anyhow::Error::msg(format!(
+                        "Groups can't be inside a circuit: {item}"
+                    )));
+                }
+                let label = label.trim().to_owned();
+                set.groups.push((set.parts.len(), label.clone()));
+                group = Some(label);
+                return Ok(());
+            }
+
+            if let Some(header) = item.strip_prefix("Circuit x") {
+                let (reps, rest) = CircuitRest::parse_header(&format!("x{header}"), parse_md_dur)
+                    .ok_or_else(|| {
+                    unexpected(
+                        "`Circuit xN` or `Circuit xN rest 30s after each|pair|round`",
+                        item,
+                    )
+                })?;
+                circuits.push((indent, reps, rest, vec![]));
+                return Ok(());
+            }
+
+            push_element(&mut circuits, set, parse_item(item)?);
+            Ok(())
+        };
+        parse_line().map_err(|e| at_line(i + 1, line, e))?;
+    }
+
+    if let Some(set) = sections.last_mut() {
+        close_circuits(&mut circuits, set, 0);
+    }
+
+    let Some(name) = workout_name else {
+        return Err(anyhow::Error::msg(
+            "Didn't provide workout name (expected a `# Title` heading)",
+        ));
+    };
+
+    Ok(Workout {
+        name: name.to_owned(),
+        sections,
+        lead_in,
+        defines: vec![],
+        audio,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workout::format_workout;
+
+    #[test]
+    fn reads_markdown_durations() {
+        let workout = load_markdown("# T\n\n## Main x2\n\n- Push 1m30s\n- Rest 90sec\n").unwrap();
+        let set = &workout.sections[0];
+        assert_eq!(set.reps, 2);
+        assert!(matches!(
+            set.parts[0],
+            WorkoutSetElement::Excercise {
+                amount: ExcerciseAmout::Time { duration, .. },
+                ..
+            } if duration == Duration::from_secs(90)
+        ));
+        assert!(matches!(
+            set.parts[1],
+            WorkoutSetElement::Rest { duration } if duration == Duration::from_secs(90)
+        ));
+    }
+
+    fn error(source: &str) -> String {
+        match load_markdown(source) {
+            Ok(_) => panic!("parsed: {source}"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn errors_say_the_line_and_column() {
+        assert_eq!(
+            error("# T\n\n## Main\n\n- Push 30s\n- Rest 9q\n"),
+            "line 6, column 8: Expected a duration like 30s, 2m or 1m30s, got: 9q"
+        );
+        assert_eq!(
+            error("# T\n\n- Push xx\n"),
+            "line 3, column 8: Expected reps like x12, got: xx"
+        );
+    }
+
+    #[test]
+    fn reads_the_same_as_the_text_format() {
+        let markdown = load_markdown(
+            "# T\n\n## Main x2\n\n- Push 30s\"\n- Rest 15s\n- Curls x10\n- Set rest 1m\n",
+        )
+        .unwrap();
+        let text = Workout::parse(
+            "Workout T\n\nSet Main x2\n\tExcercise Push 00:30\"\n\tRest 00:15\n\tExcercise Curls x10\nSet rest 01:00\n",
+        )
+        .unwrap();
+        assert_eq!(format_workout(&markdown), format_workout(&text));
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use crate::{record::SessionRecord, session::StepKind, workout::ExcerciseAmout};
+
+/// How long things actually took in the history, for
+/// [`Workout::length_with`](crate::workout::Workout::length_with).
+#[derive(Default)]
+pub struct Measured {
+    // every untimed excercise done, with how long it took
+    excercises: Vec<(String, ExcerciseAmout, Duration)>,
+    /// The median of how long rests took over how long they were planned.
+    pub rest_factor: f64,
+}
+impl Measured {
+    pub fn new(records: &[SessionRecord]) -> Self {
+        let mut excercises = vec![];
+        let mut rests = vec![];
+        // forgotten ones would only throw it off
+        for step in records
+            .iter()
+            .flat_map(|r| &r.steps)
+            .filter(|s| s.idle.is_none() && !s.skipped)
+        {
+            match (&step.kind, step.planned) {
+                (StepKind::Excercise { name, amount, .. }, _)
+                    if !matches!(amount, ExcerciseAmout::Time { .. }) =>
+                {
+                    excercises.push((name.clone(), amount.clone(), step.duration))
+                }
+                (StepKind::Rest { .. } | StepKind::SetRest, Some(planned))
+                    if !planned.is_zero() =>
+                {
+                    rests.push(step.duration.as_secs_f64() / planned.as_secs_f64())
+                }
+                _ => {}
+            }
+        }
+
+        rests.sort_by(f64::total_cmp);
+        Self {
+            excercises,
+            rest_factor: rests.get(rests.len() / 2).copied().unwrap_or(1.),
+        }
+    }
+
+    /// The median time of the excercise, at this amount if it was ever done
+    /// at it.
+    pub fn excercise(&self, name: &str, amount: &ExcerciseAmout) -> Option<Duration> {
+        let of_name = self.excercises.iter().filter(|(n, ..)| n == name);
+        let mut times: Vec<_> = of_name
+            .clone()
+            .filter(|(_, a, _)| a == amount)
+            .map(|(.., d)| *d)
+            .collect();
+        if times.is_empty() {
+            times = of_name.map(|(.., d)| *d).collect();
+        }
+        times.sort();
+        times.get(times.len() / 2).copied()
+    }
+
+    pub fn rest(&self, planned: Duration) -> Duration {
+        planned.mul_f64(self.rest_factor)
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    collections::hash_map::RandomState,
+    fs,
+    hash::{BuildHasher, Hasher},
+};
+
+use anyhow::Result;
+
+use crate::dirs::config_dir;
+
+const BUILT_IN: &[&str] = &[
+    "That's another one done.",
+    "Strong work, keep it going.",
+    "You showed up, that's the hard part.",
+    "Every rep counts.",
+    "Breathe, you've got this.",
+    "Halfway there is still further than the couch.",
+    "Future you says thanks.",
+    "Consistency beats intensity.",
+    "Nice pace, hold on to it.",
+    "One set at a time.",
+];
+
+/// Lines to keep going by, the built in ones and those of `motivation.txt` in
+/// the config directory, shuffled once so a session doesn't repeat them
+/// until they run out.
+pub struct Motivation {
+    lines: Vec<String>,
+    next: usize,
+}
+impl Motivation {
+    pub fn load() -> Result<Self> {
+        let mut lines: Vec<_> = BUILT_IN.iter().map(|l| l.to_string()).collect();
+        let path = config_dir()?.join("motivation.txt");
+        if path.exists() {
+            lines.extend(
+                fs::read_to_string(&path)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                    .map(str::to_owned),
+            );
+        }
+
+        // xorshift, seeded by the hasher's random keys
+        let mut state = RandomState::new().build_hasher().finish() | 1;
+        for i in (1..lines.len()).rev() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            lines.swap(i, (state % (i as u64 + 1)) as usize);
+        }
+        Ok(Self { lines, next: 0 })
+    }
+
+    pub fn line(&mut self) -> Option<&str> {
+        if self.lines.is_empty() {
+            return None;
+        }
+        let line = &self.lines[self.next % self.lines.len()];
+        self.next += 1;
+        Some(line)
+    }
+}
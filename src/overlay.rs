@@ -0,0 +1,383 @@
+use std::{
+    env,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::font::glyph;
+
+const WIDTH: u32 = 480;
+// small enough for a single X request
+const HEIGHT: u32 = 120;
+const MARGIN: i32 = 40;
+
+const BACKGROUND: u32 = 0xE0202020;
+const FOREGROUND: u32 = 0xFFFFFFFF;
+
+/// A small always on top window with the countdown and what's going on, for
+/// when the terminal is behind a video or a browser.
+pub struct Overlay {
+    text: Sender<(String, String)>,
+}
+
+impl Overlay {
+    /// Opens it on Wayland (with the layer shell) if that's running, on X11
+    /// otherwise.
+    pub fn open() -> Result<Self> {
+        let (text, rx) = mpsc::channel();
+        let (ready_tx, ready) = mpsc::channel();
+        thread::spawn(move || {
+            let result = if env::var_os("WAYLAND_DISPLAY").is_some() {
+                wayland::run(rx, &ready_tx)
+            } else {
+                x11::run(rx, &ready_tx)
+            };
+            // after it was opened nobody's waiting for the error anymore
+            if let Err(e) = result {
+                if let Err(mpsc::SendError(Err(e))) = ready_tx.send(Err(e)) {
+                    eprintln!("The overlay closed: {e}");
+                }
+            }
+        });
+
+        ready
+            .recv()
+            .map_err(|_| anyhow::Error::msg("Couldn't open the overlay"))??;
+        Ok(Self { text })
+    }
+
+    pub fn show(&self, label: &str, time: &str) {
+        let _ = self.text.send((label.to_owned(), time.to_owned()));
+    }
+}
+
+// waits for the next text while still getting to the window's events
+fn next_text(rx: &Receiver<(String, String)>) -> Option<Option<(String, String)>> {
+    match rx.recv_timeout(Duration::from_millis(100)) {
+        Ok(text) => Some(Some(text)),
+        Err(RecvTimeoutError::Timeout) => Some(None),
+        Err(RecvTimeoutError::Disconnected) => None,
+    }
+}
+
+/// ARGB pixels: the label in small letters over the time in big ones.
+fn render(label: &str, time: &str) -> Vec<u32> {
+    let mut pixels = vec![BACKGROUND; (WIDTH * HEIGHT) as usize];
+    draw_text(&mut pixels, label, 3, 10);
+    draw_text(&mut pixels, time, 10, 40);
+    pixels
+}
+
+fn draw_text(pixels: &mut [u32], text: &str, scale: u32, top: u32) {
+    let advance = 6 * scale;
+    let fits = (WIDTH / advance) as usize;
+    let text: Vec<char> = text.to_uppercase().chars().take(fits).collect();
+    let left = (WIDTH - advance * text.len() as u32 + scale) / 2;
+
+    for (i, c) in text.iter().enumerate() {
+        let x0 = left + advance * i as u32;
+        for (row, bits) in glyph(*c).iter().enumerate() {
+            for col in 0..5 {
+                if bits & (0b10000 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    let y = top + row as u32 * scale + dy;
+                    let x = x0 + col * scale;
+                    let start = (y * WIDTH + x) as usize;
+                    pixels[start..start + scale as usize].fill(FOREGROUND);
+                }
+            }
+        }
+    }
+}
+
+fn bytes(pixels: &[u32]) -> Vec<u8> {
+    pixels.iter().flat_map(|p| p.to_le_bytes()).collect()
+}
+
+mod x11 {
+    use std::sync::mpsc::{Receiver, Sender};
+
+    use anyhow::Result;
+    use x11rb::{
+        connection::Connection,
+        protocol::{xproto::*, Event},
+        COPY_DEPTH_FROM_PARENT,
+    };
+
+    use super::{bytes, next_text, render, HEIGHT, MARGIN, WIDTH};
+
+    pub fn run(rx: Receiver<(String, String)>, ready: &Sender<Result<()>>) -> Result<()> {
+        let (conn, screen) = x11rb::connect(None)?;
+        let screen = &conn.setup().roots[screen];
+
+        // override redirect keeps the window manager out of it, so it's on
+        // top and never focused
+        let window = conn.generate_id()?;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            screen.root,
+            (screen.width_in_pixels as i16 - WIDTH as i16) / 2,
+            MARGIN as i16,
+            WIDTH as u16,
+            HEIGHT as u16,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new()
+                .override_redirect(1)
+                .background_pixel(screen.black_pixel)
+                .event_mask(EventMask::EXPOSURE),
+        )?;
+        let gc = conn.generate_id()?;
+        conn.create_gc(gc, window, &CreateGCAux::new())?;
+        conn.map_window(window)?;
+        conn.flush()?;
+        let _ = ready.send(Ok(()));
+
+        let mut pixels = render("", "");
+        let mut dirty = true;
+        while let Some(text) = next_text(&rx) {
+            if let Some((label, time)) = text {
+                pixels = render(&label, &time);
+                dirty = true;
+            }
+            while let Some(event) = conn.poll_for_event()? {
+                dirty |= matches!(event, Event::Expose(_));
+            }
+            if dirty {
+                conn.put_image(
+                    ImageFormat::Z_PIXMAP,
+                    window,
+                    gc,
+                    WIDTH as u16,
+                    HEIGHT as u16,
+                    0,
+                    0,
+                    0,
+                    screen.root_depth,
+                    &bytes(&pixels),
+                )?;
+                conn.flush()?;
+                dirty = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+mod wayland {
+    use std::sync::mpsc::{Receiver, Sender};
+
+    use anyhow::Result;
+    use smithay_client_toolkit::{
+        compositor::{CompositorHandler, CompositorState},
+        delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_shm,
+        output::{OutputHandler, OutputState},
+        reexports::client::{
+            globals::registry_queue_init,
+            protocol::{wl_output, wl_shm, wl_surface},
+            Connection, QueueHandle,
+        },
+        registry::{ProvidesRegistryState, RegistryState},
+        registry_handlers,
+        shell::{
+            wlr_layer::{
+                Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
+                LayerSurfaceConfigure,
+            },
+            WaylandSurface,
+        },
+        shm::{slot::SlotPool, Shm, ShmHandler},
+    };
+
+    use super::{bytes, next_text, render, HEIGHT, MARGIN, WIDTH};
+
+    struct State {
+        registry: RegistryState,
+        outputs: OutputState,
+        shm: Shm,
+        pool: SlotPool,
+        layer: LayerSurface,
+        pixels: Vec<u32>,
+        configured: bool,
+        closed: bool,
+    }
+
+    impl State {
+        fn draw(&mut self) -> Result<()> {
+            let (buffer, canvas) = self.pool.create_buffer(
+                WIDTH as i32,
+                HEIGHT as i32,
+                WIDTH as i32 * 4,
+                wl_shm::Format::Argb8888,
+            )?;
+            canvas.copy_from_slice(&bytes(&self.pixels));
+
+            let surface = self.layer.wl_surface();
+            surface.damage_buffer(0, 0, WIDTH as i32, HEIGHT as i32);
+            buffer.attach_to(surface)?;
+            self.layer.commit();
+            Ok(())
+        }
+    }
+
+    pub fn run(rx: Receiver<(String, String)>, ready: &Sender<Result<()>>) -> Result<()> {
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init(&conn)?;
+        let qh = queue.handle();
+
+        let compositor = CompositorState::bind(&globals, &qh)?;
+        let layer_shell = LayerShell::bind(&globals, &qh)?;
+        let shm = Shm::bind(&globals, &qh)?;
+
+        let surface = compositor.create_surface(&qh);
+        let layer =
+            layer_shell.create_layer_surface(&qh, surface, Layer::Overlay, Some("workout"), None);
+        layer.set_anchor(Anchor::TOP);
+        layer.set_margin(MARGIN, 0, 0, 0);
+        layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+        layer.set_size(WIDTH, HEIGHT);
+        // the first commit has no buffer, the compositor answers with a configure
+        layer.commit();
+
+        let mut state = State {
+            registry: RegistryState::new(&globals),
+            outputs: OutputState::new(&globals, &qh),
+            pool: SlotPool::new((WIDTH * HEIGHT * 4) as usize, &shm)?,
+            shm,
+            layer,
+            pixels: render("", ""),
+            configured: false,
+            closed: false,
+        };
+        queue.roundtrip(&mut state)?;
+        let _ = ready.send(Ok(()));
+
+        while let Some(text) = next_text(&rx) {
+            if let Some((label, time)) = text {
+                state.pixels = render(&label, &time);
+                if state.configured {
+                    state.draw()?;
+                }
+            }
+            queue.roundtrip(&mut state)?;
+            if state.closed {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    impl LayerShellHandler for State {
+        fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &LayerSurface) {
+            self.closed = true;
+        }
+
+        fn configure(
+            &mut self,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+            _: &LayerSurface,
+            _: LayerSurfaceConfigure,
+            _: u32,
+        ) {
+            if !self.configured {
+                self.configured = true;
+                if self.draw().is_err() {
+                    self.closed = true;
+                }
+            }
+        }
+    }
+
+    impl CompositorHandler for State {
+        fn scale_factor_changed(
+            &mut self,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+            _: &wl_surface::WlSurface,
+            _: i32,
+        ) {
+        }
+
+        fn transform_changed(
+            &mut self,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+            _: &wl_surface::WlSurface,
+            _: wl_output::Transform,
+        ) {
+        }
+
+        fn frame(
+            &mut self,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+            _: &wl_surface::WlSurface,
+            _: u32,
+        ) {
+        }
+
+        fn surface_enter(
+            &mut self,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+            _: &wl_surface::WlSurface,
+            _: &wl_output::WlOutput,
+        ) {
+        }
+
+        fn surface_leave(
+            &mut self,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+            _: &wl_surface::WlSurface,
+            _: &wl_output::WlOutput,
+        ) {
+        }
+    }
+
+    impl OutputHandler for State {
+        fn output_state(&mut self) -> &mut OutputState {
+            &mut self.outputs
+        }
+
+        fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+
+        fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {
+        }
+
+        fn output_destroyed(
+            &mut self,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+            _: wl_output::WlOutput,
+        ) {
+        }
+    }
+
+    impl ShmHandler for State {
+        fn shm_state(&mut self) -> &mut Shm {
+            &mut self.shm
+        }
+    }
+
+    impl ProvidesRegistryState for State {
+        fn registry(&mut self) -> &mut RegistryState {
+            &mut self.registry
+        }
+        registry_handlers![OutputState];
+    }
+
+    delegate_compositor!(State);
+    delegate_output!(State);
+    delegate_shm!(State);
+    delegate_layer!(State);
+    delegate_registry!(State);
+}
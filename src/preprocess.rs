@@ -0,0 +1,97 @@
+use anyhow::Result;
+
+struct Block {
+    line: usize,
+    condition: bool,
+    in_else: bool,
+    // `Circuit` and `Define` blocks share `End` with `If` but are kept in
+    // the output
+    kept: Option<&'static str>,
+}
+impl Block {
+    fn keeps(&self) -> bool {
+        self.condition != self.in_else
+    }
+}
+
+/// Resolves `If flag(NAME)` / `Else` / `End` blocks against the `--flag`s
+/// given. Dropped lines are left empty so line numbers stay the same.
+pub fn resolve_conditionals(source: &str, flags: &[String]) -> Result<String> {
+    let mut blocks: Vec<Block> = vec![];
+    let mut out = String::with_capacity(source.len());
+
+    for (i, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+
+        if let Some(condition) = trimmed.strip_prefix("If ") {
+            let Some(flag) = condition
+                .trim()
+                .strip_prefix("flag(")
+                .and_then(|f| f.strip_suffix(')'))
+            else {
+                return Err(anyhow::Error::msg(format!(
+                    "Expected `If flag(NAME)` on line {}",
+                    i + 1
+                )));
+            };
+            blocks.push(Block {
+                line: i + 1,
+                condition: flags.iter().any(|f| f == flag.trim()),
+                in_else: false,
+                kept: None,
+            });
+        } else if trimmed == "Else" {
+            match blocks.last_mut() {
+                Some(b) if !b.in_else && b.kept.is_none() => b.in_else = true,
+                _ => {
+                    return Err(anyhow::Error::msg(format!(
+                        "`Else` without `If` on line {}",
+                        i + 1
+                    )))
+                }
+            }
+        } else if trimmed == "End" {
+            match blocks.pop() {
+                Some(b) if b.kept.is_some() => {
+                    if blocks.iter().all(Block::keeps) {
+                        out.push_str(line);
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    return Err(anyhow::Error::msg(format!(
+                        "`End` without `If` on line {}",
+                        i + 1
+                    )))
+                }
+            }
+        } else if trimmed.starts_with("Circuit ") || trimmed == "Define" {
+            if blocks.iter().all(Block::keeps) {
+                out.push_str(line);
+            }
+            blocks.push(Block {
+                line: i + 1,
+                condition: true,
+                in_else: false,
+                kept: Some(if trimmed == "Define" {
+                    "Define"
+                } else {
+                    "Circuit"
+                }),
+            });
+        } else if blocks.iter().all(Block::keeps) {
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+
+    if let Some(b) = blocks.last() {
+        return Err(anyhow::Error::msg(format!(
+            "`{}` on line {} is missing its `End`",
+            b.kept.unwrap_or("If"),
+            b.line
+        )));
+    }
+
+    Ok(out)
+}
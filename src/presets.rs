@@ -0,0 +1,245 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::workout::{
+    parse_duration, ExcerciseAmout, ExcerciseOptions, Workout, WorkoutSet, WorkoutSetElement,
+};
+
+// seconds, or `MM:SS` in some exports
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Secs {
+    Number(f64),
+    Text(String),
+}
+impl Secs {
+    fn duration(&self) -> Result<Duration> {
+        match self {
+            Self::Number(secs) if *secs >= 0. => Ok(Duration::from_secs(secs.round() as u64)),
+            Self::Number(secs) => Err(anyhow::Error::msg(format!(
+                "Expected a duration, got: {secs}"
+            ))),
+            Self::Text(text) => parse_duration(text),
+        }
+    }
+}
+
+fn duration(secs: &Option<Secs>) -> Result<Duration> {
+    secs.as_ref().map_or(Ok(Duration::ZERO), Secs::duration)
+}
+
+// a Seconds Pro timer: its intervals one after the other, the whole list
+// done `numberOfSets` times
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SecondsTimer {
+    name: String,
+    intervals: Vec<SecondsInterval>,
+    #[serde(default, alias = "sets")]
+    number_of_sets: Option<u16>,
+    #[serde(default)]
+    rest_between_sets: Option<Secs>,
+}
+
+#[derive(Deserialize)]
+struct SecondsInterval {
+    #[serde(default)]
+    name: Option<String>,
+    duration: Secs,
+    /// `warmup`, `rest`, `cooldown` or whatever else for work.
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+}
+
+// an IntervalTimer preset: warm-up, rounds of high and low, cool-down
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntervalTimer {
+    name: String,
+    #[serde(default)]
+    warmup: Option<Secs>,
+    #[serde(alias = "work")]
+    high: Secs,
+    #[serde(default, alias = "rest")]
+    low: Option<Secs>,
+    rounds: u16,
+    #[serde(default)]
+    sets: Option<u16>,
+    #[serde(default)]
+    rest_between_sets: Option<Secs>,
+    #[serde(default)]
+    cooldown: Option<Secs>,
+}
+
+fn timed(name: &str, duration: Duration) -> WorkoutSetElement {
+    WorkoutSetElement::Excercise {
+        name: name.to_owned(),
+        amount: ExcerciseAmout::Time {
+            duration,
+            midbeep: false,
+        },
+        options: ExcerciseOptions::default(),
+    }
+}
+
+fn set(name: &str, parts: Vec<WorkoutSetElement>) -> WorkoutSet {
+    WorkoutSet {
+        name: Some(name.to_owned()),
+        parts,
+        reps: 1,
+        set_rest: None,
+        rest_after: None,
+        lead_in: None,
+        groups: vec![],
+    }
+}
+
+fn from_seconds(timer: SecondsTimer) -> Result<Workout> {
+    let (mut warm_up, mut main, mut cool_down) = (vec![], vec![], vec![]);
+    for interval in &timer.intervals {
+        let duration = interval.duration.duration()?;
+        if duration.is_zero() {
+            continue;
+        }
+        let kind = interval.kind.as_deref().unwrap_or_default().to_lowercase();
+        let name = interval.name.as_deref().unwrap_or("Work");
+        let part = match kind.as_str() {
+            "rest" => WorkoutSetElement::Rest { duration },
+            _ if name.eq_ignore_ascii_case("rest") => WorkoutSetElement::Rest { duration },
+            _ => timed(name, duration),
+        };
+        // only the ones before and after the work are left out of its sets
+        match kind.as_str() {
+            "warmup" if main.is_empty() => warm_up.push(part),
+            "cooldown" => cool_down.push(part),
+            _ => {
+                main.append(&mut cool_down);
+                main.push(part);
+            }
+        }
+    }
+    if main.is_empty() {
+        return Err(anyhow::Error::msg(format!(
+            "{} has no intervals to do",
+            timer.name
+        )));
+    }
+
+    let mut sections = vec![];
+    if !warm_up.is_empty() {
+        sections.push(set("Warm-up", warm_up));
+    }
+    let rest = duration(&timer.rest_between_sets)?;
+    sections.push(WorkoutSet {
+        reps: timer.number_of_sets.unwrap_or(1),
+        set_rest: (!rest.is_zero()).then_some(rest),
+        ..set(&timer.name, main)
+    });
+    if !cool_down.is_empty() {
+        sections.push(set("Cool-down", cool_down));
+    }
+    Ok(Workout {
+        name: timer.name,
+        sections,
+        lead_in: None,
+        defines: vec![],
+        audio: None,
+    })
+}
+
+fn from_interval_timer(timer: IntervalTimer) -> Result<Workout> {
+    let mut sections = vec![];
+    let warm_up = duration(&timer.warmup)?;
+    if !warm_up.is_zero() {
+        sections.push(set("Warm-up", vec![timed("Warm-up", warm_up)]));
+    }
+    let rest = duration(&timer.rest_between_sets)?;
+    sections.push(WorkoutSet {
+        reps: timer.sets.unwrap_or(1),
+        set_rest: (!rest.is_zero()).then_some(rest),
+        ..set(
+            "Intervals",
+            vec![WorkoutSetElement::Intervals {
+                name: "Work".to_owned(),
+                rounds: timer.rounds,
+                work: timer.high.duration()?,
+                rest: duration(&timer.low)?,
+            }],
+        )
+    });
+    let cool_down = duration(&timer.cooldown)?;
+    if !cool_down.is_zero() {
+        sections.push(set("Cool-down", vec![timed("Cool-down", cool_down)]));
+    }
+    Ok(Workout {
+        name: timer.name,
+        sections,
+        lead_in: None,
+        defines: vec![],
+        audio: None,
+    })
+}
+
+/// A preset exported from an interval timer app, told apart by its fields:
+/// a Seconds Pro timer has `intervals`, an IntervalTimer one `rounds` of
+/// `high` and `low`.
+pub fn import(source: &str) -> Result<Workout> {
+    let value: Value = serde_json::from_str(source)?;
+    if value.get("intervals").is_some() {
+        from_seconds(serde_json::from_value(value)?)
+    } else if value.get("rounds").is_some() {
+        from_interval_timer(serde_json::from_value(value)?)
+    } else {
+        Err(anyhow::Error::msg(
+            "Expected a Seconds Pro timer (with `intervals`) or an IntervalTimer preset (with `rounds`)",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workout::format_workout;
+
+    #[test]
+    fn imports_a_seconds_timer() {
+        let workout = import(
+            r#"{"name": "HIIT", "numberOfSets": 3, "restBetweenSets": 60, "intervals": [
+                {"name": "Warm-up", "duration": 120, "type": "warmup"},
+                {"name": "Burpees", "duration": "00:40"},
+                {"name": "Rest", "duration": 20},
+                {"name": "Stretch", "duration": 90, "type": "cooldown"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            format_workout(&workout),
+            "Format 2\nWorkout HIIT\n\nSet Warm-up\n\tExcercise Warm-up 02:00\n\nSet HIIT x3\n\tExcercise Burpees 00:40\n\tRest 00:20\nSet rest 01:00\n\nSet Cool-down\n\tExcercise Stretch 01:30\n"
+        );
+    }
+
+    #[test]
+    fn imports_an_interval_timer() {
+        let workout =
+            import(r#"{"name": "Tabata", "high": 20, "low": 10, "rounds": 8, "warmup": 60}"#)
+                .unwrap();
+        assert_eq!(workout.sections.len(), 2);
+        assert!(matches!(
+            workout.sections[1].parts[0],
+            WorkoutSetElement::Intervals { rounds: 8, work, rest, .. }
+                if work == Duration::from_secs(20) && rest == Duration::from_secs(10)
+        ));
+    }
+
+    #[test]
+    fn refuses_anything_else() {
+        let error = match import(r#"{"name": "T"}"#) {
+            Ok(_) => panic!("imported"),
+            Err(e) => e.to_string(),
+        };
+        assert!(error.starts_with("Expected a Seconds Pro timer"));
+    }
+}
@@ -0,0 +1,292 @@
+use anyhow::Result;
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+use crate::workout::split_format;
+
+pub struct ProgramDay {
+    pub label: Option<String>,
+    /// The day's workout, with every other line of the file left empty so
+    /// line numbers stay the same. `None` is a rest day, unless it has
+    /// `alternates`.
+    pub source: Option<String>,
+    /// Workout files (relative to the program) taking turns on this day, from
+    /// an `Alternate a.wk, b.wk` line.
+    pub alternates: Vec<String>,
+}
+impl ProgramDay {
+    pub fn is_rest(&self) -> bool {
+        self.source.is_none() && self.alternates.is_empty()
+    }
+
+    /// Which of the alternates (by workout name) is up after `last`, the
+    /// workout done on this day the last time.
+    pub fn next_alternate(names: &[String], last: Option<&str>) -> usize {
+        match last.and_then(|l| names.iter().position(|n| n == l)) {
+            Some(i) => (i + 1) % names.len(),
+            None => 0,
+        }
+    }
+}
+
+/// What [`Program::due`] has planned for today. All hold the (0 based) day
+/// to train next.
+pub enum Due {
+    Today(usize),
+    Rest(usize),
+    /// Already trained today.
+    Done(usize),
+}
+
+/// A week of the mesocycle.
+pub struct Week {
+    /// Work is scaled by this, 1 is as written.
+    pub intensity: f64,
+    pub label: Option<String>,
+}
+
+/// A `Program NAME` file: optionally a `Start YYYY-MM-DD` and a `Week N
+/// [XX%] [LABEL]` line for every week of the cycle, then `Day LABEL` lines,
+/// each followed by a normal workout (or nothing for a rest day).
+pub struct Program {
+    pub name: String,
+    pub start: Option<NaiveDate>,
+    /// Repeats once it's over, empty if the program has no weeks.
+    pub weeks: Vec<Week>,
+    pub days: Vec<ProgramDay>,
+}
+impl Program {
+    pub fn is_program(source: &str) -> bool {
+        source
+            .lines()
+            .map(str::trim_start)
+            .find(|l| !l.trim().is_empty() && !l.starts_with("Format "))
+            .is_some_and(|l| l.starts_with("Program "))
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        let (_, source) = split_format(source)?;
+        let lines: Vec<&str> = source.lines().collect();
+        let Some(first) = lines.iter().position(|l| !l.trim().is_empty()) else {
+            return Err(anyhow::Error::msg("Empty program"));
+        };
+        let Some(name) = lines[first].trim().strip_prefix("Program ") else {
+            return Err(anyhow::Error::msg("Didn't provide program name"));
+        };
+
+        let mut program_start = None;
+        let mut weeks = vec![];
+        let mut days = vec![];
+        let mut start = None;
+        for (i, line) in lines.iter().enumerate().skip(first + 1) {
+            let trimmed = line.trim();
+            if trimmed == "Day" || trimmed.starts_with("Day ") {
+                if let Some(s) = start {
+                    days.push(Self::day(&lines, s, i)?);
+                }
+                start = Some(i);
+            } else if start.is_some() {
+                // part of the day's workout
+                continue;
+            } else if let Some(date) = trimmed.strip_prefix("Start ") {
+                program_start = Some(NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(
+                    |_| {
+                        anyhow::Error::msg(format!(
+                            "Couldn't parse start date (YYYY-MM-DD) on line {}",
+                            i + 1
+                        ))
+                    },
+                )?);
+            } else if let Some(week) = trimmed.strip_prefix("Week ") {
+                weeks.push(Self::week(week, weeks.len() + 1, i + 1)?);
+            } else if !trimmed.is_empty() {
+                return Err(anyhow::Error::msg(format!(
+                    "Expected `Day` on line {}",
+                    i + 1
+                )));
+            }
+        }
+        if let Some(s) = start {
+            days.push(Self::day(&lines, s, lines.len())?);
+        }
+
+        if days.is_empty() {
+            return Err(anyhow::Error::msg("Program has no days"));
+        }
+        Ok(Self {
+            name: name.trim().to_owned(),
+            start: program_start,
+            weeks,
+            days,
+        })
+    }
+
+    // `N [XX%] [LABEL]`
+    fn week(s: &str, n: usize, line: usize) -> Result<Week> {
+        let (number, rest) = s.trim().split_once(' ').unwrap_or((s.trim(), ""));
+        if number.parse() != Ok(n) {
+            return Err(anyhow::Error::msg(format!(
+                "Expected `Week {n}` on line {line}"
+            )));
+        }
+
+        let rest = rest.trim();
+        let (intensity, label) = match rest.split_once(' ').unwrap_or((rest, "")) {
+            (percent, label) if percent.ends_with('%') => {
+                let percent: f64 = percent.trim_end_matches('%').parse().map_err(|_| {
+                    anyhow::Error::msg(format!("Couldn't parse intensity on line {line}"))
+                })?;
+                (percent / 100., label.trim())
+            }
+            _ => (1., rest),
+        };
+        Ok(Week {
+            intensity,
+            label: (!label.is_empty()).then(|| label.to_owned()),
+        })
+    }
+
+    /// The 0 based week of the cycle `date` falls into, counted from the
+    /// start date.
+    pub fn week_at(&self, date: NaiveDate) -> Option<usize> {
+        let start = self.start?;
+        if self.weeks.is_empty() {
+            return None;
+        }
+        let week = (date - start).num_days().max(0) / 7;
+        Some(week as usize % self.weeks.len())
+    }
+
+    pub fn week_name(&self, index: usize) -> String {
+        let week = &self.weeks[index];
+        let mut name = format!("week {}", index + 1);
+        if let Some(label) = &week.label {
+            name.push_str(&format!(" ({label})"));
+        }
+        if week.intensity != 1. {
+            name.push_str(&format!(" at {:.0}%", week.intensity * 100.));
+        }
+        name
+    }
+
+    fn day(lines: &[&str], start: usize, end: usize) -> Result<ProgramDay> {
+        let label = lines[start].trim()[3..].trim();
+        let label = (!label.is_empty()).then(|| label.to_owned());
+        let content: Vec<_> = (start + 1..end)
+            .filter(|&i| !lines[i].trim().is_empty())
+            .collect();
+
+        // `Alternate a.wk, b.wk` (or `Alternate: ...`) instead of a workout
+        if let Some(&first) = content.first() {
+            if let Some(files) = lines[first].trim().strip_prefix("Alternate") {
+                let alternates: Vec<_> = files
+                    .trim_start_matches(':')
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|f| !f.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+                if alternates.is_empty() || content.len() > 1 {
+                    return Err(anyhow::Error::msg(format!(
+                        "Expected only `Alternate FILE, FILE...` on line {}",
+                        first + 1
+                    )));
+                }
+                return Ok(ProgramDay {
+                    label,
+                    source: None,
+                    alternates,
+                });
+            }
+        }
+
+        Ok(ProgramDay {
+            label,
+            alternates: vec![],
+            source: (!content.is_empty()).then(|| {
+                let mut source = "\n".repeat(start + 1);
+                for line in &lines[start + 1..end] {
+                    source.push_str(line);
+                    source.push('\n');
+                }
+                source
+            }),
+        })
+    }
+
+    /// `3` is the third day, anything else is matched against the labels.
+    /// Returns the 0 based index.
+    pub fn find_day(&self, day: &str) -> Result<usize> {
+        let index = match day.parse::<usize>() {
+            Ok(n) if (1..=self.days.len()).contains(&n) => Some(n - 1),
+            Ok(_) => None,
+            Err(_) => self.days.iter().position(|d| {
+                d.label
+                    .as_deref()
+                    .is_some_and(|l| l.eq_ignore_ascii_case(day))
+            }),
+        };
+        index.ok_or_else(|| {
+            anyhow::Error::msg(format!(
+                "{} has no day {day} (it has {} days)",
+                self.name,
+                self.days.len()
+            ))
+        })
+    }
+
+    pub fn day_name(&self, index: usize) -> String {
+        match &self.days[index].label {
+            Some(label) => format!("day {} ({label})", index + 1),
+            None => format!("day {}", index + 1),
+        }
+    }
+
+    fn weekday(&self, index: usize) -> Option<Weekday> {
+        self.days[index].label.as_deref()?.parse().ok()
+    }
+
+    fn next_workout(&self, after: usize) -> usize {
+        (1..=self.days.len())
+            .map(|i| (after + i) % self.days.len())
+            .find(|&i| !self.days[i].is_rest())
+            .unwrap_or(after)
+    }
+
+    /// Picks the day to train `today`, given the completed `(date, day)`s of
+    /// this program, oldest first.
+    ///
+    /// If every day is labeled with a weekday (`Day mon`) it goes by the
+    /// calendar, otherwise the days follow each other, every rest day taking
+    /// up a day after the last session. Missed days are skipped either way.
+    pub fn due(&self, done: &[(NaiveDate, usize)], today: NaiveDate) -> Due {
+        let last = done.last().copied();
+
+        if (0..self.days.len()).all(|i| self.weekday(i).is_some()) {
+            let on = |date: NaiveDate| {
+                (0..self.days.len()).find(|&i| self.weekday(i) == Some(date.weekday()))
+            };
+            let next = (1..=7)
+                .filter_map(|d| today.checked_add_days(Days::new(d)))
+                .filter_map(on)
+                .find(|&i| !self.days[i].is_rest())
+                .unwrap_or(0);
+
+            return match on(today) {
+                Some(i) if last == Some((today, i)) => Due::Done(next),
+                Some(i) if !self.days[i].is_rest() => Due::Today(i),
+                _ => Due::Rest(next),
+            };
+        }
+
+        let Some((date, day)) = last else {
+            return Due::Today(self.next_workout(self.days.len() - 1));
+        };
+        let next = self.next_workout(day);
+        let rests = (next + self.days.len() - day - 1) % self.days.len();
+        match (today - date).num_days() {
+            0 => Due::Done(next),
+            since if since <= rests as i64 => Due::Rest(next),
+            _ => Due::Today(next),
+        }
+    }
+}
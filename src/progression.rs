@@ -0,0 +1,183 @@
+use std::{fmt::Display, time::Duration};
+
+use anyhow::Result;
+
+use crate::{
+    record::SessionRecord,
+    session::StepKind,
+    workout::{format_duration, parse_duration, ExcerciseAmout, Workout, WorkoutSetElement},
+};
+
+#[derive(Clone)]
+pub enum ProgressStep {
+    /// Reps, calories or meters.
+    Add(u16),
+    AddTime(Duration),
+    To(ExcerciseAmout),
+}
+
+/// `progress +2 after 3`: every time the excercise was done as prescribed in
+/// `after` completed sessions in a row, the step is applied. `progress x15
+/// after 3` goes there once instead.
+#[derive(Clone)]
+pub struct Progression {
+    pub step: ProgressStep,
+    pub after: u16,
+}
+impl Progression {
+    pub fn parse(s: &str) -> Result<Self> {
+        let error = || {
+            anyhow::Error::msg(format!(
+                "Expected `progress +N|+MM:SS|AMOUNT after SESSIONS`, got: {s}"
+            ))
+        };
+
+        let (step, after) = s.trim().split_once(" after ").ok_or_else(error)?;
+        let step = match step.trim().strip_prefix('+') {
+            Some(time) if time.contains(':') => {
+                ProgressStep::AddTime(parse_duration(time).map_err(|_| error())?)
+            }
+            Some(n) => ProgressStep::Add(n.parse().map_err(|_| error())?),
+            None => ProgressStep::To(ExcerciseAmout::parse(step.trim())?),
+        };
+        let after = after.trim().parse().map_err(|_| error())?;
+        if after == 0 {
+            return Err(error());
+        }
+        Ok(Self { step, after })
+    }
+
+    fn apply(&self, amount: &ExcerciseAmout) -> Result<ExcerciseAmout> {
+        Ok(match (&self.step, amount) {
+            (ProgressStep::To(to), _) => to.clone(),
+            (ProgressStep::Add(n), ExcerciseAmout::Reps(r)) => {
+                ExcerciseAmout::Reps(r.saturating_add(*n))
+            }
+            (ProgressStep::Add(n), ExcerciseAmout::Calories(c)) => {
+                ExcerciseAmout::Calories(c.saturating_add(*n))
+            }
+            (ProgressStep::Add(n), ExcerciseAmout::Meters(m)) => {
+                ExcerciseAmout::Meters(m.saturating_add(*n as u32))
+            }
+            (ProgressStep::AddTime(d), ExcerciseAmout::Time { duration, midbeep }) => {
+                ExcerciseAmout::Time {
+                    duration: *duration + *d,
+                    midbeep: *midbeep,
+                }
+            }
+            _ => {
+                return Err(anyhow::Error::msg(format!(
+                    "Can't progress {} with `{self}`",
+                    amount.text()
+                )))
+            }
+        })
+    }
+}
+impl Display for Progression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.step {
+            ProgressStep::Add(n) => write!(f, "+{n}")?,
+            ProgressStep::AddTime(d) => write!(f, "+{}", format_duration(*d))?,
+            ProgressStep::To(amount) => write!(f, "{}", amount.text())?,
+        }
+        write!(f, " after {}", self.after)
+    }
+}
+
+// the amount it ends up at after the sessions, oldest first
+fn progress(
+    name: &str,
+    amount: &ExcerciseAmout,
+    progression: &Progression,
+    sessions: &[SessionRecord],
+) -> Result<ExcerciseAmout> {
+    let mut current = amount.clone();
+    let mut clean = 0;
+    for session in sessions {
+        let steps: Vec<_> = session
+            .steps
+            .iter()
+            .filter_map(|s| match &s.kind {
+                StepKind::Excercise {
+                    name: n, amount, ..
+                } if n == name => Some((amount, s.reps, s.skipped)),
+                _ => None,
+            })
+            .collect();
+        // skipped, or done at something else (e.g. a deload week)
+        if steps.is_empty()
+            || steps
+                .iter()
+                .any(|(a, _, skipped)| **a != current || *skipped)
+        {
+            continue;
+        }
+
+        // fell short of the reps, when they're collected
+        let short = steps.iter().any(|(a, reps, _)| match (a, reps) {
+            (ExcerciseAmout::Reps(planned), Some(done)) => done < planned,
+            _ => false,
+        });
+        if short {
+            clean = 0;
+            continue;
+        }
+
+        clean += 1;
+        if clean == progression.after {
+            current = progression.apply(&current)?;
+            clean = 0;
+        }
+    }
+    Ok(current)
+}
+
+/// Applies every `progress` rule of the workout, going by its completed
+/// sessions in the history (only loaded if there are rules). Returns what
+/// changed.
+pub fn apply(
+    workout: &mut Workout,
+    history: impl FnOnce() -> Result<Vec<SessionRecord>>,
+) -> Result<Vec<String>> {
+    let mut rules = false;
+    workout.visit_elements_mut(|p| {
+        if let WorkoutSetElement::Excercise { options, .. } = p {
+            rules |= options.progress.is_some();
+        }
+    });
+    if !rules {
+        return Ok(vec![]);
+    }
+
+    let sessions: Vec<_> = history()?
+        .into_iter()
+        .filter(|r| r.completed && r.workout == workout.name)
+        .collect();
+    let mut changes = vec![];
+    let mut result = Ok(());
+    workout.visit_elements_mut(|p| {
+        let WorkoutSetElement::Excercise {
+            name,
+            amount,
+            options,
+        } = p
+        else {
+            return;
+        };
+        let Some(progression) = &options.progress else {
+            return;
+        };
+
+        match progress(name, amount, progression, &sessions) {
+            Ok(next) if next != *amount => {
+                changes.push(format!("{name}: {} -> {}", amount.text(), next.text()));
+                *amount = next;
+            }
+            Ok(_) => {}
+            Err(e) => result = Err(e),
+        }
+    });
+    result?;
+    Ok(changes)
+}
@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    session::{SetInfo, StepKind},
+    workout::{Collect, Weight},
+};
+
+// durations are stored as (fractional) seconds
+pub(crate) mod secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_f64(d.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_secs_f64(f64::deserialize(d)?))
+    }
+}
+
+pub(crate) mod opt_secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match d {
+            Some(d) => s.serialize_some(&d.as_secs_f64()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<f64>::deserialize(d)?.map(Duration::from_secs_f64))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StepRecord {
+    pub set: usize,
+    pub set_rep: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub element: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub kind: StepKind,
+    #[serde(default, with = "opt_secs", skip_serializing_if = "Option::is_none")]
+    pub planned: Option<Duration>,
+    /// Since the start of the session.
+    #[serde(with = "secs")]
+    pub started: Duration,
+    #[serde(with = "secs")]
+    pub duration: Duration,
+    /// Counted after going to failure, or collected for a coach.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reps: Option<u16>,
+    /// What the workout asked to collect, answered after the session.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collect: Vec<Collect>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpe: Option<f32>,
+    /// The load it was done with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<Weight>,
+    /// Whether there's a video of it.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub video: bool,
+    /// How long it sat auto-paused waiting for input, not in `duration`.
+    #[serde(default, with = "opt_secs", skip_serializing_if = "Option::is_none")]
+    pub idle: Option<Duration>,
+    /// Whether a key press said it was done, when that was asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledged: Option<bool>,
+    /// Gone past with `n`, or along with its prep or question.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub skipped: bool,
+}
+
+fn is_false(v: &bool) -> bool {
+    !v
+}
+
+/// Why a session was finished early, asked right after.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AbortReason {
+    OutOfTime,
+    Injury,
+    Equipment,
+    Fatigue,
+}
+impl AbortReason {
+    pub const ALL: [Self; 4] = [
+        Self::OutOfTime,
+        Self::Injury,
+        Self::Equipment,
+        Self::Fatigue,
+    ];
+
+    /// Its number in the question (from 1), or its name or the start of it.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_lowercase();
+        if s.is_empty() {
+            return None;
+        }
+        if let Ok(n) = s.parse::<usize>() {
+            return Self::ALL.get(n.checked_sub(1)?).copied();
+        }
+        Self::ALL.into_iter().find(|r| r.name().starts_with(&s))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::OutOfTime => "out of time",
+            Self::Injury => "injury",
+            Self::Equipment => "equipment",
+            Self::Fatigue => "fatigue",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub workout: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub completed: bool,
+    /// Where to pick up a session that was finished early, same as `--from`
+    /// but 0 based.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_from: Option<(u16, u16, u16)>,
+    /// How far into the excercise at `resume_from`, if it's timed.
+    #[serde(default, with = "opt_secs", skip_serializing_if = "Option::is_none")]
+    pub resume_offset: Option<Duration>,
+    /// Why it was finished early, if that was said.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_reason: Option<AbortReason>,
+    /// The program file the workout came from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program: Option<String>,
+    /// 0 based day of the program.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<usize>,
+    /// 0 based week of the program's cycle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub week: Option<usize>,
+    /// Labels like `travel`, to filter the stats by.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub sets: Vec<SetInfo>,
+    #[serde(default)]
+    pub steps: Vec<StepRecord>,
+    /// What was changed about the rest of the session while it was paused.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edits: Vec<String>,
+}
+impl SessionRecord {
+    /// Without the idle gaps of excercises that were auto-paused.
+    pub fn duration(&self) -> Duration {
+        let idle: Duration = self.steps.iter().filter_map(|s| s.idle).sum();
+        (self.end - self.start)
+            .to_std()
+            .unwrap_or_default()
+            .saturating_sub(idle)
+    }
+
+    /// Time spent in each labeled group, in the order they came up.
+    pub fn group_times(&self) -> Vec<(&str, Duration)> {
+        let mut times: Vec<(&str, Duration)> = vec![];
+        for step in &self.steps {
+            let Some(group) = &step.group else {
+                continue;
+            };
+            match times.iter_mut().find(|(g, _)| g == group) {
+                Some((_, time)) => *time += step.duration,
+                None => times.push((group, step.duration)),
+            }
+        }
+        times
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::workout::{load_workout, Workout, WorkoutSet, WorkoutSetElement};
+
+// the stretches to pick from, per side ones switch halfway
+const POOL: &str = include_str!("recovery.wk");
+const TRANSITION: Duration = Duration::from_secs(10);
+
+/// A gentle session of at most `duration` from the bundled stretches. Every
+/// `day` starts somewhere else in the pool, so rest days don't all look the
+/// same.
+pub fn generate(duration: Duration, day: usize) -> Result<Workout> {
+    let pool = load_workout(POOL, &[])?;
+    let stretches = &pool.sections[0].parts;
+
+    let mut parts = vec![];
+    let mut length = Duration::ZERO;
+    for stretch in stretches.iter().cycle().skip(day % stretches.len()) {
+        let transition = if parts.is_empty() {
+            Duration::ZERO
+        } else {
+            TRANSITION
+        };
+        if length + transition + stretch.length() > duration {
+            break;
+        }
+        if !transition.is_zero() {
+            parts.push(WorkoutSetElement::Rest {
+                duration: transition,
+            });
+        }
+        parts.push(stretch.clone());
+        length += transition + stretch.length();
+    }
+    if parts.is_empty() {
+        return Err(anyhow::Error::msg(
+            "That's too short for a recovery session",
+        ));
+    }
+
+    Ok(Workout {
+        name: "Active recovery".to_owned(),
+        sections: vec![WorkoutSet {
+            name: Some("Mobility".to_owned()),
+            parts,
+            reps: 1,
+            set_rest: None,
+            rest_after: None,
+            lead_in: Some(Duration::from_secs(5)),
+            groups: vec![],
+        }],
+        lead_in: None,
+        defines: vec![],
+        audio: None,
+    })
+}
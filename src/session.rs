@@ -0,0 +1,2471 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt::{Display, Write},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::reap,
+    config::Units,
+    locale,
+    measured::Measured,
+    motivation::Motivation,
+    record::{SessionRecord, StepRecord},
+    workout::{
+        format_duration, BeepLevel, Collect, ExcerciseAmout, Pan, Sound, Weight, Workout,
+        WorkoutSetElement, PRE_SECTION_WAIT, REST_END_WARNING,
+    },
+};
+
+/// The countdown before the first excercise, unless the config says.
+pub const PRE_ROLL: Duration = Duration::from_secs(6);
+/// How long to wait for an answer before including an optional excercise.
+pub const OPTIONAL_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a key press acknowledging a timed excercise is waited for.
+pub const ACKNOWLEDGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct Cue {
+    /// Offset from the start of the step.
+    pub at: Duration,
+    pub sound: Option<Sound>,
+    pub message: Option<String>,
+    pub event: CueEvent,
+}
+
+/// What a cue marks, so it can be panned in the config.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum CueEvent {
+    Start,
+    /// The midpoint of a per-side excercise.
+    Switch,
+    End,
+    Countdown,
+    /// Before a rest ends.
+    Rest,
+    /// Escalating beeps, and passing the last time.
+    Warning,
+    /// Just a message.
+    Note,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum StepKind {
+    Countdown,
+    Excercise {
+        /// Counts only the excercises of the set, like the `--from` position.
+        index: usize,
+        name: String,
+        amount: ExcerciseAmout,
+        #[serde(
+            default,
+            with = "crate::record::opt_secs",
+            skip_serializing_if = "Option::is_none"
+        )]
+        estimate: Option<Duration>,
+    },
+    Rest {
+        next: Option<String>,
+    },
+    /// Setting up for the excercise after it.
+    Prep {
+        name: String,
+    },
+    /// Asking whether to do the optional excercise after it.
+    Optional {
+        name: String,
+    },
+    /// Waiting for a key press saying the excercise before it was done.
+    Acknowledge {
+        name: String,
+    },
+    SetRest,
+}
+
+/// What happens in a running session, in the order it does, for
+/// [`WorkoutObserver`]s.
+#[derive(Clone)]
+pub enum Event {
+    /// From the set, its round and the excercise, `(0, 0, 0)` from the start.
+    WorkoutStarted {
+        from: (u16, u16, u16),
+    },
+    /// The first round of a set, with its countdown.
+    SetStarted {
+        set: usize,
+    },
+    /// Every round after the first.
+    SetRepeated {
+        set: usize,
+        rep: u16,
+    },
+    /// A line of the motivation between sets.
+    Motivation {
+        line: String,
+    },
+    ExcerciseStarted {
+        index: usize,
+        name: String,
+        amount: ExcerciseAmout,
+        estimate: Option<Duration>,
+    },
+    RestStarted {
+        duration: Duration,
+        next: Option<String>,
+    },
+    /// Between rounds or sets, the next one's lead-in included.
+    SetRestStarted {
+        duration: Duration,
+    },
+    PrepStarted {
+        name: String,
+        duration: Duration,
+    },
+    /// Whether to do an optional excercise, it's done unless declined.
+    OptionalAsked {
+        name: String,
+        timeout: Duration,
+    },
+    /// Whether an excercise was done, see [`Session::require_acknowledgement`].
+    AcknowledgeAsked {
+        name: String,
+        timeout: Duration,
+    },
+    /// A few seconds before a rest is over.
+    RestEnding {
+        remaining: Duration,
+    },
+    /// One of the current step's cues, a line, a sound or both.
+    Cue {
+        event: CueEvent,
+        message: Option<String>,
+        sound: Option<Sound>,
+    },
+    /// The rest after a rep excercise changed for the reps typed in, see
+    /// [`Session::adapt_rest`].
+    RestAdapted {
+        done: u16,
+        target: u16,
+        from: Duration,
+        to: Duration,
+    },
+    /// The current excercise (or rest) gone past, or an optional one declined.
+    Skipped,
+    /// Back to the excercise before.
+    WentBack {
+        name: String,
+    },
+    /// The current excercise (or rest) started over.
+    Restarted,
+    /// Paused by itself after waiting this long for input, see
+    /// [`Session::set_idle_pause`].
+    Idle {
+        after: Duration,
+    },
+    WorkoutFinished {
+        /// Not finished early.
+        completed: bool,
+    },
+}
+
+/// Told everything that happens in a running session, for another frontend
+/// than the terminal's lines, like a log or a web page.
+pub trait WorkoutObserver {
+    fn event(&mut self, event: &Event);
+}
+
+/// How late a step started or a cue played, for `--timing-report`.
+pub struct Timing {
+    pub step: usize,
+    /// Since the start of the session.
+    pub intended: Duration,
+    pub late: Duration,
+    pub cue: bool,
+}
+
+#[derive(Clone)]
+pub struct Step {
+    pub set: usize,
+    pub set_rep: u16,
+    pub element: Option<usize>,
+    pub group: Option<String>,
+    pub kind: StepKind,
+    /// `None` if the step only ends through [`Session::advance`].
+    pub duration: Option<Duration>,
+    pub cues: Vec<Cue>,
+    pub collect: Vec<Collect>,
+    /// The load it's done with, for the history.
+    pub weight: Option<Weight>,
+    /// The phases of its tempo, clicked while it's on, if it has a
+    /// `metronome`.
+    pub metronome: Vec<Duration>,
+}
+
+pub struct Progress {
+    /// 0 to 100, weighed by planned time.
+    pub percent: f64,
+    pub set: usize,
+    pub set_name: Option<String>,
+    pub set_rep: u16,
+    pub set_reps: u16,
+    /// Index into the set's parts, `None` outside of them (countdowns, set rests).
+    pub element: Option<usize>,
+    pub excercise: Option<usize>,
+    pub excercise_name: Option<String>,
+    /// Time left of the current step, `None` if it waits for the user.
+    pub remaining: Option<Duration>,
+    /// Planned time left of the whole session, without what waits for the
+    /// user.
+    pub left: Duration,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum State {
+    Countdown,
+    Preparing,
+    /// Waiting for [`Session::decline`], includes the excercise on timeout.
+    Confirming,
+    /// Waiting for [`Session::acknowledge`], moves on without it on timeout.
+    Acknowledging,
+    Excercising,
+    Resting,
+    Paused,
+    AwaitingInput,
+    Finished,
+}
+
+fn rest_cues(duration: Duration, indent: &str) -> Vec<Cue> {
+    match duration.checked_sub(REST_END_WARNING) {
+        Some(at) if !at.is_zero() => vec![Cue {
+            at,
+            sound: Some(Sound::Beep(BeepLevel::Mid)),
+            message: Some(format!("{indent}{}s left", REST_END_WARNING.as_secs())),
+            event: CueEvent::Rest,
+        }],
+        _ => vec![],
+    }
+}
+
+// a beep for each of the last `seconds` seconds
+fn countdown_cues(duration: Duration, seconds: u64) -> Vec<Cue> {
+    (1..=seconds)
+        .rev()
+        .filter_map(|s| duration.checked_sub(Duration::from_secs(s)))
+        .map(|at| Cue {
+            at,
+            sound: Some(Sound::Beep(BeepLevel::Mid)),
+            message: None,
+            event: CueEvent::Countdown,
+        })
+        .collect()
+}
+
+// a line every second, and a beep after the first one, when the session's
+// starting beeps are still playing
+fn pre_roll_cues(duration: Duration) -> Vec<Cue> {
+    let seconds = duration.as_secs();
+    (0..seconds)
+        .map(|s| Cue {
+            at: Duration::from_secs(s),
+            sound: (s > 0).then_some(Sound::Beep(BeepLevel::Mid)),
+            message: Some(format!("Starting in {}...", seconds - s)),
+            event: CueEvent::Countdown,
+        })
+        .collect()
+}
+
+fn step_cues(kind: &StepKind, duration: Option<Duration>) -> Vec<Cue> {
+    match kind {
+        StepKind::Countdown => countdown_cues(duration.unwrap_or_default(), 3),
+        StepKind::Excercise { amount, .. } => {
+            let mut cues = vec![Cue {
+                at: Duration::ZERO,
+                sound: Some(Sound::Beep(BeepLevel::High)),
+                message: None,
+                event: CueEvent::Start,
+            }];
+            if let Some(duration) = duration {
+                if let ExcerciseAmout::Time { midbeep: true, .. } = amount {
+                    cues.push(Cue {
+                        at: duration.div_f64(2.),
+                        sound: Some(Sound::Beep(BeepLevel::Mid)),
+                        message: Some("    Reached midpoint".to_owned()),
+                        event: CueEvent::Switch,
+                    });
+                }
+                cues.push(Cue {
+                    at: duration,
+                    sound: Some(Sound::Beep(BeepLevel::Low)),
+                    message: None,
+                    event: CueEvent::End,
+                });
+            }
+            cues
+        }
+        StepKind::Rest { .. } | StepKind::Prep { .. } => {
+            rest_cues(duration.unwrap_or_default(), "    ")
+        }
+        StepKind::Optional { .. } | StepKind::Acknowledge { .. } => vec![],
+        StepKind::SetRest => rest_cues(duration.unwrap_or_default(), "  "),
+    }
+}
+
+// halving the time left between beeps, so they speed up towards the end
+fn escalating_cues(duration: Duration) -> Vec<Cue> {
+    let mut cues = vec![];
+    let mut left = duration / 2;
+    // a beep is half a second long, closer ones would just blur together
+    while left >= Duration::from_secs(1) {
+        cues.push(Cue {
+            at: duration - left,
+            sound: Some(Sound::Beep(BeepLevel::Mid)),
+            message: None,
+            event: CueEvent::Warning,
+        });
+        left /= 2;
+    }
+    cues
+}
+
+/// Where the steps of an element belong.
+struct Position<'a> {
+    set: usize,
+    set_rep: u16,
+    element: usize,
+    group: Option<&'a str>,
+    /// The `index` of its excercise steps.
+    excercise: usize,
+}
+impl Position<'_> {
+    fn step(&self, kind: StepKind, duration: Option<Duration>, cues: Vec<Cue>) -> Step {
+        Step {
+            set: self.set,
+            set_rep: self.set_rep,
+            element: Some(self.element),
+            group: self.group.map(str::to_owned),
+            kind,
+            duration,
+            cues,
+            collect: vec![],
+            weight: None,
+            metronome: vec![],
+        }
+    }
+}
+
+// e.g. `3×8 @ 60kg, RPE 8`, every time it came up in the last session with it
+fn last_performance(history: &[SessionRecord], name: &str) -> Option<String> {
+    let is = |s: &&StepRecord| matches!(&s.kind, StepKind::Excercise { name: n, .. } if n == name);
+    let record = history
+        .iter()
+        .filter(|r| r.steps.iter().any(|s| is(&s)))
+        .max_by_key(|r| r.start)?;
+    let steps: Vec<_> = record.steps.iter().filter(is).collect();
+
+    let done: Vec<_> = steps
+        .iter()
+        .map(|s| match (&s.kind, s.reps) {
+            (_, Some(reps)) => reps.to_string(),
+            (StepKind::Excercise { amount, .. }, None) => match amount {
+                ExcerciseAmout::Reps(reps) => reps.to_string(),
+                ExcerciseAmout::Time { .. } => format_duration(s.duration),
+                amount => amount.to_string(),
+            },
+            _ => String::new(),
+        })
+        .collect();
+    let mut out = match done.iter().all(|d| *d == done[0]) {
+        true if done.len() > 1 => format!("{}×{}", done.len(), done[0]),
+        true => done[0].clone(),
+        false => done.join(", "),
+    };
+    if let Some(weight) = steps.iter().filter_map(|s| s.weight).next_back() {
+        let _ = write!(out, " @ {}", locale::weight(&weight));
+    }
+    if let Some(rpe) = steps.iter().filter_map(|s| s.rpe).next_back() {
+        let _ = write!(out, ", RPE {}", locale::number(rpe));
+    }
+    Some(out)
+}
+
+fn push_element(
+    steps: &mut Vec<Step>,
+    at: &Position,
+    p: &WorkoutSetElement,
+    next: Option<&WorkoutSetElement>,
+) {
+    if p.is_empty() {
+        return;
+    }
+    match p {
+        WorkoutSetElement::Excercise {
+            name,
+            amount,
+            options,
+        } => {
+            if options.optional {
+                let kind = StepKind::Optional { name: name.clone() };
+                steps.push(at.step(kind, Some(OPTIONAL_TIMEOUT), vec![]));
+            }
+            if let Some(prep) = options.prep {
+                let kind = StepKind::Prep { name: name.clone() };
+                steps.push(at.step(kind, Some(prep), rest_cues(prep, "    ")));
+            }
+
+            // a 00:00 one waits for enter
+            let duration = match amount {
+                ExcerciseAmout::Time { duration, .. } if !duration.is_zero() => Some(*duration),
+                _ => None,
+            };
+            let kind = StepKind::Excercise {
+                index: at.excercise,
+                name: name.clone(),
+                amount: amount.clone(),
+                estimate: options.estimate,
+            };
+
+            let mut cues = step_cues(&kind, duration);
+            if let (true, Some(duration)) = (options.escalate, duration) {
+                cues.extend(escalating_cues(duration));
+                cues.sort_by_key(|c| c.at);
+            }
+            if let Some(cue_override) = &options.cue {
+                for cue in &mut cues {
+                    if let Some(sound) = &cue.sound {
+                        cue.sound = cue_override.replace(sound);
+                    }
+                }
+            }
+            // how to do it, under the excercise's line
+            let weight = options.details.weight.map(|w| w.to_string());
+            let details = [
+                ("weight", &weight),
+                ("tempo", &options.details.tempo),
+                ("equipment", &options.details.equipment),
+                ("note", &options.details.note),
+                ("demo", &options.details.demo),
+            ];
+            let details = details.into_iter().filter_map(|(label, value)| {
+                Some(Cue {
+                    at: Duration::ZERO,
+                    sound: None,
+                    message: Some(format!("    {label}: {}", value.as_ref()?)),
+                    event: CueEvent::Note,
+                })
+            });
+            cues.splice(0..0, details);
+            // after the cue override, which is only about beeps
+            cues.extend(options.details.say.iter().map(|say| Cue {
+                at: say.at,
+                sound: Some(Sound::Speech(say.text.clone())),
+                message: Some(format!("    \"{}\"", say.text)),
+                event: CueEvent::Note,
+            }));
+            cues.sort_by_key(|c| c.at);
+
+            let mut step = at.step(kind, duration, cues);
+            step.collect = options.collect.clone();
+            step.weight = options.details.weight;
+            step.metronome = options.details.metronome().unwrap_or_default();
+            steps.push(step);
+        }
+        WorkoutSetElement::Rest { duration } => {
+            let next = match next {
+                Some(
+                    WorkoutSetElement::Excercise { name, .. }
+                    | WorkoutSetElement::Intervals { name, .. },
+                ) => Some(name.clone()),
+                _ => None,
+            };
+            let kind = StepKind::Rest { next };
+            let cues = step_cues(&kind, Some(*duration));
+            steps.push(at.step(kind, Some(*duration), cues));
+        }
+        WorkoutSetElement::Intervals {
+            name,
+            rounds,
+            work,
+            rest,
+        } => {
+            for round in 0..*rounds {
+                let work_kind = StepKind::Excercise {
+                    index: at.excercise,
+                    name: format!("{name} {}/{rounds}", round + 1),
+                    amount: ExcerciseAmout::Time {
+                        duration: *work,
+                        midbeep: false,
+                    },
+                    estimate: None,
+                };
+                let rest_kind = StepKind::Rest {
+                    next: (round + 1 < *rounds).then(|| name.clone()),
+                };
+                for (kind, duration) in [(work_kind, *work), (rest_kind, *rest)] {
+                    if duration.is_zero() {
+                        continue;
+                    }
+                    let cues = step_cues(&kind, Some(duration));
+                    steps.push(at.step(kind, Some(duration), cues));
+                }
+            }
+        }
+        WorkoutSetElement::Circuit { reps, parts, rest } => {
+            for round in 0..*reps {
+                let last = round + 1 == *reps;
+                let parts = match rest {
+                    Some(rest) => Cow::Owned(rest.round(parts, last)),
+                    None => Cow::Borrowed(parts),
+                };
+                let first = steps.len();
+                for (i, part) in parts.iter().enumerate() {
+                    // the next round starts after the last one's rest
+                    let next = parts.get(i + 1).or(parts.first().filter(|_| !last));
+                    push_element(steps, at, part, next);
+                }
+                if let Some(step) = steps.get_mut(first) {
+                    step.cues.insert(
+                        0,
+                        Cue {
+                            at: Duration::ZERO,
+                            sound: None,
+                            message: Some(format!("    Round {} / {reps}", round + 1)),
+                            event: CueEvent::Note,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Flattens the workout into the steps a session goes through, starting at
+/// `from` (set, set repetition, n-th excercise of the set).
+pub fn schedule(workout: &Workout, from: (u16, u16, u16)) -> Result<Vec<Step>> {
+    let mut from = (from.0 as usize, from.1, from.2 as usize);
+    // from the top, sets left out or without an excercise aren't started in
+    if from == (0, 0, 0) {
+        from.0 = workout
+            .sections
+            .iter()
+            .position(|s| s.reps > 0 && s.parts.iter().any(|p| p.is_excercise() && !p.is_empty()))
+            .ok_or_else(|| anyhow::Error::msg("There's no excercise to do in any set"))?;
+    }
+    let out_of_bounds = || anyhow::Error::msg("Starting position is out of bounds");
+
+    let Some(first_set) = workout.sections.get(from.0) else {
+        return Err(out_of_bounds());
+    };
+    if from.1 >= first_set.reps {
+        return Err(out_of_bounds());
+    }
+    let mut exes_left = from.2 + 1;
+    let first_element = first_set
+        .parts
+        .iter()
+        .position(|p| {
+            if p.is_excercise() {
+                exes_left -= 1;
+                return exes_left == 0;
+            }
+            false
+        })
+        .ok_or_else(out_of_bounds)?;
+
+    let mut steps = vec![Step {
+        set: from.0,
+        set_rep: from.1,
+        element: None,
+        group: None,
+        kind: StepKind::Countdown,
+        duration: Some(PRE_ROLL),
+        cues: pre_roll_cues(PRE_ROLL),
+        collect: vec![],
+        weight: None,
+        metronome: vec![],
+    }];
+
+    for (si, s) in workout.sections.iter().enumerate().skip(from.0) {
+        let lead_in = s.lead_in.or(workout.lead_in).unwrap_or(PRE_SECTION_WAIT);
+        let first_rep = if si == from.0 { from.1 } else { 0 };
+        for rep in first_rep..s.reps {
+            steps.push(Step {
+                set: si,
+                set_rep: rep,
+                element: None,
+                group: None,
+                kind: StepKind::Countdown,
+                duration: Some(lead_in),
+                cues: countdown_cues(lead_in, 3),
+                collect: vec![],
+                weight: None,
+                metronome: vec![],
+            });
+
+            let first_part = if si == from.0 && rep == from.1 {
+                first_element
+            } else {
+                0
+            };
+            let mut excercise_index = s.parts[..first_part]
+                .iter()
+                .filter(|p| p.is_excercise())
+                .count();
+            for (pi, p) in s.parts.iter().enumerate().skip(first_part) {
+                let at = Position {
+                    set: si,
+                    set_rep: rep,
+                    element: pi,
+                    group: s.group_of(pi),
+                    excercise: excercise_index,
+                };
+                push_element(&mut steps, &at, p, s.parts.get(pi + 1));
+                if p.is_excercise() {
+                    excercise_index += 1;
+                }
+            }
+
+            if rep + 1 < s.reps {
+                // eaten up by the lead-in, there's nothing left to rest
+                let dur = s.set_rest.map(|d| d.saturating_sub(lead_in));
+                if let Some(dur) = dur.filter(|d| !d.is_zero()) {
+                    steps.push(Step {
+                        set: si,
+                        set_rep: rep,
+                        element: None,
+                        group: s
+                            .group_of(s.parts.len().saturating_sub(1))
+                            .map(str::to_owned),
+                        kind: StepKind::SetRest,
+                        duration: Some(dur),
+                        cues: step_cues(&StepKind::SetRest, Some(dur)),
+                        collect: vec![],
+                        weight: None,
+                        metronome: vec![],
+                    });
+                }
+            }
+        }
+
+        // the next set's lead-in is part of it too
+        let next_lead_in = match workout.sections.get(si + 1) {
+            Some(next) => next.lead_in.or(workout.lead_in).unwrap_or(PRE_SECTION_WAIT),
+            None => continue,
+        };
+        let dur = s.rest_after.map(|d| d.saturating_sub(next_lead_in));
+        // a set done 0 times is left out, its rest with it
+        if let (Some(dur), true) = (dur.filter(|d| !d.is_zero()), s.reps > 0) {
+            steps.push(Step {
+                set: si,
+                set_rep: s.reps - 1,
+                element: None,
+                group: s
+                    .group_of(s.parts.len().saturating_sub(1))
+                    .map(str::to_owned),
+                kind: StepKind::SetRest,
+                duration: Some(dur),
+                cues: step_cues(&StepKind::SetRest, Some(dur)),
+                collect: vec![],
+                weight: None,
+                metronome: vec![],
+            });
+        }
+    }
+
+    Ok(steps)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SetInfo {
+    pub name: Option<String>,
+    pub reps: u16,
+}
+impl Display for SetInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name.as_deref().unwrap_or("[UNKNOWN]"))?;
+        if self.reps > 1 {
+            write!(f, " x{}", self.reps)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where to resume from, see [`Session::resume_position`].
+type Checkpoint = dyn Fn(Option<(u16, u16, u16)>);
+/// The steps done so far and where to resume from.
+type Snapshot = dyn Fn(&[StepRecord], Option<(u16, u16, u16)>);
+
+/// Drives a workout through its [`schedule`]. Front-ends call [`Session::tick`]
+/// whenever [`Session::next_deadline`] passes and [`Session::advance`] to
+/// finish steps waiting on the user.
+pub struct Session<B: Fn(&Sound)> {
+    from: (u16, u16, u16),
+    sets: Vec<SetInfo>,
+    steps: Vec<Step>,
+    current: usize,
+    session_start: Instant,
+    step_start: Instant,
+    next_cue: usize,
+    paused_at: Option<Instant>,
+    log: Vec<StepRecord>,
+    units: Units,
+    adaptive_rest: Option<f64>,
+    speed: f64,
+    redraw: Duration,
+    /// The step the metronome's clicking for.
+    clicking: Option<usize>,
+    muted: bool,
+    max_length: Option<Duration>,
+    edits: Vec<String>,
+    motivation: Option<Motivation>,
+    panning: BTreeMap<CueEvent, Pan>,
+    timings: Option<Vec<Timing>>,
+    acknowledging: bool,
+    tags: Vec<String>,
+    ask_tags: bool,
+    idle: Option<(Measured, f64)>,
+    /// When the current excercise was auto-paused.
+    idle_since: Option<Instant>,
+    idle_gap: Option<Duration>,
+    /// Told where to resume from whenever a step starts.
+    checkpoint: Option<Box<Checkpoint>>,
+    snapshot: Option<Box<Snapshot>>,
+    observers: Vec<Box<dyn WorkoutObserver>>,
+    /// The events printed as lines, like they always were.
+    printing: bool,
+    beep: B,
+}
+impl<B: Fn(&Sound)> Session<B> {
+    pub fn new(workout: &Workout, from: (u16, u16, u16), beep: B) -> Result<Self> {
+        let sets = workout
+            .sections
+            .iter()
+            .map(|s| SetInfo {
+                name: s.name.clone(),
+                reps: s.reps,
+            })
+            .collect();
+        let mut session = Self::with_steps(sets, schedule(workout, from)?, beep);
+        session.from = from;
+        Ok(session)
+    }
+
+    pub fn with_steps(sets: Vec<SetInfo>, steps: Vec<Step>, beep: B) -> Self {
+        let now = Instant::now();
+        Self {
+            from: (0, 0, 0),
+            sets,
+            steps,
+            current: 0,
+            session_start: now,
+            step_start: now,
+            next_cue: 0,
+            paused_at: None,
+            log: vec![],
+            units: Units::default(),
+            adaptive_rest: None,
+            speed: 1.,
+            redraw: Duration::from_secs(1),
+            clicking: None,
+            muted: false,
+            max_length: None,
+            edits: vec![],
+            motivation: None,
+            panning: BTreeMap::new(),
+            timings: None,
+            acknowledging: false,
+            tags: vec![],
+            ask_tags: false,
+            idle: None,
+            idle_since: None,
+            idle_gap: None,
+            checkpoint: None,
+            snapshot: None,
+            observers: vec![],
+            printing: true,
+            beep,
+        }
+    }
+
+    /// Saves where to resume from at every step, so it picks up there even
+    /// after the power went out.
+    pub fn set_checkpoint(&mut self, checkpoint: impl Fn(Option<(u16, u16, u16)>) + 'static) {
+        self.checkpoint = Some(Box::new(checkpoint));
+    }
+
+    /// Hands over the steps done so far whenever a step starts, to keep the
+    /// session somewhere in case it never gets to the end.
+    pub fn set_snapshot(
+        &mut self,
+        snapshot: impl Fn(&[StepRecord], Option<(u16, u16, u16)>) + 'static,
+    ) {
+        self.snapshot = Some(Box::new(snapshot));
+    }
+
+    /// Tells `observer` everything that happens from now on.
+    pub fn observe(&mut self, observer: Box<dyn WorkoutObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Leaves the lines of the steps and their cues to the observers.
+    pub fn set_printing(&mut self, printing: bool) {
+        self.printing = printing;
+    }
+
+    /// Prints one of its lines whenever a set is done.
+    pub fn set_motivation(&mut self, motivation: Motivation) {
+        self.motivation = Some(motivation);
+    }
+
+    /// Where the cues of each event are heard, over the default of `sides`
+    /// for the start and switch and centered for the rest.
+    pub fn set_panning(&mut self, panning: BTreeMap<CueEvent, Pan>) {
+        self.panning = panning;
+    }
+
+    /// Labels for the session, like `travel`, to filter the stats by.
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+
+    /// Asks for more tags once it's over.
+    pub fn ask_for_tags(&mut self) {
+        self.ask_tags = true;
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn asks_for_tags(&self) -> bool {
+        self.ask_tags
+    }
+
+    /// What distances are shown in.
+    pub fn set_units(&mut self, units: Units) {
+        self.units = units;
+    }
+
+    /// Redraws the stopwatch and the overlay this often instead of every
+    /// second, for slow terminals. The cues stay on time.
+    pub fn set_redraw_every(&mut self, every: Duration) {
+        self.redraw = every.max(Duration::from_secs(1));
+    }
+
+    pub fn redraw_every(&self) -> Duration {
+        self.redraw
+    }
+
+    /// Silences the sounds from now on (or brings them back), for when
+    /// someone walks into the room.
+    pub fn mute(&mut self, muted: bool) {
+        self.muted = muted;
+        (self.beep)(&Sound::Mute(muted));
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Stops it once it's been running this long, paused or not, so a
+    /// forgotten one doesn't keep a kiosk busy for good.
+    pub fn set_max_length(&mut self, max: Duration) {
+        self.max_length = Some(max);
+    }
+
+    pub fn max_length(&self) -> Option<Duration> {
+        self.max_length
+    }
+
+    /// Goes through a recorded session again, with every step taking as long
+    /// as it did back then.
+    pub fn replay(record: &SessionRecord, beep: B) -> Self {
+        let steps = record
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                let duration = Some(s.duration);
+                Step {
+                    set: s.set,
+                    set_rep: s.set_rep,
+                    element: s.element,
+                    group: s.group.clone(),
+                    cues: if i == 0 {
+                        vec![]
+                    } else {
+                        step_cues(&s.kind, duration)
+                    },
+                    kind: s.kind.clone(),
+                    duration,
+                    collect: vec![],
+                    weight: s.weight,
+                    metronome: vec![],
+                }
+            })
+            .collect();
+        Self::with_steps(record.sets.clone(), steps, beep)
+    }
+
+    /// How long the countdown before the first excercise takes, before
+    /// [`Session::set_speed`].
+    pub fn set_pre_roll(&mut self, pre_roll: Duration) {
+        if let Some(step) = self.steps.first_mut() {
+            step.duration = Some(pre_roll);
+            step.cues = pre_roll_cues(pre_roll);
+        }
+    }
+
+    /// Beeps in the last `seconds` of every timed excercise and rest too, like
+    /// the countdowns do, to know when to stop without looking. Before
+    /// [`Session::set_speed`].
+    pub fn set_countdown_beeps(&mut self, seconds: u8) {
+        for step in &mut self.steps {
+            let (
+                StepKind::Excercise { .. } | StepKind::Rest { .. } | StepKind::SetRest,
+                Some(duration),
+            ) = (&step.kind, step.duration)
+            else {
+                continue;
+            };
+            // the start beep is at 0 already
+            let cues = countdown_cues(duration, seconds as u64);
+            step.cues
+                .extend(cues.into_iter().filter(|c| !c.at.is_zero()));
+            step.cues.sort_by_key(|c| c.at);
+        }
+    }
+
+    /// Runs every timer `speed` times as fast, to try out a workout.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+        if speed == 1. {
+            return;
+        }
+        for step in &mut self.steps {
+            step.duration = step.duration.map(|d| d.div_f64(speed));
+            for cue in &mut step.cues {
+                cue.at = cue.at.div_f64(speed);
+            }
+        }
+    }
+
+    /// Starts the first excercise `offset` into it, with the time that's left.
+    /// Only timed excercises can be, call it after [`Session::set_speed`].
+    pub fn start_into(&mut self, offset: Duration) -> Result<()> {
+        let speed = self.speed;
+        let Some(step) = self
+            .steps
+            .iter_mut()
+            .find(|s| matches!(s.kind, StepKind::Excercise { .. }))
+        else {
+            return Ok(());
+        };
+        let (
+            StepKind::Excercise {
+                name,
+                amount: ExcerciseAmout::Time { duration, .. },
+                ..
+            },
+            Some(planned),
+        ) = (&step.kind, step.duration)
+        else {
+            return Err(anyhow::Error::msg(
+                "Only timed excercises can be started partway",
+            ));
+        };
+        if offset >= *duration {
+            return Err(anyhow::Error::msg(format!(
+                "{name} is only {}, can't start {} into it",
+                format_duration(*duration),
+                format_duration(offset)
+            )));
+        }
+
+        let skipped = offset.div_f64(speed);
+        step.duration = Some(planned - skipped);
+        // what's shown when it starts stays
+        step.cues.retain(|c| c.at.is_zero() || c.at >= skipped);
+        for cue in &mut step.cues {
+            cue.at = cue.at.saturating_sub(skipped);
+        }
+        step.cues.insert(
+            0,
+            Cue {
+                at: Duration::ZERO,
+                sound: None,
+                message: Some(format!("    picking up {} in", format_duration(offset))),
+                event: CueEvent::Note,
+            },
+        );
+        Ok(())
+    }
+
+    /// Races rep excercises against how long they took in `last`: shows the
+    /// old time when they start and beeps once it's passed.
+    pub fn ghost(&mut self, last: &SessionRecord) {
+        for step in &mut self.steps {
+            let StepKind::Excercise { name, .. } = &step.kind else {
+                continue;
+            };
+            if step.duration.is_some() {
+                continue;
+            }
+
+            let Some(previous) = last.steps.iter().find(|r| {
+                (r.set, r.set_rep, r.element) == (step.set, step.set_rep, step.element)
+                    && matches!(&r.kind, StepKind::Excercise { name: n, .. } if n == name)
+            }) else {
+                continue;
+            };
+            let time = previous.duration;
+
+            step.cues.push(Cue {
+                at: Duration::ZERO,
+                sound: None,
+                message: Some(format!("    last time: {:.1}s", time.as_secs_f64())),
+                event: CueEvent::Note,
+            });
+            step.cues.push(Cue {
+                at: time,
+                sound: Some(Sound::Beep(BeepLevel::Mid)),
+                message: Some(format!("    passed last time ({:.1}s)", time.as_secs_f64())),
+                event: CueEvent::Warning,
+            });
+        }
+    }
+
+    /// Shows how an excercise went the last time it's in the history, in
+    /// the rest (or countdown) before the first time it comes up.
+    pub fn last_time(&mut self, history: &[SessionRecord]) {
+        let mut shown = vec![];
+        for i in 0..self.steps.len() {
+            if !matches!(
+                self.steps[i].kind,
+                StepKind::Countdown | StepKind::Rest { .. } | StepKind::SetRest
+            ) {
+                continue;
+            }
+            // through setting up for it and asking whether to do it
+            let next = self.steps[i + 1..]
+                .iter()
+                .find(|s| !matches!(s.kind, StepKind::Prep { .. } | StepKind::Optional { .. }));
+            let Some(StepKind::Excercise { name, .. }) = next.map(|s| &s.kind) else {
+                continue;
+            };
+            if shown.contains(name) {
+                continue;
+            }
+            shown.push(name.clone());
+
+            let Some(last) = last_performance(history, name) else {
+                continue;
+            };
+            self.steps[i].cues.insert(
+                0,
+                Cue {
+                    at: Duration::ZERO,
+                    sound: None,
+                    message: Some(format!("    last time: {last}")),
+                    event: CueEvent::Note,
+                },
+            );
+        }
+    }
+
+    pub fn sets(&self) -> &[SetInfo] {
+        &self.sets
+    }
+
+    /// Actual timings of the steps done so far.
+    pub fn log(&self) -> &[StepRecord] {
+        &self.log
+    }
+
+    pub fn into_log(self) -> Vec<StepRecord> {
+        self.log
+    }
+
+    pub fn start(&mut self, now: Instant) {
+        self.session_start = now;
+        self.emit(Event::WorkoutStarted { from: self.from });
+        self.enter(0, now);
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn current_step(&self) -> Option<&Step> {
+        self.steps.get(self.current)
+    }
+
+    pub fn state(&self) -> State {
+        let Some(step) = self.current_step() else {
+            return State::Finished;
+        };
+        if self.paused_at.is_some() {
+            return State::Paused;
+        }
+
+        match (&step.kind, step.duration) {
+            (StepKind::Countdown, _) => State::Countdown,
+            (StepKind::Prep { .. }, _) => State::Preparing,
+            (StepKind::Optional { .. }, _) => State::Confirming,
+            (StepKind::Acknowledge { .. }, _) => State::Acknowledging,
+            (StepKind::Excercise { .. }, None) => State::AwaitingInput,
+            (StepKind::Excercise { .. }, Some(_)) => State::Excercising,
+            (StepKind::Rest { .. } | StepKind::SetRest, _) => State::Resting,
+        }
+    }
+
+    pub fn progress(&self, now: Instant) -> Progress {
+        let planned = |s: &Step| s.duration.unwrap_or_default();
+        let total: Duration = self.steps.iter().map(planned).sum();
+        let done: Duration = self.steps[..self.current.min(self.steps.len())]
+            .iter()
+            .map(planned)
+            .sum();
+
+        let Some(step) = self.current_step() else {
+            let last = self.steps.last();
+            let set = last.map_or(0, |s| s.set);
+            return Progress {
+                percent: 100.,
+                set,
+                set_name: self.sets.get(set).and_then(|s| s.name.clone()),
+                set_rep: last.map_or(0, |s| s.set_rep),
+                set_reps: self.sets.get(set).map_or(0, |s| s.reps),
+                element: None,
+                excercise: None,
+                excercise_name: None,
+                remaining: None,
+                left: Duration::ZERO,
+            };
+        };
+
+        let elapsed = self.elapsed(now);
+        let current = step.duration.map_or(Duration::ZERO, |d| elapsed.min(d));
+        let percent = if total.is_zero() {
+            100. * self.current as f64 / self.steps.len() as f64
+        } else {
+            100. * (done + current).as_secs_f64() / total.as_secs_f64()
+        };
+
+        let (excercise, excercise_name) = match &step.kind {
+            StepKind::Excercise { index, name, .. } => (Some(*index), Some(name.clone())),
+            _ => (None, None),
+        };
+        let set = &self.sets[step.set];
+        Progress {
+            percent,
+            set: step.set,
+            set_name: set.name.clone(),
+            set_rep: step.set_rep,
+            set_reps: set.reps,
+            element: step.element,
+            excercise,
+            excercise_name,
+            remaining: step.duration.map(|d| d.saturating_sub(elapsed)),
+            left: total.saturating_sub(done + current),
+        }
+    }
+
+    /// Time spent in the current step, not counting pauses.
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        self.paused_at
+            .unwrap_or(now)
+            .saturating_duration_since(self.step_start)
+    }
+
+    /// When [`Session::tick`] next has something to do, `None` while paused,
+    /// finished or waiting for [`Session::advance`].
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.paused_at.is_some() {
+            return None;
+        }
+        let step = self.current_step()?;
+        let offset = match step.cues.get(self.next_cue) {
+            Some(cue) if step.duration.is_none_or(|d| cue.at < d) => Some(cue.at),
+            _ => step.duration,
+        };
+        let offset = [offset, self.idle_limit()].into_iter().flatten().min()?;
+        Some(self.step_start + offset)
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        if self.paused_at.is_some() {
+            return self.sync_metronome();
+        }
+
+        while let Some(step) = self.steps.get(self.current) {
+            let elapsed = now.saturating_duration_since(self.step_start);
+            let until = match step.duration {
+                Some(d) => elapsed.min(d),
+                None => elapsed,
+            };
+            self.fire_cues(until);
+
+            match self.steps[self.current].duration {
+                // next step starts when this one was supposed to end, not when we noticed
+                Some(d) if elapsed >= d => {
+                    let end = self.step_start + d;
+                    if let StepKind::Acknowledge { .. } = self.steps[self.current].kind {
+                        self.set_acknowledged(self.log.len(), false);
+                    }
+                    if let Some(timings) = &mut self.timings {
+                        timings.push(Timing {
+                            step: self.current + 1,
+                            intended: end - self.session_start,
+                            late: now.saturating_duration_since(end),
+                            cue: false,
+                        });
+                    }
+                    self.log_current(end);
+                    self.enter(self.current + 1, end);
+                }
+                _ => break,
+            }
+        }
+
+        if let Some(limit) = self.idle_limit() {
+            let at = self.step_start + limit;
+            if now >= at {
+                self.go_idle(at, limit);
+            }
+        }
+        self.sync_metronome();
+    }
+
+    // clicking while an excercise with one is on, not while paused
+    fn sync_metronome(&mut self) {
+        let on = match self.state() {
+            State::Excercising | State::AwaitingInput => self
+                .current_step()
+                .filter(|s| !s.metronome.is_empty())
+                .map(|_| self.current),
+            _ => None,
+        };
+        if on == self.clicking {
+            return;
+        }
+        if self.clicking.take().is_some() {
+            (self.beep)(&Sound::MetronomeStop);
+        }
+        if let Some(step) = on.and_then(|i| self.steps.get(i)) {
+            (self.beep)(&Sound::Metronome(step.metronome.clone()));
+            self.clicking = on;
+        }
+    }
+
+    /// Ends the current step right away.
+    pub fn advance(&mut self, now: Instant) {
+        if self.current >= self.steps.len() {
+            return;
+        }
+        self.resume(now);
+        self.fire_cues(Duration::MAX);
+        self.log_current(now);
+        self.enter(self.current + 1, now);
+    }
+
+    /// Adds the rep count to an already logged step.
+    pub fn record_reps(&mut self, log_index: usize, reps: u16) {
+        if let Some(record) = self.log.get_mut(log_index) {
+            record.reps = Some(reps);
+        }
+    }
+
+    /// Changes the rest right after a rep excercise by `fraction` of it for
+    /// the reps that were typed in, see [`Session::adapt_rest`].
+    pub fn set_adaptive_rest(&mut self, fraction: f64) {
+        self.adaptive_rest = Some(fraction);
+    }
+
+    /// Lengthens the current rest if the excercise before it fell short of
+    /// its reps, shortens it if it went over.
+    pub fn adapt_rest(&mut self, now: Instant) {
+        let Some(fraction) = self.adaptive_rest else {
+            return;
+        };
+        let Some(StepRecord {
+            kind:
+                StepKind::Excercise {
+                    amount: ExcerciseAmout::Reps(target),
+                    ..
+                },
+            reps: Some(done),
+            ..
+        }) = self.log.last()
+        else {
+            return;
+        };
+        let (target, done) = (*target, *done);
+        let Some(step) = self.steps.get_mut(self.current) else {
+            return;
+        };
+        if !matches!(step.kind, StepKind::Rest { .. } | StepKind::SetRest) || done == target {
+            return;
+        }
+        let Some(duration) = step.duration else {
+            return;
+        };
+
+        let factor = if done < target {
+            1. + fraction
+        } else {
+            1. - fraction
+        };
+        let adapted = duration.mul_f64(factor);
+        step.duration = Some(adapted);
+        step.cues = step_cues(&step.kind, Some(adapted));
+        let elapsed = now.saturating_duration_since(self.step_start);
+        self.next_cue = step.cues.iter().filter(|c| c.at <= elapsed).count();
+        self.emit(Event::RestAdapted {
+            done,
+            target,
+            from: duration,
+            to: adapted,
+        });
+    }
+
+    /// Skips the optional excercise being asked about.
+    pub fn decline(&mut self, now: Instant) {
+        let Some(step) = self.current_step() else {
+            return;
+        };
+        if !matches!(step.kind, StepKind::Optional { .. }) || self.paused_at.is_some() {
+            return;
+        }
+
+        // its prep and the excercise itself
+        let element = (step.set, step.set_rep, step.element);
+        let mut next = self.current + 1;
+        while self
+            .steps
+            .get(next)
+            .is_some_and(|s| (s.set, s.set_rep, s.element) == element)
+        {
+            next += 1;
+        }
+
+        self.log_current(now);
+        self.emit(Event::Skipped);
+        self.enter(next, now);
+    }
+
+    // where the excercise of the step at `index` starts, with its prep and
+    // question, the step itself for rests and countdowns
+    fn excercise_start(&self, index: usize) -> usize {
+        let mut i = index;
+        if let (Some(StepKind::Acknowledge { .. }), true) =
+            (self.steps.get(i).map(|s| &s.kind), i > 0)
+        {
+            i -= 1;
+        }
+        if let Some(StepKind::Excercise { .. }) = self.steps.get(i).map(|s| &s.kind) {
+            while i > 0
+                && matches!(
+                    self.steps[i - 1].kind,
+                    StepKind::Prep { .. } | StepKind::Optional { .. }
+                )
+            {
+                i -= 1;
+            }
+        }
+        i
+    }
+
+    // logs the current step as far as it got and goes to `index`
+    fn jump(&mut self, index: usize, now: Instant) {
+        if self.current >= self.steps.len() {
+            return;
+        }
+        self.resume(now);
+        self.log_current(now);
+        self.enter(index, now);
+    }
+
+    /// Goes past the current excercise (with its prep and question), or just
+    /// the current rest or countdown.
+    pub fn skip(&mut self, now: Instant) {
+        let Some(step) = self.current_step() else {
+            return;
+        };
+        let mut next = self.current + 1;
+        if let StepKind::Acknowledge { .. } = step.kind {
+            self.set_acknowledged(self.log.len(), false);
+        } else if matches!(
+            step.kind,
+            StepKind::Prep { .. } | StepKind::Optional { .. } | StepKind::Excercise { .. }
+        ) {
+            next = self.steps[self.current..]
+                .iter()
+                .position(|s| matches!(s.kind, StepKind::Excercise { .. }))
+                .map_or(next, |i| self.current + i + 1);
+            if let Some(StepKind::Acknowledge { .. }) = self.steps.get(next).map(|s| &s.kind) {
+                next += 1;
+            }
+        }
+        self.emit(Event::Skipped);
+        self.resume(now);
+        self.log_current(now);
+        if let Some(record) = self.log.last_mut() {
+            record.skipped = true;
+        }
+        // the excercise gone past with its prep or question, in no time
+        for index in self.current + 1..next {
+            if let StepKind::Excercise { .. } = self.steps[index].kind {
+                let mut record = self.record(index, now, now);
+                record.skipped = true;
+                self.log.push(record);
+            }
+        }
+        self.enter(next, now);
+    }
+
+    /// Goes back to the start of the excercise before the current one.
+    pub fn back(&mut self, now: Instant) {
+        let start = self.excercise_start(self.current);
+        let Some(previous) = self.steps[..start.min(self.steps.len())]
+            .iter()
+            .rposition(|s| matches!(s.kind, StepKind::Excercise { .. }))
+        else {
+            return self.restart(now);
+        };
+        if let StepKind::Excercise { name, .. } = &self.steps[previous].kind {
+            let name = name.clone();
+            self.emit(Event::WentBack { name });
+        }
+        self.jump(self.excercise_start(previous), now);
+    }
+
+    /// Starts the current excercise (or rest) over.
+    pub fn restart(&mut self, now: Instant) {
+        if self.current >= self.steps.len() {
+            return;
+        }
+        self.emit(Event::Restarted);
+        self.jump(self.excercise_start(self.current), now);
+    }
+
+    /// The steps still ahead that can be edited: excercises, rests and
+    /// preps, with what they are.
+    pub fn upcoming(&self) -> impl Iterator<Item = (usize, String)> + '_ {
+        (self.current + 1..self.steps.len()).filter_map(|i| self.describe(i).map(|d| (i, d)))
+    }
+
+    fn describe(&self, index: usize) -> Option<String> {
+        let step = &self.steps[index];
+        let set = &self.sets[step.set];
+        let at = format!(
+            "{} ({}/{})",
+            set.name.as_deref().unwrap_or("[UNKNOWN]"),
+            step.set_rep + 1,
+            set.reps
+        );
+        let planned = || {
+            let duration = step.duration.unwrap_or_default() + self.lead_in_after(index);
+            format_duration(duration.mul_f64(self.speed))
+        };
+        Some(match &step.kind {
+            StepKind::Excercise { name, amount, .. } => format!("{at} {name} {amount}"),
+            StepKind::Rest { .. } => format!("{at} rest {}", planned()),
+            StepKind::SetRest => format!("{at} set rest {}", planned()),
+            StepKind::Prep { name } => format!("{at} {name} {}", planned()),
+            StepKind::Countdown | StepKind::Optional { .. } | StepKind::Acknowledge { .. } => {
+                return None
+            }
+        })
+    }
+
+    // set rests are shown with the countdown after them
+    fn lead_in_after(&self, index: usize) -> Duration {
+        match (&self.steps[index].kind, self.steps.get(index + 1)) {
+            (StepKind::SetRest, Some(next)) if matches!(next.kind, StepKind::Countdown) => {
+                next.duration.unwrap_or_default()
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    fn editable(&self, index: usize) -> Result<String> {
+        if index <= self.current {
+            return Err(anyhow::Error::msg("Only what's still ahead can be edited"));
+        }
+        self.steps
+            .get(index)
+            .and_then(|_| self.describe(index))
+            .ok_or_else(|| anyhow::Error::msg("No such step"))
+    }
+
+    /// Takes an upcoming step out, with the prep and question before an
+    /// excercise.
+    pub fn drop_step(&mut self, index: usize) -> Result<String> {
+        let what = self.editable(index)?;
+        let step = &self.steps[index];
+        let mut first = index;
+        if matches!(step.kind, StepKind::Excercise { .. }) {
+            let element = (step.set, step.set_rep, step.element);
+            while first - 1 > self.current
+                && matches!(
+                    self.steps[first - 1].kind,
+                    StepKind::Prep { .. } | StepKind::Optional { .. }
+                )
+                && (
+                    self.steps[first - 1].set,
+                    self.steps[first - 1].set_rep,
+                    self.steps[first - 1].element,
+                ) == element
+            {
+                first -= 1;
+            }
+        }
+        let last = match self.steps.get(index + 1) {
+            Some(Step {
+                kind: StepKind::Acknowledge { .. },
+                ..
+            }) => index + 1,
+            _ => index,
+        };
+        self.steps.drain(first..=last);
+
+        let edit = format!("Dropped {what}");
+        self.edits.push(edit.clone());
+        Ok(edit)
+    }
+
+    /// Changes how long an upcoming timed excercise, rest or prep takes.
+    pub fn set_duration(&mut self, index: usize, duration: Duration) -> Result<String> {
+        let what = self.editable(index)?;
+        let speed = self.speed;
+        let scaled = duration
+            .div_f64(speed)
+            .saturating_sub(self.lead_in_after(index));
+        let step = &mut self.steps[index];
+        match (&mut step.kind, step.duration) {
+            (
+                StepKind::Excercise {
+                    amount: ExcerciseAmout::Time { duration: d, .. },
+                    ..
+                },
+                Some(old),
+            ) => {
+                *d = duration;
+                let factor = scaled.as_secs_f64() / old.as_secs_f64().max(f64::EPSILON);
+                for cue in &mut step.cues {
+                    cue.at = cue.at.mul_f64(factor);
+                }
+            }
+            (StepKind::Rest { .. } | StepKind::SetRest | StepKind::Prep { .. }, Some(_)) => {
+                step.cues = step_cues(&step.kind, Some(scaled.mul_f64(speed)))
+                    .into_iter()
+                    .map(|c| Cue {
+                        at: c.at.div_f64(speed),
+                        ..c
+                    })
+                    .collect();
+            }
+            _ => return Err(anyhow::Error::msg(format!("{what} isn't timed"))),
+        }
+        step.duration = Some(scaled);
+
+        let edit = format!("{what} -> {}", format_duration(duration));
+        self.edits.push(edit.clone());
+        Ok(edit)
+    }
+
+    /// Repeats the current set once more, after its last round.
+    pub fn add_round(&mut self) -> Result<String> {
+        let Some(set) = self.current_step().map(|s| s.set) else {
+            return Err(anyhow::Error::msg("The session is over"));
+        };
+        let last = self.sets[set].reps.saturating_sub(1);
+        let in_last = |s: &Step| (s.set, s.set_rep) == (set, last);
+        // past the countdown at the very start, which has the first set's position
+        let start = self.steps[1..]
+            .iter()
+            .position(in_last)
+            .ok_or_else(|| anyhow::Error::msg("The set's last round is already done"))?
+            + 1;
+        let mut end = start
+            + self.steps[start..]
+                .iter()
+                .take_while(|s| in_last(s))
+                .count();
+        // the rest after the set goes after the new round
+        let rest_after = matches!(self.steps[end - 1].kind, StepKind::SetRest);
+        if rest_after {
+            end -= 1;
+        }
+        if end <= self.current {
+            return Err(anyhow::Error::msg("The set's last round is already done"));
+        }
+
+        let mut round = vec![];
+        // the rest between rounds, if the set has one
+        if let Some(set_rest) = self
+            .steps
+            .iter()
+            .find(|s| s.set == set && s.set_rep < last && matches!(s.kind, StepKind::SetRest))
+        {
+            round.push(Step {
+                set_rep: last,
+                ..set_rest.clone()
+            });
+        }
+        round.extend(self.steps[start..end].iter().map(|s| Step {
+            set_rep: last + 1,
+            ..s.clone()
+        }));
+        let added = round.len();
+        self.steps.splice(end..end, round);
+        if rest_after {
+            self.steps[end + added].set_rep = last + 1;
+        }
+        self.sets[set].reps += 1;
+
+        let edit = format!("Added a round of {}", self.sets[set]);
+        self.edits.push(edit.clone());
+        Ok(edit)
+    }
+
+    /// Asks for a key press after every timed excercise, or the last round
+    /// of intervals, and logs whether it came. Rep excercises are
+    /// acknowledged by ending them.
+    pub fn require_acknowledgement(&mut self) {
+        let mut i = 0;
+        while i < self.steps.len() {
+            let step = &self.steps[i];
+            let (StepKind::Excercise { index, name, .. }, Some(_)) = (&step.kind, step.duration)
+            else {
+                i += 1;
+                continue;
+            };
+            // the next round of the same intervals
+            let next = self.steps[i + 1..]
+                .iter()
+                .find(|s| !matches!(s.kind, StepKind::Rest { .. }));
+            let another_round = next.is_some_and(|s| {
+                matches!(&s.kind, StepKind::Excercise { index: n, .. } if n == index)
+                    && (s.set, s.set_rep, s.element) == (step.set, step.set_rep, step.element)
+            });
+            if !another_round {
+                let acknowledge = Step {
+                    kind: StepKind::Acknowledge { name: name.clone() },
+                    duration: Some(ACKNOWLEDGE_TIMEOUT),
+                    cues: vec![],
+                    collect: vec![],
+                    weight: None,
+                    metronome: vec![],
+                    ..step.clone()
+                };
+                self.steps.insert(i + 1, acknowledge);
+                i += 1;
+            }
+            i += 1;
+        }
+        self.acknowledging = true;
+    }
+
+    pub fn acknowledging(&self) -> bool {
+        self.acknowledging
+    }
+
+    /// Ends the question after an excercise, saying it was done.
+    pub fn acknowledge(&mut self, now: Instant) {
+        if self.state() != State::Acknowledging {
+            return;
+        }
+        self.set_acknowledged(self.log.len(), true);
+        self.advance(now);
+    }
+
+    /// Marks the last excercise logged before `log_index`.
+    pub fn set_acknowledged(&mut self, log_index: usize, acknowledged: bool) {
+        let end = log_index.min(self.log.len());
+        let excercise = self.log[..end]
+            .iter_mut()
+            .rev()
+            .find(|r| matches!(r.kind, StepKind::Excercise { .. }));
+        if let Some(record) = excercise {
+            record.acknowledged = Some(acknowledged);
+        }
+    }
+
+    /// Keeps track of how late steps start and cues play.
+    pub fn record_timings(&mut self) {
+        self.timings = Some(vec![]);
+    }
+
+    /// How late every step that started on its own was, with its latest
+    /// cue, and how late they were overall.
+    pub fn timing_report(&self) -> Option<String> {
+        let timings = self.timings.as_ref()?;
+        let ms = |d: Duration| format!("{:.1}ms", d.as_secs_f64() * 1000.);
+        let clock = |d: Duration| {
+            let millis = d.as_millis();
+            format!(
+                "{:02}:{:02}.{:03}",
+                millis / 60_000,
+                millis / 1000 % 60,
+                millis % 1000
+            )
+        };
+
+        let mut out = String::new();
+        let _ = writeln!(out, "\nTiming report (late: after it was due)");
+        let _ = writeln!(
+            out,
+            "  {:9}  {:9}  {:>8}  {:>8}  step",
+            "due", "started", "late", "cues"
+        );
+        for timing in timings.iter().filter(|t| !t.cue) {
+            let cues = timings
+                .iter()
+                .filter(|t| t.cue && t.step == timing.step)
+                .map(|t| t.late)
+                .max()
+                .map_or_else(|| "-".to_owned(), ms);
+            let what = match self.steps.get(timing.step).map(|s| &s.kind) {
+                None => "end".to_owned(),
+                Some(StepKind::Countdown) => "countdown".to_owned(),
+                Some(StepKind::Optional { name }) => format!("{name}?"),
+                Some(StepKind::Acknowledge { name }) => format!("done with {name}?"),
+                Some(_) => self.describe(timing.step).unwrap_or_default(),
+            };
+            let _ = writeln!(
+                out,
+                "  {}  {}  {:>8}  {cues:>8}  {what}",
+                clock(timing.intended),
+                clock(timing.intended + timing.late),
+                ms(timing.late)
+            );
+        }
+
+        for (cue, what) in [(false, "steps"), (true, "cues")] {
+            let late: Vec<_> = timings
+                .iter()
+                .filter(|t| t.cue == cue)
+                .map(|t| t.late)
+                .collect();
+            let Some(max) = late.iter().max() else {
+                continue;
+            };
+            let _ = writeln!(
+                out,
+                "  {} {what}: {} late on average, {} at most",
+                late.len(),
+                ms(late.iter().sum::<Duration>() / late.len() as u32),
+                ms(*max)
+            );
+        }
+        let _ = writeln!(
+            out,
+            "  (cues include handing the sound to the audio output, steps that \
+             waited for enter aren't listed)"
+        );
+        Some(out)
+    }
+
+    /// What was changed with the edits above, in order.
+    pub fn edits(&self) -> &[String] {
+        &self.edits
+    }
+
+    /// Where `--from` would pick up the rest of the session: the current
+    /// excercise, or the next one if between excercises.
+    pub fn resume_position(&self) -> Option<(u16, u16, u16)> {
+        self.steps[self.current.min(self.steps.len())..]
+            .iter()
+            .find_map(|s| match s.kind {
+                StepKind::Excercise { index, .. } => Some((s.set as u16, s.set_rep, index as u16)),
+                _ => None,
+            })
+    }
+
+    /// How far into the current timed excercise it is, for
+    /// [`Session::start_into`] next time.
+    pub fn resume_offset(&self, now: Instant) -> Option<Duration> {
+        let step = self.current_step()?;
+        let (
+            StepKind::Excercise {
+                amount: ExcerciseAmout::Time { duration, .. },
+                ..
+            },
+            Some(planned),
+        ) = (&step.kind, step.duration)
+        else {
+            return None;
+        };
+        let left = planned
+            .saturating_sub(self.elapsed(now))
+            .mul_f64(self.speed);
+        // whole seconds, it's written down as MM:SS
+        let offset = Duration::from_secs(duration.saturating_sub(left).as_secs());
+        (!offset.is_zero()).then_some(offset)
+    }
+
+    /// Finishes the session early, skipping every remaining step.
+    pub fn stop(&mut self, now: Instant) {
+        if self.current >= self.steps.len() {
+            return;
+        }
+        self.resume(now);
+        self.log_current(now);
+        self.current = self.steps.len();
+        self.emit(Event::WorkoutFinished { completed: false });
+        self.sync_metronome();
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        if self.paused_at.is_none() && self.current < self.steps.len() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    pub fn resume(&mut self, now: Instant) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.step_start += now - paused_at;
+        }
+        if let Some(since) = self.idle_since.take() {
+            self.idle_gap = Some(now.saturating_duration_since(since));
+        }
+    }
+
+    /// Auto-pauses a rep excercise after this many times its median in
+    /// `measured`.
+    pub fn set_idle_pause(&mut self, measured: Measured, factor: f64) {
+        self.idle = Some((measured, factor));
+    }
+
+    // how long the current excercise waits for input before it's auto-paused
+    fn idle_limit(&self) -> Option<Duration> {
+        let (measured, factor) = self.idle.as_ref()?;
+        if self.idle_gap.is_some() || self.idle_since.is_some() {
+            return None;
+        }
+        match &self.current_step()?.kind {
+            StepKind::Excercise { name, amount, .. } if self.current_step()?.duration.is_none() => {
+                Some(measured.excercise(name, amount)?.mul_f64(*factor))
+            }
+            _ => None,
+        }
+    }
+
+    fn go_idle(&mut self, at: Instant, limit: Duration) {
+        self.paused_at = Some(at);
+        self.idle_since = Some(at);
+        // a beep and a notification too, whoever walked away won't see it
+        for _ in 0..3 {
+            (self.beep)(&Sound::Beep(BeepLevel::Low));
+        }
+        notify(&format!("No input for {}, paused", format_duration(limit)));
+        self.emit(Event::Idle { after: limit });
+    }
+
+    fn fire_cues(&mut self, until: Duration) {
+        while let Some(cue) = self.steps[self.current].cues.get(self.next_cue).cloned() {
+            if cue.at > until {
+                break;
+            }
+            if cue.event == CueEvent::Rest {
+                let duration = self.steps[self.current].duration.unwrap_or_default();
+                self.emit(Event::RestEnding {
+                    remaining: duration.saturating_sub(cue.at),
+                });
+            }
+            self.emit(Event::Cue {
+                event: cue.event,
+                message: cue.message.clone(),
+                sound: cue.sound.clone(),
+            });
+            let step = &self.steps[self.current];
+            if let Some(sound) = &cue.sound {
+                let intended = self.step_start + cue.at;
+                // not the ones played early when a step is ended
+                let due = Instant::now() >= intended;
+                match self.pan(step, cue.event) {
+                    Pan::Center => (self.beep)(sound),
+                    pan => (self.beep)(&Sound::Panned(Box::new(sound.clone()), pan)),
+                }
+                // handing it to the audio output included
+                if let (true, Some(timings)) = (due, &mut self.timings) {
+                    timings.push(Timing {
+                        step: self.current,
+                        intended: intended - self.session_start,
+                        late: Instant::now() - intended,
+                        cue: true,
+                    });
+                }
+            }
+            self.next_cue += 1;
+        }
+    }
+
+    fn pan(&self, step: &Step, event: CueEvent) -> Pan {
+        let default = match event {
+            CueEvent::Start | CueEvent::Switch => Pan::Sides,
+            _ => Pan::Center,
+        };
+        match self.panning.get(&event).copied().unwrap_or(default) {
+            Pan::Sides => {
+                let per_side = matches!(
+                    step.kind,
+                    StepKind::Excercise {
+                        amount: ExcerciseAmout::Time { midbeep: true, .. },
+                        ..
+                    }
+                );
+                match event {
+                    CueEvent::Start if per_side => Pan::Left,
+                    CueEvent::Switch | CueEvent::End if per_side => Pan::Right,
+                    _ => Pan::Center,
+                }
+            }
+            pan => pan,
+        }
+    }
+
+    fn log_current(&mut self, end: Instant) {
+        let record = self.record(self.current, self.step_start, end);
+        self.log.push(record);
+    }
+
+    fn record(&self, index: usize, start: Instant, end: Instant) -> StepRecord {
+        let step = &self.steps[index];
+        StepRecord {
+            set: step.set,
+            set_rep: step.set_rep,
+            element: step.element,
+            group: step.group.clone(),
+            kind: step.kind.clone(),
+            planned: step.duration,
+            started: start.saturating_duration_since(self.session_start),
+            duration: end.saturating_duration_since(start),
+            reps: None,
+            collect: step.collect.clone(),
+            rpe: None,
+            weight: step.weight,
+            video: false,
+            idle: self.idle_gap,
+            acknowledged: None,
+            skipped: false,
+        }
+    }
+
+    fn enter(&mut self, index: usize, at: Instant) {
+        self.current = index;
+        self.step_start = at;
+        self.next_cue = 0;
+        self.idle_gap = None;
+        if let Some(checkpoint) = &self.checkpoint {
+            checkpoint(self.resume_position());
+        }
+        if let Some(snapshot) = &self.snapshot {
+            snapshot(&self.log, self.resume_position());
+        }
+
+        // the next step's files read while this one's on, its first cue
+        // would wait for them otherwise
+        let files = self.steps.iter().skip(index).take(2).flat_map(|s| &s.cues);
+        for sound in files.filter_map(|c| c.sound.as_ref()) {
+            let sound = match sound {
+                Sound::Panned(sound, _) => &**sound,
+                sound => sound,
+            };
+            if let Sound::File(path) = sound {
+                (self.beep)(&Sound::Preload(path.clone()));
+            }
+        }
+        let Some(step) = self.steps.get(index) else {
+            self.emit(Event::WorkoutFinished { completed: true });
+            return;
+        };
+        let mut events = vec![];
+        // the last set's is left to the summary
+        if index > 0 && self.steps[index - 1].set != step.set {
+            if let Some(line) = self.motivation.as_mut().and_then(Motivation::line) {
+                events.push(Event::Motivation {
+                    line: line.to_owned(),
+                });
+            }
+        }
+        let duration = step.duration.unwrap_or_default();
+        let started = match &step.kind {
+            StepKind::Countdown if index == 0 || self.steps[index - 1].set != step.set => {
+                Event::SetStarted { set: step.set }
+            }
+            StepKind::Countdown if step.set_rep > 0 => Event::SetRepeated {
+                set: step.set,
+                rep: step.set_rep,
+            },
+            // the first round's lead-in, right after the pre-roll
+            StepKind::Countdown => return self.fire_all(events),
+            StepKind::Excercise {
+                index,
+                name,
+                amount,
+                estimate,
+            } => Event::ExcerciseStarted {
+                index: *index,
+                name: name.clone(),
+                amount: amount.clone(),
+                estimate: *estimate,
+            },
+            StepKind::Rest { next } => Event::RestStarted {
+                duration,
+                next: next.clone(),
+            },
+            StepKind::Optional { name } => Event::OptionalAsked {
+                name: name.clone(),
+                timeout: duration,
+            },
+            StepKind::Prep { name } => Event::PrepStarted {
+                name: name.clone(),
+                duration,
+            },
+            StepKind::Acknowledge { name } => Event::AcknowledgeAsked {
+                name: name.clone(),
+                timeout: duration,
+            },
+            StepKind::SetRest => {
+                // the lead-in of the next repetition is part of the rest
+                let lead_in = self.steps.get(index + 1).and_then(|s| s.duration);
+                Event::SetRestStarted {
+                    duration: duration + lead_in.unwrap_or_default(),
+                }
+            }
+        };
+        events.push(started);
+        self.fire_all(events);
+    }
+
+    // the events of a step starting, then its first cues
+    fn fire_all(&mut self, events: Vec<Event>) {
+        for event in events {
+            self.emit(event);
+        }
+        self.fire_cues(Duration::ZERO);
+    }
+
+    fn emit(&mut self, event: Event) {
+        if self.printing {
+            self.print(&event);
+        }
+        for observer in &mut self.observers {
+            observer.event(&event);
+        }
+    }
+
+    // the lines of the plain terminal output
+    fn print(&self, event: &Event) {
+        match event {
+            Event::WorkoutStarted { from } if *from != (0, 0, 0) => {
+                let set = &self.sets[from.0 as usize];
+                print!(
+                    "Starting from set {}",
+                    set.name.as_deref().unwrap_or("[UNKNOWN]")
+                );
+                if from.1 != 0 {
+                    print!(" ({} / {})", from.1 + 1, set.reps);
+                }
+                println!(" {}. excercise", from.2 + 1);
+            }
+            Event::SetStarted { set } => println!("\nSection {}", self.sets[*set]),
+            Event::SetRepeated { set, rep } => {
+                println!(
+                    "\nRepeating section ({} / {})",
+                    rep + 1,
+                    self.sets[*set].reps
+                );
+            }
+            Event::Motivation { line } => println!("\n  {line}"),
+            Event::ExcerciseStarted {
+                name,
+                amount,
+                estimate,
+                ..
+            } => {
+                match amount {
+                    ExcerciseAmout::Meters(m) => {
+                        print!("  [EXCERCISE]: {name} {}", self.units.distance(*m))
+                    }
+                    _ => print!("  [EXCERCISE]: {name} {amount}"),
+                }
+                if let Some(estimate) = estimate {
+                    print!(" (~{})", format_duration(*estimate));
+                }
+                println!();
+            }
+            Event::RestStarted { duration, next } => {
+                println!("  [REST]: {duration:?}");
+                if let Some(next) = next {
+                    println!("    next: {next}");
+                }
+            }
+            Event::OptionalAsked { name, timeout } => {
+                println!(
+                    "  [OPTIONAL]: {name}, type n and press enter to skip it ({}s)",
+                    timeout.as_secs()
+                );
+            }
+            Event::PrepStarted { name, duration } => println!("  [PREP]: {duration:?} for {name}"),
+            Event::AcknowledgeAsked { name, timeout } => {
+                println!(
+                    "    Done with {name}? Press a key to say so ({}s)",
+                    timeout.as_secs()
+                );
+            }
+            Event::SetRestStarted { duration } => println!("[REST]: {duration:?}"),
+            Event::RestAdapted {
+                done,
+                target,
+                from,
+                to,
+            } => {
+                println!(
+                    "    {done} of {target} reps, resting {}s {}",
+                    to.abs_diff(*from).as_secs(),
+                    if done < target { "longer" } else { "less" }
+                );
+            }
+            Event::Skipped => println!("    Skipping it"),
+            Event::WentBack { name } => println!("    Back to {name}"),
+            Event::Restarted => println!("    Starting it over"),
+            Event::Idle { after } => {
+                println!("\n    No input for {}, paused", format_duration(*after));
+            }
+            Event::Cue {
+                message: Some(message),
+                ..
+            } => println!("{message}"),
+            _ => {}
+        }
+    }
+}
+
+// a desktop notification, if there's a way to send one
+fn notify(message: &str) {
+    use std::process::{Command, Stdio};
+
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification \"{}\" with title \"workout\"",
+            message.replace('"', "'")
+        ));
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("notify-send");
+        command.arg("workout").arg(message);
+        command
+    } else {
+        return;
+    };
+    if let Ok(child) = command.stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+        reap(child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+
+    use super::*;
+
+    fn workout(source: &str) -> Workout {
+        Workout::parse(source).unwrap()
+    }
+
+    // two rounds of reps, a rest and a timed one, then another set
+    const TWO_SETS: &str = "Workout T\nSet A x2 | lead-in 00:03\n\tExcercise Push x10\n\tRest 00:20\n\tExcercise Plank 00:30\nSet B | lead-in 00:03\n\tExcercise Squats x5\n";
+
+    fn session(source: &str) -> Session<fn(&Sound)> {
+        let mut session =
+            Session::new(&workout(source), (0, 0, 0), (|_| {}) as fn(&Sound)).unwrap();
+        session.set_printing(false);
+        session
+    }
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    fn current(session: &Session<fn(&Sound)>) -> (String, usize, u16) {
+        let step = session.current_step().unwrap();
+        let name = match &step.kind {
+            StepKind::Excercise { name, .. } => name.clone(),
+            StepKind::Countdown => "countdown".to_owned(),
+            StepKind::Rest { .. } | StepKind::SetRest => "rest".to_owned(),
+            _ => "other".to_owned(),
+        };
+        (name, step.set, step.set_rep)
+    }
+
+    // started at `t0` and run up to the plank of the first round, 20s of
+    // countdowns, 11s of push ups and the 20s rest
+    fn at_the_plank(t0: Instant) -> Session<fn(&Sound)> {
+        let mut session = session(TWO_SETS);
+        session.start(t0);
+        session.tick(t0 + secs(9));
+        session.advance(t0 + secs(20));
+        session.tick(t0 + secs(40));
+        session
+    }
+
+    #[test]
+    fn starts_at_the_first_set_with_an_excercise() {
+        for first in [
+            "Set Warmup x0\n\tExcercise Jog 01:00\n",
+            "Set Warmup\n\tRest 00:30\n",
+            "Set Warmup\n\tExcercise Jog x0\n",
+        ] {
+            let steps = schedule(
+                &workout(&format!(
+                    "Workout T\n{first}Set Main\n\tExcercise Push x10\n"
+                )),
+                (0, 0, 0),
+            )
+            .unwrap();
+            assert!(steps.iter().all(|s| s.set == 1), "{first}");
+            assert!(matches!(
+                &steps.last().unwrap().kind,
+                StepKind::Excercise { name, .. } if name == "Push"
+            ));
+        }
+        assert!(schedule(
+            &workout("Workout T\nSet Main x0\n\tExcercise Push x10\n"),
+            (0, 0, 0)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn ticks_through_timed_steps_and_waits_for_reps() {
+        let t0 = Instant::now();
+        let mut session = session(TWO_SETS);
+        session.start(t0);
+        assert_eq!(session.state(), State::Countdown);
+        assert!(session.next_deadline().is_some_and(|d| d <= t0 + PRE_ROLL));
+
+        // the pre-roll and the lead-in end on their own, reps wait
+        session.tick(t0 + secs(9));
+        assert_eq!(session.state(), State::AwaitingInput);
+        assert_eq!(current(&session), ("Push".to_owned(), 0, 0));
+        assert_eq!(session.next_deadline(), None);
+        session.tick(t0 + secs(300));
+        assert_eq!(session.state(), State::AwaitingInput);
+
+        session.advance(t0 + secs(20));
+        assert_eq!(session.state(), State::Resting);
+        // steps start when the one before was due to end, not when it's noticed
+        session.tick(t0 + secs(45));
+        assert_eq!(session.state(), State::Excercising);
+        assert_eq!(session.progress(t0 + secs(45)).remaining, Some(secs(25)));
+
+        let log = session.log();
+        assert_eq!(log.len(), 4);
+        assert_eq!(log[2].duration, secs(11));
+        assert_eq!(log[3].duration, secs(20));
+        assert_eq!(log[3].started, secs(20));
+    }
+
+    #[test]
+    fn finishing_early_logs_the_step_as_far_as_it_got() {
+        let t0 = Instant::now();
+        let mut session = at_the_plank(t0);
+        let now = t0 + secs(50);
+        assert_eq!(session.resume_position(), Some((0, 0, 1)));
+        assert_eq!(session.resume_offset(now), Some(secs(10)));
+
+        session.stop(now);
+        assert_eq!(session.state(), State::Finished);
+        assert_eq!(session.next_deadline(), None);
+        let last = session.log().last().unwrap();
+        assert!(matches!(&last.kind, StepKind::Excercise { name, .. } if name == "Plank"));
+        assert_eq!(last.duration, secs(10));
+        assert_eq!(session.progress(now).percent, 100.);
+    }
+
+    #[test]
+    fn pauses_leave_the_time_left_alone() {
+        let t0 = Instant::now();
+        let mut session = at_the_plank(t0);
+        session.pause(t0 + secs(45));
+        session.tick(t0 + secs(200));
+        assert_eq!(session.state(), State::Paused);
+        assert_eq!(session.next_deadline(), None);
+
+        session.resume(t0 + secs(200));
+        assert_eq!(session.resume_offset(t0 + secs(200)), Some(secs(5)));
+        session.tick(t0 + secs(224));
+        assert_eq!(session.state(), State::Excercising);
+        session.tick(t0 + secs(225));
+        assert_eq!(current(&session), ("countdown".to_owned(), 0, 1));
+    }
+
+    #[test]
+    fn skips_and_goes_back_across_rounds_and_sets() {
+        let t0 = Instant::now();
+        let mut session = at_the_plank(t0);
+
+        session.skip(t0 + secs(41));
+        assert_eq!(current(&session), ("countdown".to_owned(), 0, 1));
+        assert!(session.log().last().unwrap().skipped);
+        // from the next round's countdown, back to the plank before it
+        session.back(t0 + secs(42));
+        assert_eq!(current(&session), ("Plank".to_owned(), 0, 0));
+        session.restart(t0 + secs(50));
+        assert_eq!(current(&session), ("Plank".to_owned(), 0, 0));
+        assert_eq!(session.elapsed(t0 + secs(50)), Duration::ZERO);
+
+        // this plank again, the last round's countdown, push ups and rest
+        for _ in 0..4 {
+            session.skip(t0 + secs(51));
+        }
+        assert_eq!(current(&session), ("Plank".to_owned(), 0, 1));
+        session.skip(t0 + secs(52));
+        assert_eq!(current(&session), ("countdown".to_owned(), 1, 0));
+        session.skip(t0 + secs(53));
+        assert_eq!(current(&session), ("Squats".to_owned(), 1, 0));
+        session.back(t0 + secs(54));
+        assert_eq!(current(&session), ("Plank".to_owned(), 0, 1));
+
+        let skipped = session
+            .log()
+            .iter()
+            .filter(|r| r.skipped && matches!(r.kind, StepKind::Excercise { .. }))
+            .count();
+        assert_eq!(skipped, 4);
+    }
+
+    #[test]
+    fn edits_what_is_still_ahead() {
+        let t0 = Instant::now();
+        let mut session = session(TWO_SETS);
+        session.start(t0);
+        let steps = session.steps().len();
+        let rest = session
+            .upcoming()
+            .find(|(_, what)| what.contains("rest"))
+            .unwrap()
+            .0;
+        session.set_duration(rest, secs(45)).unwrap();
+        assert_eq!(session.steps()[rest].duration, Some(secs(45)));
+        // the plank right after it
+        session.drop_step(rest + 1).unwrap();
+        assert_eq!(session.steps().len(), steps - 1);
+        assert!(session.drop_step(0).is_err());
+
+        session.add_round().unwrap();
+        assert_eq!(session.sets()[0].reps, 3);
+        assert!(session.steps().iter().any(|s| s.set == 0 && s.set_rep == 2));
+        assert_eq!(session.edits().len(), 3);
+    }
+
+    #[test]
+    fn rests_longer_after_falling_short() {
+        let t0 = Instant::now();
+        let mut session = session(TWO_SETS);
+        session.set_adaptive_rest(0.5);
+        session.start(t0);
+        session.tick(t0 + secs(9));
+        session.advance(t0 + secs(20));
+        session.record_reps(session.log().len() - 1, 6);
+        session.adapt_rest(t0 + secs(20));
+        assert_eq!(session.current_step().unwrap().duration, Some(secs(30)));
+    }
+
+    #[test]
+    fn starts_partway_into_a_timed_excercise() {
+        let mut timed = session("Workout T\nSet A\n\tExcercise Plank 00:30\n");
+        timed.start_into(secs(10)).unwrap();
+        let plank = timed
+            .steps()
+            .iter()
+            .find(|s| matches!(s.kind, StepKind::Excercise { .. }))
+            .unwrap();
+        assert_eq!(plank.duration, Some(secs(20)));
+        assert!(timed.start_into(secs(30)).is_err());
+        assert!(session(TWO_SETS).start_into(secs(5)).is_err());
+    }
+
+    #[test]
+    fn pauses_reps_left_alone_for_too_long() {
+        // push ups took 10s last time
+        let t0 = Instant::now();
+        let mut last = session(TWO_SETS);
+        last.start(t0);
+        last.tick(t0 + secs(9));
+        last.advance(t0 + secs(19));
+        let record = SessionRecord {
+            workout: "T".to_owned(),
+            start: Local::now(),
+            end: Local::now(),
+            completed: false,
+            resume_from: None,
+            resume_offset: None,
+            abort_reason: None,
+            program: None,
+            day: None,
+            week: None,
+            tags: vec![],
+            sets: last.sets().to_vec(),
+            steps: last.into_log(),
+            edits: vec![],
+        };
+
+        let mut session = session(TWO_SETS);
+        session.set_idle_pause(Measured::new(&[record]), 2.);
+        session.start(t0);
+        session.tick(t0 + secs(9));
+        assert_eq!(session.next_deadline(), Some(t0 + secs(29)));
+        session.tick(t0 + secs(30));
+        assert_eq!(session.state(), State::Paused);
+
+        session.resume(t0 + secs(60));
+        session.advance(t0 + secs(61));
+        let push = session.log().last().unwrap();
+        assert_eq!(push.idle, Some(secs(31)));
+    }
+
+    #[test]
+    fn asks_whether_timed_excercises_were_done() {
+        let t0 = Instant::now();
+        let mut session = session("Workout T\nSet A | lead-in 00:03\n\tExcercise Plank 00:30\n\tExcercise Side plank 00:30\n");
+        session.require_acknowledgement();
+        session.start(t0);
+        session.tick(t0 + secs(39));
+        assert_eq!(session.state(), State::Acknowledging);
+        session.acknowledge(t0 + secs(40));
+        assert_eq!(session.state(), State::Excercising);
+        // no answer for the second one
+        session.tick(t0 + secs(80));
+        let excercises: Vec<_> = session
+            .log()
+            .iter()
+            .filter(|r| matches!(r.kind, StepKind::Excercise { .. }))
+            .map(|r| r.acknowledged)
+            .collect();
+        assert_eq!(excercises, [Some(true), Some(false)]);
+        assert_eq!(session.state(), State::Finished);
+    }
+
+    #[test]
+    fn runs_a_workout_starting_with_a_set_done_0_times() {
+        let t0 = Instant::now();
+        let mut session = session(
+            "Workout T\nSet Warmup x0\n\tExcercise Jog 01:00\nSet Main | lead-in 00:03\n\tExcercise Push x10\n",
+        );
+        session.start(t0);
+        session.tick(t0 + secs(9));
+        assert_eq!(current(&session), ("Push".to_owned(), 1, 0));
+        assert_eq!(session.resume_position(), Some((1, 0, 0)));
+    }
+}
@@ -0,0 +1,747 @@
+use std::{fmt::Write, time::Duration};
+
+use anyhow::Result;
+use chrono::{Datelike, Days, NaiveDate};
+use plotters::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    locale,
+    record::{AbortReason, SessionRecord, StepRecord},
+    session::StepKind,
+    workout::{ExcerciseAmout, Weight},
+};
+
+const WEEKS: u64 = 12;
+const BAR_WIDTH: f64 = 40.;
+const TREND_SESSIONS: usize = 20;
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Everything `workout stats` prints, for other programs to show the same
+/// numbers.
+#[derive(Serialize)]
+pub struct Summary {
+    pub totals: Totals,
+    pub weeks: Vec<Week>,
+    pub abort_reasons: Vec<AbortCount>,
+    pub trends: Vec<Trend>,
+    pub volumes: Vec<Volume>,
+    pub personal_records: Vec<PersonalRecord>,
+    pub streaks: Streaks,
+    pub excercise_times: Vec<ExcerciseTime>,
+}
+impl Summary {
+    pub fn new(records: &[SessionRecord], today: NaiveDate) -> Self {
+        Self {
+            totals: totals(records),
+            weeks: weeks(records, today),
+            abort_reasons: abort_reasons(records),
+            trends: rep_trends(records),
+            volumes: excercise_volumes(records, today),
+            personal_records: personal_records(records),
+            streaks: streaks(records, today),
+            excercise_times: excercise_times(records),
+        }
+    }
+}
+
+/// Of every session there is.
+#[derive(Serialize)]
+pub struct Totals {
+    pub sessions: usize,
+    pub completed: usize,
+    /// Rests included.
+    pub minutes: f64,
+}
+
+#[derive(Serialize)]
+pub struct Week {
+    /// The Monday it starts on.
+    pub start: NaiveDate,
+    pub sessions: usize,
+    pub reps: f64,
+    /// Spent in excercises, not rests.
+    pub minutes: f64,
+}
+
+#[derive(Serialize)]
+pub struct AbortCount {
+    /// `None` for sessions where it wasn't said.
+    pub reason: Option<AbortReason>,
+    pub sessions: usize,
+}
+
+/// Reps per session of a rep excercise, oldest first.
+#[derive(Serialize)]
+pub struct Trend {
+    pub excercise: String,
+    pub reps: Vec<f64>,
+}
+
+/// An excercise's volume in every one of the last weeks, oldest first.
+#[derive(Serialize)]
+pub struct Volume {
+    pub excercise: String,
+    /// `reps` if it's done for reps, `mins` otherwise.
+    pub unit: &'static str,
+    pub weeks: Vec<f64>,
+}
+
+/// The most reps in a set and the heaviest weight of an excercise, and when.
+#[derive(Serialize)]
+pub struct PersonalRecord {
+    pub excercise: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reps: Option<Best<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<Best<Weight>>,
+}
+
+/// All the time spent in an excercise.
+#[derive(Serialize)]
+pub struct ExcerciseTime {
+    pub excercise: String,
+    pub minutes: f64,
+    /// How many sessions it was in.
+    pub sessions: usize,
+}
+
+#[derive(Serialize, Clone, Copy)]
+pub struct Best<T> {
+    pub value: T,
+    pub date: NaiveDate,
+}
+
+/// Of the last year.
+#[derive(Serialize)]
+pub struct Streaks {
+    pub training_days: usize,
+    /// In days in a row.
+    pub longest: usize,
+    /// Up to today, or yesterday if there's been nothing yet today.
+    pub current: usize,
+}
+
+pub fn sparkline(values: &[f64]) -> String {
+    let max = values.iter().copied().fold(0., f64::max);
+    values
+        .iter()
+        .map(|v| {
+            if max == 0. {
+                SPARKS[0]
+            } else {
+                SPARKS[((v / max) * (SPARKS.len() - 1) as f64).round() as usize]
+            }
+        })
+        .collect()
+}
+
+/// A horizontal bar for every row, scaled to the biggest.
+pub fn bars(rows: &[(String, f64)]) -> String {
+    let max = rows.iter().map(|(_, v)| *v).fold(0., f64::max);
+    let label_width = rows
+        .iter()
+        .map(|(l, _)| l.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for (label, value) in rows {
+        let width = if max == 0. {
+            0
+        } else {
+            (value / max * BAR_WIDTH).round() as usize
+        };
+        let _ = writeln!(
+            out,
+            "  {label:label_width$} {} {}",
+            "█".repeat(width),
+            value.round() as u64
+        );
+    }
+    out
+}
+
+/// Reps done in an excercise step: the ones typed in or counted after going
+/// to failure, else the planned ones. None if it was skipped.
+pub fn reps_of(step: &StepRecord) -> Option<u16> {
+    if step.skipped {
+        return None;
+    }
+    match &step.kind {
+        StepKind::Excercise {
+            amount: ExcerciseAmout::Reps(r),
+            ..
+        } => Some(step.reps.unwrap_or(*r)),
+        StepKind::Excercise {
+            amount: ExcerciseAmout::Failure,
+            ..
+        } => step.reps,
+        _ => None,
+    }
+}
+
+pub fn totals(records: &[SessionRecord]) -> Totals {
+    Totals {
+        sessions: records.len(),
+        completed: records.iter().filter(|r| r.completed).count(),
+        minutes: records
+            .iter()
+            .map(|r| r.duration().as_secs_f64() / 60.)
+            .fold(0., |a, b| a + b),
+    }
+}
+
+/// Of every excercise done, the most first.
+pub fn excercise_times(records: &[SessionRecord]) -> Vec<ExcerciseTime> {
+    let mut times: Vec<ExcerciseTime> = vec![];
+    for record in records {
+        let mut seen: Vec<&str> = vec![];
+        for step in &record.steps {
+            let (StepKind::Excercise { name, .. }, false) = (&step.kind, step.skipped) else {
+                continue;
+            };
+            let minutes = step.duration.as_secs_f64() / 60.;
+            let first = !seen.contains(&name.as_str());
+            if first {
+                seen.push(name);
+            }
+            match times.iter_mut().find(|t| &t.excercise == name) {
+                Some(time) => {
+                    time.minutes += minutes;
+                    time.sessions += first as usize;
+                }
+                None => times.push(ExcerciseTime {
+                    excercise: name.clone(),
+                    minutes,
+                    sessions: 1,
+                }),
+            }
+        }
+    }
+    times.sort_by(|a, b| b.minutes.total_cmp(&a.minutes));
+    times
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday() as u64)
+}
+
+/// Sessions, reps and excercise minutes of each of the last weeks, oldest
+/// first.
+pub fn weeks(records: &[SessionRecord], today: NaiveDate) -> Vec<Week> {
+    let this_week = week_start(today);
+    (0..WEEKS)
+        .rev()
+        .map(|w| {
+            let start = this_week - Days::new(7 * w);
+            let sessions: Vec<_> = records
+                .iter()
+                .filter(|r| week_start(r.start.date_naive()) == start)
+                .collect();
+            let steps = sessions.iter().flat_map(|r| &r.steps);
+            // summing no floats gives -0, which the JSON shows
+            let reps = steps
+                .clone()
+                .filter_map(reps_of)
+                .map(f64::from)
+                .fold(0., |a, b| a + b);
+            let minutes = steps
+                .filter(|s| matches!(s.kind, StepKind::Excercise { .. }) && !s.skipped)
+                .map(|s| s.duration.as_secs_f64() / 60.)
+                .fold(0., |a, b| a + b);
+            Week {
+                start,
+                sessions: sessions.len(),
+                reps,
+                minutes,
+            }
+        })
+        .collect()
+}
+
+/// Reps per session of every rep excercise, over the last sessions that had
+/// it.
+pub fn rep_trends(records: &[SessionRecord]) -> Vec<Trend> {
+    let mut trends: Vec<(String, Vec<f64>)> = vec![];
+    for record in records {
+        let mut session: Vec<(&str, f64)> = vec![];
+        for step in &record.steps {
+            let (StepKind::Excercise { name, .. }, Some(reps)) = (&step.kind, reps_of(step)) else {
+                continue;
+            };
+            match session.iter_mut().find(|(n, _)| n == name) {
+                Some((_, total)) => *total += reps as f64,
+                None => session.push((name, reps as f64)),
+            }
+        }
+
+        for (name, reps) in session {
+            match trends.iter_mut().find(|(n, _)| n == name) {
+                Some((_, values)) => values.push(reps),
+                None => trends.push((name.to_owned(), vec![reps])),
+            }
+        }
+    }
+
+    trends
+        .into_iter()
+        .map(|(excercise, mut reps)| {
+            let skip = reps.len().saturating_sub(TREND_SESSIONS);
+            reps.drain(..skip);
+            Trend { excercise, reps }
+        })
+        .collect()
+}
+
+pub fn stats(records: &[SessionRecord], today: NaiveDate) -> String {
+    let weeks = weeks(records, today);
+    let label = |d: &NaiveDate| d.format("%m-%d").to_string();
+
+    let mut out = String::new();
+    let totals = totals(records);
+    let streaks = streaks(records, today);
+    let _ = writeln!(
+        out,
+        "{} sessions ({} completed), {} in all, a streak of {} days now (longest {})\n",
+        totals.sessions,
+        totals.completed,
+        locale::minutes(Duration::from_secs_f64(totals.minutes * 60.)),
+        streaks.current,
+        streaks.longest
+    );
+    let _ = writeln!(out, "Sessions per week");
+    out.push_str(&bars(
+        &weeks
+            .iter()
+            .map(|w| (label(&w.start), w.sessions as f64))
+            .collect::<Vec<_>>(),
+    ));
+    let _ = writeln!(out, "\nReps per week");
+    out.push_str(&bars(
+        &weeks
+            .iter()
+            .map(|w| (label(&w.start), w.reps))
+            .collect::<Vec<_>>(),
+    ));
+    let _ = writeln!(out, "\nExcercise minutes per week");
+    out.push_str(&bars(
+        &weeks
+            .iter()
+            .map(|w| (label(&w.start), w.minutes))
+            .collect::<Vec<_>>(),
+    ));
+
+    let reasons = abort_reasons(records);
+    if !reasons.is_empty() {
+        let _ = writeln!(out, "\nWhy sessions were finished early");
+        out.push_str(&bars(
+            &reasons
+                .iter()
+                .map(|c| {
+                    let reason = c.reason.map_or("not said", |r| r.name());
+                    (reason.to_owned(), c.sessions as f64)
+                })
+                .collect::<Vec<_>>(),
+        ));
+    }
+
+    let trends = rep_trends(records);
+    if !trends.is_empty() {
+        let _ = writeln!(out, "\nReps per session (last {TREND_SESSIONS})");
+        let name_width = trends
+            .iter()
+            .map(|t| t.excercise.chars().count())
+            .max()
+            .unwrap_or(0);
+        for Trend { excercise, reps } in &trends {
+            let _ = writeln!(
+                out,
+                "  {excercise:name_width$} {} {:.0} -> {:.0}",
+                sparkline(reps),
+                reps.first().unwrap_or(&0.),
+                reps.last().unwrap_or(&0.)
+            );
+        }
+    }
+
+    let personal = personal_records(records);
+    if !personal.is_empty() {
+        let _ = writeln!(out, "\nPersonal records");
+        let name_width = personal
+            .iter()
+            .map(|r| r.excercise.chars().count())
+            .max()
+            .unwrap_or(0);
+        for record in &personal {
+            let mut bests = vec![];
+            if let Some(Best { value, date }) = record.reps {
+                bests.push(format!("{value} reps on {date}"));
+            }
+            if let Some(Best { value, date }) = record.weight {
+                bests.push(format!("{} on {date}", locale::weight(&value)));
+            }
+            let _ = writeln!(
+                out,
+                "  {:name_width$} {}",
+                record.excercise,
+                bests.join(", ")
+            );
+        }
+    }
+
+    let times = excercise_times(records);
+    if !times.is_empty() {
+        let _ = writeln!(out, "\nTime per excercise");
+        let name_width = times
+            .iter()
+            .map(|t| t.excercise.chars().count())
+            .max()
+            .unwrap_or(0);
+        for time in &times {
+            let _ = writeln!(
+                out,
+                "  {:name_width$} {} in {} sessions",
+                time.excercise,
+                locale::minutes(Duration::from_secs_f64(time.minutes * 60.)),
+                time.sessions
+            );
+        }
+    }
+    out
+}
+
+/// How many sessions were finished early for every reason, and without one.
+pub fn abort_reasons(records: &[SessionRecord]) -> Vec<AbortCount> {
+    let early: Vec<_> = records.iter().filter(|r| !r.completed).collect();
+    let mut counts: Vec<_> = AbortReason::ALL
+        .iter()
+        .copied()
+        .map(Some)
+        .chain([None])
+        .map(|reason| AbortCount {
+            reason,
+            sessions: early.iter().filter(|r| r.abort_reason == reason).count(),
+        })
+        .collect();
+    counts.retain(|c| c.sessions > 0);
+    counts
+}
+
+/// Of every excercise that had reps or a weight, in the order they were
+/// first done.
+pub fn personal_records(records: &[SessionRecord]) -> Vec<PersonalRecord> {
+    // pounds and kilos compare
+    let kilos = |w: &Weight| {
+        if w.pounds {
+            w.amount * 0.4536
+        } else {
+            w.amount
+        }
+    };
+
+    let mut bests: Vec<PersonalRecord> = vec![];
+    for record in records {
+        let date = record.start.date_naive();
+        for step in &record.steps {
+            let (StepKind::Excercise { name, .. }, false) = (&step.kind, step.skipped) else {
+                continue;
+            };
+            let (reps, weight) = (reps_of(step), step.weight);
+            if reps.is_none() && weight.is_none() {
+                continue;
+            }
+            let best = match bests.iter().position(|b| &b.excercise == name) {
+                Some(i) => &mut bests[i],
+                None => {
+                    bests.push(PersonalRecord {
+                        excercise: name.clone(),
+                        reps: None,
+                        weight: None,
+                    });
+                    bests.last_mut().unwrap()
+                }
+            };
+            if let Some(value) = reps {
+                if best.reps.is_none_or(|most| value > most.value) {
+                    best.reps = Some(Best { value, date });
+                }
+            }
+            if let Some(value) = weight {
+                if best
+                    .weight
+                    .is_none_or(|heaviest| kilos(&value) > kilos(&heaviest.value))
+                {
+                    best.weight = Some(Best { value, date });
+                }
+            }
+        }
+    }
+    bests
+}
+
+/// Days with a session in them, over the weeks of the last year the
+/// heatmap shows.
+pub fn streaks(records: &[SessionRecord], today: NaiveDate) -> Streaks {
+    let first = week_start(today) - Days::new(7 * 52);
+    let mut trained = vec![false; (today - first).num_days() as usize + 1];
+    for record in records {
+        let date = record.start.date_naive();
+        if (first..=today).contains(&date) {
+            trained[(date - first).num_days() as usize] = true;
+        }
+    }
+
+    let longest = trained.split(|t| !t).map(<[bool]>::len).max().unwrap_or(0);
+    // today can still be trained on
+    let until = match trained.last() {
+        Some(true) => trained.len(),
+        _ => trained.len() - 1,
+    };
+    let current = trained[..until].iter().rev().take_while(|t| **t).count();
+    Streaks {
+        training_days: trained.iter().filter(|t| **t).count(),
+        longest,
+        current,
+    }
+}
+
+/// Weekly volume of every excercise over the last weeks, oldest first: reps
+/// if it's done for reps, minutes otherwise.
+pub fn excercise_volumes(records: &[SessionRecord], today: NaiveDate) -> Vec<Volume> {
+    let this_week = week_start(today);
+    let first = this_week - Days::new(7 * (WEEKS - 1));
+
+    let mut volumes: Vec<(String, &'static str, Vec<f64>)> = vec![];
+    for record in records {
+        let date = record.start.date_naive();
+        if date < first {
+            continue;
+        }
+        let week = ((week_start(date) - first).num_days() / 7) as usize;
+
+        for step in &record.steps {
+            let (StepKind::Excercise { name, .. }, false) = (&step.kind, step.skipped) else {
+                continue;
+            };
+            let (unit, value) = match reps_of(step) {
+                Some(reps) => ("reps", reps as f64),
+                None => ("mins", step.duration.as_secs_f64() / 60.),
+            };
+
+            let index = match volumes.iter().position(|(n, u, _)| n == name && *u == unit) {
+                Some(i) => i,
+                None => {
+                    volumes.push((name.clone(), unit, vec![0.; WEEKS as usize]));
+                    volumes.len() - 1
+                }
+            };
+            volumes[index].2[week] += value;
+        }
+    }
+    volumes
+        .into_iter()
+        .map(|(excercise, unit, weeks)| Volume {
+            excercise,
+            unit,
+            weeks,
+        })
+        .collect()
+}
+
+/// An SVG line chart of every excercise's weekly volume.
+pub fn chart_svg(records: &[SessionRecord], today: NaiveDate) -> Result<String> {
+    const PANEL: u32 = 200;
+
+    let volumes = excercise_volumes(records, today);
+    let first = week_start(today) - Days::new(7 * (WEEKS - 1));
+    let week_label = |w: &u64| (first + Days::new(7 * w)).format("%m-%d").to_string();
+
+    let mut svg = String::new();
+    {
+        let height = PANEL * volumes.len().max(1) as u32;
+        let root = SVGBackend::with_string(&mut svg, (640, height)).into_drawing_area();
+        root.fill(&WHITE)?;
+
+        let panels = root.split_evenly((volumes.len().max(1), 1));
+        for (
+            area,
+            Volume {
+                excercise: name,
+                unit,
+                weeks: values,
+            },
+        ) in panels.iter().zip(&volumes)
+        {
+            let max = values.iter().copied().fold(0., f64::max).max(1.);
+            let mut chart = ChartBuilder::on(area)
+                .caption(format!("{name} ({unit} per week)"), ("sans-serif", 16))
+                .margin(10)
+                .x_label_area_size(20)
+                .y_label_area_size(40)
+                .build_cartesian_2d(0..WEEKS - 1, 0f64..max * 1.1)?;
+            chart
+                .configure_mesh()
+                .x_labels(WEEKS as usize)
+                .x_label_formatter(&week_label)
+                .y_labels(4)
+                .draw()?;
+            chart.draw_series(LineSeries::new(
+                values.iter().enumerate().map(|(w, v)| (w as u64, *v)),
+                BLUE.stroke_width(2),
+            ))?;
+        }
+        root.present()?;
+    }
+    Ok(svg)
+}
+
+/// A GitHub style calendar of the last year in the terminal: a column per
+/// week, a row per weekday, darker for longer sessions.
+pub fn heatmap(records: &[SessionRecord], today: NaiveDate) -> String {
+    const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+    const WEEKDAYS: [&str; 7] = ["Mon", "", "Wed", "", "Fri", "", "Sun"];
+
+    let first = week_start(today) - Days::new(7 * 52);
+    let days = (today - first).num_days() as usize + 1;
+    let mut minutes = vec![0.; days];
+    for record in records {
+        let date = record.start.date_naive();
+        if (first..=today).contains(&date) {
+            minutes[(date - first).num_days() as usize] += record.duration().as_secs_f64() / 60.;
+        }
+    }
+    let max = minutes.iter().copied().fold(0., f64::max);
+
+    // month names over the week they start in, if there's room
+    let mut months = vec![' '; 53];
+    let mut free = 0;
+    for week in 0..53 {
+        let date = first + Days::new(7 * week as u64);
+        let starts_month = week == 0 || (date - Days::new(7)).month() != date.month();
+        if starts_month && week >= free && week + 3 <= months.len() {
+            for (i, c) in date.format("%b").to_string().chars().enumerate() {
+                months[week + i] = c;
+            }
+            free = week + 4;
+        }
+    }
+    let mut out = format!("    {}\n", months.iter().collect::<String>().trim_end());
+
+    for (weekday, label) in WEEKDAYS.iter().enumerate() {
+        let _ = write!(out, "{label:3} ");
+        for week in 0..53 {
+            let day = week * 7 + weekday;
+            let Some(&m) = minutes.get(day) else {
+                break;
+            };
+            let shade = if m == 0. || max == 0. {
+                0
+            } else {
+                ((m / max * 4.).ceil() as usize).clamp(1, 4)
+            };
+            out.push(SHADES[shade]);
+        }
+        out.push('\n');
+    }
+
+    let streaks = streaks(records, today);
+    let _ = writeln!(
+        out,
+        "\n{} training days in the last year, longest streak {} days, {} now",
+        streaks.training_days, streaks.longest, streaks.current
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    fn step(amount: ExcerciseAmout, reps: Option<u16>) -> StepRecord {
+        StepRecord {
+            set: 0,
+            set_rep: 0,
+            element: None,
+            group: None,
+            kind: StepKind::Excercise {
+                index: 0,
+                name: "Push".to_owned(),
+                amount,
+                estimate: None,
+            },
+            planned: None,
+            started: Duration::ZERO,
+            duration: Duration::from_secs(30),
+            reps,
+            collect: vec![],
+            rpe: None,
+            weight: None,
+            video: false,
+            idle: None,
+            acknowledged: None,
+            skipped: false,
+        }
+    }
+
+    #[test]
+    fn typed_in_reps_count_over_the_planned_ones() {
+        assert_eq!(reps_of(&step(ExcerciseAmout::Reps(5), Some(7))), Some(7));
+        assert_eq!(reps_of(&step(ExcerciseAmout::Reps(5), None)), Some(5));
+        assert_eq!(reps_of(&step(ExcerciseAmout::Failure, Some(12))), Some(12));
+        assert_eq!(reps_of(&step(ExcerciseAmout::Failure, None)), None);
+    }
+
+    #[test]
+    fn skipped_steps_have_no_reps() {
+        let skipped = StepRecord {
+            skipped: true,
+            ..step(ExcerciseAmout::Reps(5), Some(7))
+        };
+        assert_eq!(reps_of(&skipped), None);
+    }
+
+    #[test]
+    fn empty_weeks_are_zero_not_negative() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 8).unwrap();
+        for week in weeks(&[], today) {
+            assert!(week.reps.is_sign_positive() && week.minutes.is_sign_positive());
+        }
+        assert!(totals(&[]).minutes.is_sign_positive());
+    }
+
+    #[test]
+    fn weeks_add_up_their_sessions() {
+        let start = Local.with_ymd_and_hms(2024, 5, 7, 18, 0, 0).unwrap();
+        let record = SessionRecord {
+            workout: "T".to_owned(),
+            start,
+            end: start + chrono::Duration::minutes(5),
+            completed: true,
+            resume_from: None,
+            resume_offset: None,
+            abort_reason: None,
+            program: None,
+            day: None,
+            week: None,
+            tags: vec![],
+            sets: vec![],
+            steps: vec![
+                step(ExcerciseAmout::Reps(10), Some(12)),
+                step(ExcerciseAmout::Reps(8), None),
+            ],
+            edits: vec![],
+        };
+        let weeks = weeks(&[record], NaiveDate::from_ymd_opt(2024, 5, 8).unwrap());
+        let this_week = weeks.last().unwrap();
+        assert_eq!(
+            this_week.start,
+            NaiveDate::from_ymd_opt(2024, 5, 6).unwrap()
+        );
+        assert_eq!(this_week.sessions, 1);
+        assert_eq!(this_week.reps, 20.);
+        assert_eq!(this_week.minutes, 1.);
+        assert_eq!(weeks[weeks.len() - 2].sessions, 0);
+    }
+}
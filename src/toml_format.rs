@@ -0,0 +1,532 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    progression::Progression,
+    workout::{
+        format_duration, parse_duration, CircuitRest, Collect, CueOverride, ExcerciseAmout,
+        ExcerciseDetails, ExcerciseOptions, RestAfter, Say, Weight, Workout, WorkoutSet,
+        WorkoutSetElement,
+    },
+};
+
+// durations are "MM:SS" strings, same as in the text format. JSON and YAML
+// files hold the same tables
+
+#[derive(Serialize, Deserialize)]
+struct TomlWorkout {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lead_in: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    audio: Option<String>,
+    #[serde(default)]
+    sets: Vec<TomlSet>,
+}
+
+fn one() -> u16 {
+    1
+}
+fn is_one(v: &u16) -> bool {
+    *v == 1
+}
+fn is_false(v: &bool) -> bool {
+    !v
+}
+
+#[derive(Serialize, Deserialize)]
+struct TomlSet {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default = "one", skip_serializing_if = "is_one")]
+    reps: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rest_after: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lead_in: Option<String>,
+    #[serde(default)]
+    parts: Vec<TomlElement>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct TomlElement {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    excercise: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    time: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    midbeep: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reps: Option<u16>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    to_failure: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    calories: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    meters: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    estimate: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    optional: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    escalate: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prep: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cue: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    collect: Vec<Collect>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    progress: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    replaces: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tempo: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    metronome: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    equipment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    demo: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    weight: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    say: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ramp: Option<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    muscles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    intervals: Option<TomlIntervals>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    circuit: Option<TomlCircuit>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TomlCircuit {
+    reps: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rest: Option<String>,
+    /// each, pair or round, round if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rest_after: Option<String>,
+    #[serde(default)]
+    parts: Vec<TomlElement>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TomlIntervals {
+    rounds: u16,
+    work: String,
+    rest: String,
+}
+
+fn load_element(p: TomlElement, at: &str) -> Result<WorkoutSetElement> {
+    if let Some(c) = p.circuit {
+        let mut parts = vec![];
+        for (i, p) in c.parts.into_iter().enumerate() {
+            parts.push(load_element(
+                p,
+                &format!("{}, {})", at.trim_end_matches(')'), i + 1),
+            )?);
+        }
+        let rest = match c.rest {
+            Some(rest) => Some(CircuitRest {
+                duration: parse_duration(&rest).map_err(|_| {
+                    anyhow::Error::msg(format!("Couldn't parse circuit rest {}", at))
+                })?,
+                after: match c.rest_after {
+                    Some(after) => after.parse()?,
+                    None => RestAfter::Round,
+                },
+            }),
+            None => None,
+        };
+        return Ok(WorkoutSetElement::Circuit {
+            reps: c.reps,
+            parts,
+            rest,
+        });
+    }
+
+    if let Some(i) = p.intervals {
+        let parse = |d: &str| {
+            parse_duration(d)
+                .map_err(|_| anyhow::Error::msg(format!("Couldn't parse interval duration {}", at)))
+        };
+        return Ok(WorkoutSetElement::Intervals {
+            name: p.excercise.unwrap_or_else(|| "Intervals".to_owned()),
+            rounds: i.rounds,
+            work: parse(&i.work)?,
+            rest: parse(&i.rest)?,
+        });
+    }
+
+    let part = match (p.excercise, p.rest) {
+        (Some(name), None) => {
+            let amount = match (p.time, p.reps, p.calories, p.meters) {
+                (None, None, None, None) if p.to_failure => ExcerciseAmout::Failure,
+                (Some(time), None, None, None) if !p.to_failure => ExcerciseAmout::Time {
+                    duration: parse_duration(&time).map_err(|_| {
+                        anyhow::Error::msg(format!(
+                            "Couldn't parse excercise duration {}",
+                            at
+                        ))
+                    })?,
+                    midbeep: p.midbeep,
+                },
+                (None, Some(reps), None, None) if !p.to_failure => ExcerciseAmout::Reps(reps),
+                (None, None, Some(cal), None) if !p.to_failure => {
+                    ExcerciseAmout::Calories(cal)
+                }
+                (None, None, None, Some(meters)) if !p.to_failure => {
+                    ExcerciseAmout::Meters(meters)
+                }
+                _ => {
+                    return Err(anyhow::Error::msg(format!(
+                        "Excercise needs exactly one of `time`, `reps`, `to_failure`, `calories` or `meters` {}",
+                        at
+                    )))
+                }
+            };
+            let cue = match p.cue {
+                Some(cue) => Some(
+                    CueOverride::parse(&cue)
+                        .map_err(|e| anyhow::Error::msg(format!("{e} {}", at)))?,
+                ),
+                None => None,
+            };
+            let prep = match p.prep {
+                Some(prep) => Some(parse_duration(&prep).map_err(|_| {
+                    anyhow::Error::msg(format!("Couldn't parse prep duration {}", at))
+                })?),
+                None => None,
+            };
+            let progress = match p.progress {
+                Some(progress) => Some(
+                    Progression::parse(&progress)
+                        .map_err(|e| anyhow::Error::msg(format!("{e} {}", at)))?,
+                ),
+                None => None,
+            };
+            let estimate = match p.estimate {
+                Some(estimate) => Some(parse_duration(&estimate).map_err(|_| {
+                    anyhow::Error::msg(format!("Couldn't parse estimated duration {}", at))
+                })?),
+                None => None,
+            };
+            let weight = match p.weight {
+                Some(weight) => Some(
+                    Weight::parse(&weight)
+                        .map_err(|e| anyhow::Error::msg(format!("{e} {}", at)))?,
+                ),
+                None => None,
+            };
+            let say = p
+                .say
+                .iter()
+                .map(|s| Say::parse(s).map_err(|e| anyhow::Error::msg(format!("{e} {}", at))))
+                .collect::<Result<_>>()?;
+            WorkoutSetElement::Excercise {
+                name,
+                amount,
+                options: ExcerciseOptions {
+                    cue,
+                    prep,
+                    optional: p.optional,
+                    estimate,
+                    escalate: p.escalate,
+                    collect: p.collect,
+                    replaces: p.replaces,
+                    progress,
+                    ramp: p.ramp,
+                    muscles: p.muscles.iter().map(|m| m.to_lowercase()).collect(),
+                    details: Box::new(ExcerciseDetails {
+                        tempo: p.tempo,
+                        metronome: p.metronome,
+                        equipment: p.equipment,
+                        note: p.note,
+                        demo: p.demo,
+                        weight,
+                        say,
+                    }),
+                },
+            }
+        }
+        (None, Some(rest)) => WorkoutSetElement::Rest {
+            duration: parse_duration(&rest)
+                .map_err(|_| anyhow::Error::msg(format!("Couldn't parse rest duration {}", at)))?,
+        },
+        _ => {
+            return Err(anyhow::Error::msg(format!(
+                "Part needs exactly one of `excercise` or `rest` {}",
+                at
+            )))
+        }
+    };
+    Ok(part)
+}
+
+pub fn load_toml(source: &str) -> Result<Workout> {
+    from_tables(toml::from_str(source)?)
+}
+
+pub fn load_json(source: &str) -> Result<Workout> {
+    from_tables(serde_json::from_str(source)?)
+}
+
+pub fn load_yaml(source: &str) -> Result<Workout> {
+    from_tables(serde_yaml::from_str(source)?)
+}
+
+fn from_tables(w: TomlWorkout) -> Result<Workout> {
+    let lead_in = match w.lead_in {
+        Some(l) => Some(
+            parse_duration(&l)
+                .map_err(|_| anyhow::Error::msg("Couldn't parse lead-in duration of workout"))?,
+        ),
+        None => None,
+    };
+
+    let mut sections = vec![];
+    // groups carry over into the next sets
+    let mut group: Option<String> = None;
+    for (si, s) in w.sets.into_iter().enumerate() {
+        let set_rest = match s.rest {
+            Some(r) => Some(parse_duration(&r).map_err(|_| {
+                anyhow::Error::msg(format!("Couldn't parse rest duration of set {}", si + 1))
+            })?),
+            None => None,
+        };
+        let rest_after = match s.rest_after {
+            Some(r) => Some(parse_duration(&r).map_err(|_| {
+                anyhow::Error::msg(format!("Couldn't parse rest after set {} duration", si + 1))
+            })?),
+            None => None,
+        };
+        let set_lead_in = match s.lead_in {
+            Some(l) => Some(parse_duration(&l).map_err(|_| {
+                anyhow::Error::msg(format!("Couldn't parse lead-in duration of set {}", si + 1))
+            })?),
+            None => None,
+        };
+
+        let mut parts = vec![];
+        let mut groups: Vec<_> = group.iter().map(|g| (0, g.clone())).collect();
+        for (pi, mut p) in s.parts.into_iter().enumerate() {
+            if let Some(g) = p.group.take() {
+                groups.push((pi, g.clone()));
+                group = Some(g);
+            }
+
+            parts.push(load_element(
+                p,
+                &format!("(set {}, part {})", si + 1, pi + 1),
+            )?);
+        }
+
+        sections.push(WorkoutSet {
+            name: s.name,
+            parts,
+            reps: s.reps,
+            set_rest,
+            rest_after,
+            lead_in: set_lead_in,
+            groups,
+        });
+    }
+
+    Ok(Workout {
+        name: w.name,
+        sections,
+        lead_in,
+        defines: vec![],
+        audio: w.audio,
+    })
+}
+
+fn format_element(p: &WorkoutSetElement) -> TomlElement {
+    match p {
+        WorkoutSetElement::Excercise {
+            name,
+            amount,
+            options,
+        } => {
+            let mut part = TomlElement {
+                excercise: Some(name.clone()),
+                estimate: options.estimate.map(format_duration),
+                optional: options.optional,
+                escalate: options.escalate,
+                prep: options.prep.map(format_duration),
+                cue: options.cue.as_ref().map(ToString::to_string),
+                collect: options.collect.clone(),
+                replaces: options.replaces.clone(),
+                progress: options.progress.as_ref().map(ToString::to_string),
+                tempo: options.details.tempo.clone(),
+                metronome: options.details.metronome,
+                equipment: options.details.equipment.clone(),
+                note: options.details.note.clone(),
+                demo: options.details.demo.clone(),
+                weight: options.details.weight.map(|w| w.to_string()),
+                say: options
+                    .details
+                    .say
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+                ramp: options.ramp,
+                muscles: options.muscles.clone(),
+                ..Default::default()
+            };
+            match amount {
+                ExcerciseAmout::Time { duration, midbeep } => {
+                    part.time = Some(format_duration(*duration));
+                    part.midbeep = *midbeep;
+                }
+                ExcerciseAmout::Reps(r) => part.reps = Some(*r),
+                ExcerciseAmout::Failure => part.to_failure = true,
+                ExcerciseAmout::Calories(c) => part.calories = Some(*c),
+                ExcerciseAmout::Meters(m) => part.meters = Some(*m),
+            }
+            part
+        }
+        WorkoutSetElement::Rest { duration } => TomlElement {
+            rest: Some(format_duration(*duration)),
+            ..Default::default()
+        },
+        WorkoutSetElement::Intervals {
+            name,
+            rounds,
+            work,
+            rest,
+        } => TomlElement {
+            excercise: Some(name.clone()),
+            intervals: Some(TomlIntervals {
+                rounds: *rounds,
+                work: format_duration(*work),
+                rest: format_duration(*rest),
+            }),
+            ..Default::default()
+        },
+        WorkoutSetElement::Circuit { reps, parts, rest } => TomlElement {
+            circuit: Some(TomlCircuit {
+                reps: *reps,
+                rest: rest.map(|rest| format_duration(rest.duration)),
+                rest_after: rest.map(|rest| rest.after.to_string()),
+                parts: parts.iter().map(format_element).collect(),
+            }),
+            ..Default::default()
+        },
+    }
+}
+
+pub fn format_toml(workout: &Workout) -> Result<String> {
+    Ok(toml::to_string(&to_tables(workout))?)
+}
+
+pub fn format_json(workout: &Workout) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&to_tables(workout))? + "\n")
+}
+
+pub fn format_yaml(workout: &Workout) -> Result<String> {
+    Ok(serde_yaml::to_string(&to_tables(workout))?)
+}
+
+fn to_tables(workout: &Workout) -> TomlWorkout {
+    let mut group = None;
+    TomlWorkout {
+        name: workout.name.clone(),
+        lead_in: workout.lead_in.map(format_duration),
+        audio: workout.audio.clone(),
+        sets: workout
+            .sections
+            .iter()
+            .map(|s| TomlSet {
+                name: s.name.clone(),
+                reps: s.reps,
+                rest: s.set_rest.map(format_duration),
+                rest_after: s.rest_after.map(format_duration),
+                lead_in: s.lead_in.map(format_duration),
+                parts: s
+                    .parts
+                    .iter()
+                    .enumerate()
+                    .map(|(pi, p)| {
+                        let starts_group = s.group_of(pi).filter(|g| group != Some(*g));
+                        if starts_group.is_some() {
+                            group = starts_group;
+                        }
+                        let mut part = format_element(p);
+                        part.group = starts_group.map(str::to_owned);
+                        part
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workout::format_workout;
+
+    const EXAMPLE: &str = include_str!("../workouts/example.txt");
+
+    fn example() -> Workout {
+        Workout::parse(EXAMPLE).unwrap()
+    }
+
+    #[test]
+    fn toml_round_trips() {
+        let workout = example();
+        let back = load_toml(&format_toml(&workout).unwrap()).unwrap();
+        assert_eq!(format_workout(&back), format_workout(&workout));
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let workout = example();
+        let back = load_json(&format_json(&workout).unwrap()).unwrap();
+        assert_eq!(format_workout(&back), format_workout(&workout));
+    }
+
+    #[test]
+    fn yaml_round_trips() {
+        let workout = example();
+        let back = load_yaml(&format_yaml(&workout).unwrap()).unwrap();
+        assert_eq!(format_workout(&back), format_workout(&workout));
+    }
+
+    #[test]
+    fn the_readme_table_loads() {
+        let workout = load_toml(
+            "name = \"T\"\n\n[[sets]]\nname = \"Main\"\nreps = 2\nrest = \"00:10\"\nparts = [\n    { excercise = \"Push\", time = \"00'05\" },\n    { rest = \"00:06\" },\n    { excercise = \"Curls\", reps = 10 },\n]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            format_workout(&workout),
+            "Format 2\nWorkout T\n\nSet Main x2\n\tExcercise Push 00:05\n\tRest 00:06\n\tExcercise Curls x10\nSet rest 00:10\n"
+        );
+    }
+
+    #[test]
+    fn a_part_is_an_excercise_or_a_rest() {
+        let error = match load_json(r#"{"name": "T", "sets": [{"parts": [{}]}]}"#) {
+            Ok(_) => panic!("loaded"),
+            Err(e) => e.to_string(),
+        };
+        assert_eq!(
+            error,
+            "Part needs exactly one of `excercise` or `rest` (set 1, part 1)"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    cell::RefCell,
+    io::{self, Stdout, Write},
+};
+
+use anyhow::Result;
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::terminal,
+    layout::{Constraint, Layout, Rect},
+    text::Line,
+    widgets::{Block, Gauge, Paragraph},
+    Terminal, TerminalOptions, Viewport,
+};
+
+use crate::{
+    frontend::{Frontend, View},
+    locale,
+    workout::format_duration,
+};
+
+// the step's bar, the session's bar and the next two steps
+const ROWS: u16 = 8;
+
+/// The current excercise with its countdown as a bar, what's next and how
+/// far along the whole session is, at the top of the terminal with the
+/// usual lines scrolling underneath.
+pub struct Tui {
+    terminal: RefCell<Terminal<CrosstermBackend<Stdout>>>,
+    lines: u16,
+}
+impl Tui {
+    pub fn open() -> Result<Self> {
+        let (columns, lines) = terminal::size()?;
+        if lines < ROWS + 4 {
+            return Err(anyhow::Error::msg(format!(
+                "The terminal needs at least {} lines for the TUI, it has {lines}",
+                ROWS + 4
+            )));
+        }
+        let mut out = io::stdout();
+        // clears it, and keeps the scrolling below the bars
+        write!(out, "\x1b[2J\x1b[{};{lines}r\x1b[{lines};1H", ROWS + 1)?;
+        out.flush()?;
+        let terminal = Terminal::with_options(
+            CrosstermBackend::new(io::stdout()),
+            TerminalOptions {
+                viewport: Viewport::Fixed(Rect::new(0, 0, columns, ROWS)),
+            },
+        )?;
+        Ok(Self {
+            terminal: RefCell::new(terminal),
+            lines,
+        })
+    }
+}
+impl Frontend for Tui {
+    fn draw(&self, view: &View) {
+        let mut terminal = self.terminal.borrow_mut();
+        // back to where the scrolling lines were after
+        let _ = write!(io::stdout(), "\x1b7");
+        let _ = terminal.draw(|frame| {
+            let [step, session, upcoming] = Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(2),
+            ])
+            .areas(frame.area());
+
+            let label = match view.time.is_empty() {
+                true => view.label.clone(),
+                false => format!("{} {}", view.label, view.time),
+            };
+            frame.render_widget(
+                Gauge::default()
+                    .block(Block::bordered().title(view.position.as_str()))
+                    .ratio(view.step.unwrap_or(0.))
+                    .label(label),
+                step,
+            );
+            frame.render_widget(
+                Gauge::default()
+                    .block(Block::bordered().title("Session"))
+                    .ratio(view.percent.clamp(0., 100.) / 100.)
+                    .label(format!(
+                        "{}%, {} in, ~{} to go",
+                        locale::decimal(view.percent, 0),
+                        format_duration(view.elapsed),
+                        format_duration(view.remaining)
+                    )),
+                session,
+            );
+            let lines: Vec<Line> = ["Next", "Then"]
+                .iter()
+                .zip(&view.upcoming)
+                .map(|(when, what)| Line::from(format!(" {when}: {what}")))
+                .collect();
+            frame.render_widget(Paragraph::new(lines), upcoming);
+        });
+        let _ = terminal.show_cursor();
+        let _ = write!(io::stdout(), "\x1b8");
+        let _ = io::stdout().flush();
+    }
+}
+impl Drop for Tui {
+    fn drop(&mut self) {
+        // the bars stay up there, with the lines going on under them
+        let _ = write!(io::stdout(), "\x1b[r\x1b[{};1H", self.lines);
+        let _ = io::stdout().flush();
+    }
+}
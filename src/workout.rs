@@ -1,6 +1,29 @@
-use std::{fmt::Display, thread, time::Duration};
+use std::{
+    fmt::{self, Display, Write},
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
+use chrono::Local;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    dirs,
+    frontend::{Frontend, View},
+    keys::{Interrupted, Keys, INTERRUPT},
+    locale, markdown,
+    measured::Measured,
+    preprocess,
+    progression::Progression,
+    record::{AbortReason, SessionRecord, StepRecord},
+    session::{Session, State, StepKind, WorkoutObserver},
+    toml_format,
+};
 
 #[derive(Hash, PartialEq, Eq, Clone, Copy)]
 pub enum BeepLevel {
@@ -9,6 +32,25 @@ pub enum BeepLevel {
     Low = 2,
 }
 impl BeepLevel {
+    pub fn parse(s: &str) -> Option<Self> {
+        use BeepLevel::*;
+        match s {
+            "high" => Some(High),
+            "mid" => Some(Mid),
+            "low" => Some(Low),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        use BeepLevel::*;
+        match self {
+            High => "high",
+            Mid => "mid",
+            Low => "low",
+        }
+    }
+
     pub fn get_frequency(&self) -> f32 {
         use BeepLevel::*;
         match self {
@@ -19,377 +61,2957 @@ impl BeepLevel {
     }
 }
 
-pub struct Workout<'a> {
-    sections: Vec<WorkoutSet<'a>>,
-    name: &'a str,
+#[derive(Clone)]
+pub enum Sound {
+    Beep(BeepLevel),
+    /// With the config's longer, softer `relax_envelope`.
+    Relax(BeepLevel),
+    File(String),
+    /// Played on one side only.
+    Panned(Box<Sound>, Pan),
+    /// Read out loud.
+    Speech(String),
+    /// When the workout starts and once it's done, three beeps unless the
+    /// config has a file for them.
+    Start,
+    Complete,
+    /// Quiet clicks for the phases of a tempo, over and over until
+    /// [`Sound::MetronomeStop`], mixed in with the other sounds.
+    Metronome(Vec<Duration>),
+    MetronomeStop,
+    /// Not a sound: silences the ones after it (or brings them back), the
+    /// cues that aren't heard still go out.
+    Mute(bool),
+    /// Not a sound either: reads the file ahead of its cue, so that one
+    /// plays right away.
+    Preload(String),
+}
+impl Sound {
+    /// What it is in a word, like `high` or `start`, or the text read out,
+    /// for where the cues go that don't play sounds.
+    pub fn name(&self) -> String {
+        match self {
+            Sound::Beep(level) | Sound::Relax(level) => match level {
+                BeepLevel::High => "high",
+                BeepLevel::Mid => "mid",
+                BeepLevel::Low => "low",
+            }
+            .to_owned(),
+            Sound::File(path) => path.clone(),
+            Sound::Panned(sound, _) => sound.name(),
+            Sound::Speech(text) => text.clone(),
+            Sound::Start => "start".to_owned(),
+            Sound::Complete => "complete".to_owned(),
+            Sound::Metronome(_) => "metronome".to_owned(),
+            Sound::MetronomeStop => "metronome stop".to_owned(),
+            Sound::Mute(true) => "mute".to_owned(),
+            Sound::Mute(false) => "unmute".to_owned(),
+            Sound::Preload(path) => format!("preload {path}"),
+        }
+    }
+}
+
+/// Where a cue is heard. `sides` is left at the start of a per-side
+/// excercise and right at the switch (and the end), centered otherwise.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Pan {
+    #[default]
+    Center,
+    Left,
+    Right,
+    Sides,
+}
+impl Pan {
+    /// Of the left and right channel, `None` if it plays on both.
+    pub fn volumes(&self) -> Option<[f32; 2]> {
+        match self {
+            Pan::Left => Some([1., 0.]),
+            Pan::Right => Some([0., 1.]),
+            Pan::Center | Pan::Sides => None,
+        }
+    }
+}
+
+/// Replaces the beeps of a single excercise.
+#[derive(Clone)]
+pub enum CueOverride {
+    Beep(BeepLevel),
+    /// Gentler beeps, at the given level or the usual ones.
+    Relax(Option<BeepLevel>),
+    Sound(String),
+    Silent,
+}
+impl CueOverride {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s == "silent" {
+            return Ok(CueOverride::Silent);
+        }
+        if let Some(file) = s.strip_prefix("sound ") {
+            return Ok(CueOverride::Sound(file.trim().to_owned()));
+        }
+        if let Some(level) = s
+            .strip_prefix("beep ")
+            .and_then(|l| BeepLevel::parse(l.trim()))
+        {
+            return Ok(CueOverride::Beep(level));
+        }
+        match s.strip_prefix("relax").map(str::trim) {
+            Some("") => return Ok(CueOverride::Relax(None)),
+            Some(level) => {
+                if let Some(level) = BeepLevel::parse(level) {
+                    return Ok(CueOverride::Relax(Some(level)));
+                }
+            }
+            None => {}
+        }
+        Err(anyhow::Error::msg(format!(
+            "Unknown cue `{s}` (expected `beep high|mid|low`, `relax [high|mid|low]`, `sound FILE` or `silent`)"
+        )))
+    }
+
+    /// What to play instead of a cue's default sound.
+    pub fn replace(&self, sound: &Sound) -> Option<Sound> {
+        match (self, sound) {
+            (CueOverride::Beep(level), _) => Some(Sound::Beep(*level)),
+            (CueOverride::Relax(Some(level)), _) => Some(Sound::Relax(*level)),
+            (CueOverride::Relax(None), Sound::Beep(level)) => Some(Sound::Relax(*level)),
+            (CueOverride::Relax(None), sound) => Some(sound.clone()),
+            (CueOverride::Sound(file), _) => Some(Sound::File(file.clone())),
+            (CueOverride::Silent, _) => None,
+        }
+    }
+}
+impl Display for CueOverride {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CueOverride::Beep(level) => write!(f, "beep {}", level.name()),
+            CueOverride::Relax(Some(level)) => write!(f, "relax {}", level.name()),
+            CueOverride::Relax(None) => write!(f, "relax"),
+            CueOverride::Sound(file) => write!(f, "sound {file}"),
+            CueOverride::Silent => write!(f, "silent"),
+        }
+    }
+}
+
+/// What a coach wants to hear back about an excercise, asked for after the
+/// session.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Collect {
+    Rpe,
+    Reps,
+    Video,
+}
+impl Collect {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim() {
+            "rpe" => Ok(Collect::Rpe),
+            "reps" => Ok(Collect::Reps),
+            "video" => Ok(Collect::Video),
+            s => Err(anyhow::Error::msg(format!(
+                "Unknown `{s}` to collect (expected `rpe`, `reps` or `video`)"
+            ))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Collect::Rpe => "rpe",
+            Collect::Reps => "reps",
+            Collect::Video => "video",
+        }
+    }
+}
+
+/// The optional `| attribute` tail of an excercise line.
+#[derive(Default, Clone)]
+pub struct ExcerciseOptions {
+    pub cue: Option<CueOverride>,
+    /// Setup time before the excercise starts, e.g. for changing equipment.
+    pub prep: Option<Duration>,
+    /// Asked about when reached, so it can be skipped.
+    pub optional: bool,
+    /// Expected time of an excercise that isn't timed.
+    pub estimate: Option<Duration>,
+    /// Beeps closer and closer together towards the end of a timed excercise.
+    pub escalate: bool,
+    pub collect: Vec<Collect>,
+    /// The base's excercise it takes the place of, in a file that `Extends`
+    /// another.
+    pub replaces: Option<String>,
+    pub progress: Option<Progression>,
+    /// Ramp-up sets before it, worked up to its weight.
+    pub ramp: Option<u16>,
+    /// Muscle groups it works, lowercase, for the config's `work_caps`.
+    pub muscles: Vec<String>,
+    // boxed, it's rarely there and would make every element bigger
+    pub details: Box<ExcerciseDetails>,
+}
+
+/// How to do an excercise, shown when it starts.
+#[derive(Default, Clone)]
+pub struct ExcerciseDetails {
+    /// e.g. `3-1-1-0`, seconds down, at the bottom, up and at the top.
+    pub tempo: Option<String>,
+    /// Clicks the `tempo` while it's on.
+    pub metronome: bool,
+    pub equipment: Option<String>,
+    pub note: Option<String>,
+    /// A link to a video or picture of how it's done.
+    pub demo: Option<String>,
+    pub weight: Option<Weight>,
+    pub say: Vec<Say>,
+}
+
+impl ExcerciseDetails {
+    /// How long each phase of the `tempo` takes, `X` (explosive) none, if
+    /// it's clicked and there's a rep to it.
+    pub fn metronome(&self) -> Option<Vec<Duration>> {
+        if !self.metronome {
+            return None;
+        }
+        let phases = self
+            .tempo
+            .as_ref()?
+            .split('-')
+            .map(|phase| match phase.trim() {
+                "X" | "x" => Some(Duration::ZERO),
+                secs => secs.parse().ok().map(Duration::from_secs),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        phases.iter().any(|p| !p.is_zero()).then_some(phases)
+    }
+}
+
+/// The load of an excercise, `100kg` or `225lb`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Weight {
+    pub amount: f32,
+    pub pounds: bool,
+}
+impl Weight {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (amount, pounds) = if let Some(kg) = s.strip_suffix("kg") {
+            (kg, false)
+        } else if let Some(lb) = s.strip_suffix("lbs").or_else(|| s.strip_suffix("lb")) {
+            (lb, true)
+        } else {
+            return Err(unexpected("a weight like 100kg or 225lb", s));
+        };
+        let amount = amount
+            .trim()
+            .parse()
+            .map_err(|_| unexpected("a weight like 100kg or 225lb", s))?;
+        Ok(Self { amount, pounds })
+    }
+
+    /// `percent` of it, rounded to what the plates allow.
+    pub fn percent(&self, percent: f32) -> Self {
+        let plates = if self.pounds { 5. } else { 2.5 };
+        Self {
+            amount: (self.amount * percent / 100. / plates).round() * plates,
+            pounds: self.pounds,
+        }
+    }
+}
+// as written, `100kg`
+impl Serialize for Weight {
+    fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+impl<'de> Deserialize<'de> for Weight {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        Self::parse(&String::deserialize(d)?).map_err(serde::de::Error::custom)
+    }
+}
+impl Display for Weight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            self.amount,
+            if self.pounds { "lb" } else { "kg" }
+        )
+    }
+}
+
+/// `say TEXT`, read out loud when the excercise starts, or `say MM:SS TEXT`
+/// that far into it.
+#[derive(Clone, PartialEq)]
+pub struct Say {
+    pub at: Duration,
+    pub text: String,
+}
+impl Say {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (at, text) = match s
+            .split_once(' ')
+            .and_then(|(at, text)| Some((parse_duration(at).ok()?, text)))
+        {
+            Some((at, text)) => (at, text.trim()),
+            None => (Duration::ZERO, s),
+        };
+        if text.is_empty() {
+            return Err(anyhow::Error::msg("Nothing to say"));
+        }
+        Ok(Self {
+            at,
+            text: text.to_owned(),
+        })
+    }
+}
+impl Display for Say {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.at.is_zero() {
+            write!(f, "{} ", format_duration(self.at))?;
+        }
+        write!(f, "{}", self.text)
+    }
+}
+impl ExcerciseOptions {
+    pub fn parse(attributes: &str) -> Result<Self> {
+        let mut options = Self::default();
+        for attr in attributes.split('|').map(str::trim) {
+            if attr.is_empty() {
+                continue;
+            }
+            if attr == "optional" {
+                options.optional = true;
+                continue;
+            }
+            if attr == "escalate" {
+                options.escalate = true;
+                continue;
+            }
+            if let Some(estimate) = attr.strip_prefix("estimate ") {
+                options.estimate = Some(parse_duration(estimate.trim()).map_err(|_| {
+                    unexpected("an estimated duration in MM:SS or MM'SS", estimate.trim())
+                })?);
+                continue;
+            }
+            if let Some(collect) = attr.strip_prefix("collect:") {
+                for c in collect.split(',') {
+                    options.collect.push(Collect::parse(c)?);
+                }
+                continue;
+            }
+            if let Some(progress) = attr.strip_prefix("progress ") {
+                options.progress = Some(Progression::parse(progress)?);
+                continue;
+            }
+            if let Some(ramp) = attr.strip_prefix("ramp ") {
+                options.ramp = Some(
+                    ramp.trim()
+                        .parse()
+                        .map_err(|_| unexpected("a number of ramp-up sets", ramp.trim()))?,
+                );
+                continue;
+            }
+            if let Some(muscles) = attr.strip_prefix("muscles ") {
+                options.muscles = muscles
+                    .split(',')
+                    .map(|m| m.trim().to_lowercase())
+                    .filter(|m| !m.is_empty())
+                    .collect();
+                continue;
+            }
+            if let Some(weight) = attr.strip_prefix("weight ") {
+                options.details.weight = Some(Weight::parse(weight)?);
+                continue;
+            }
+            if let Some(replaces) = attr.strip_prefix("replaces ") {
+                options.replaces = Some(replaces.trim().to_owned());
+                continue;
+            }
+            if let Some(tempo) = attr.strip_prefix("tempo ") {
+                options.details.tempo = Some(tempo.trim().to_owned());
+                continue;
+            }
+            // with the tempo right there, or in its own attribute
+            if let Some(tempo) = attr.strip_prefix("metronome") {
+                if !tempo.trim().is_empty() {
+                    options.details.tempo = Some(tempo.trim().to_owned());
+                }
+                options.details.metronome = true;
+                continue;
+            }
+            if let Some(equipment) = attr.strip_prefix("equipment ") {
+                options.details.equipment = Some(equipment.trim().to_owned());
+                continue;
+            }
+            if let Some(say) = attr.strip_prefix("say ") {
+                options.details.say.push(Say::parse(say)?);
+                continue;
+            }
+            if let Some(note) = attr.strip_prefix("note ") {
+                options.details.note = Some(note.trim().to_owned());
+                continue;
+            }
+            if let Some(demo) = attr.strip_prefix("demo ") {
+                options.details.demo = Some(demo.trim().to_owned());
+                continue;
+            }
+            if let Some(prep) = attr.strip_prefix("prep ") {
+                options.prep =
+                    Some(parse_duration(prep.trim()).map_err(|_| {
+                        unexpected("a prep duration in MM:SS or MM'SS", prep.trim())
+                    })?);
+                continue;
+            }
+            options.cue = Some(CueOverride::parse(attr)?);
+        }
+        Ok(options)
+    }
+
+    /// Takes whatever isn't given from a `Define`d excercise's attributes.
+    fn inherit(mut self, defined: &Self) -> Self {
+        self.cue = self.cue.or_else(|| defined.cue.clone());
+        self.prep = self.prep.or(defined.prep);
+        self.optional |= defined.optional;
+        self.estimate = self.estimate.or(defined.estimate);
+        self.escalate |= defined.escalate;
+        if self.collect.is_empty() {
+            self.collect = defined.collect.clone();
+        }
+        self.progress = self.progress.or_else(|| defined.progress.clone());
+        self.ramp = self.ramp.or(defined.ramp);
+        if self.muscles.is_empty() {
+            self.muscles = defined.muscles.clone();
+        }
+        let (details, base) = (&mut self.details, &defined.details);
+        details.tempo = details.tempo.take().or(base.tempo.clone());
+        details.metronome |= base.metronome;
+        details.equipment = details.equipment.take().or(base.equipment.clone());
+        details.note = details.note.take().or(base.note.clone());
+        details.demo = details.demo.take().or(base.demo.clone());
+        details.weight = details.weight.or(base.weight);
+        if details.say.is_empty() {
+            details.say = base.say.clone();
+        }
+        self
+    }
+}
+impl Display for ExcerciseOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.optional {
+            write!(f, " | optional")?;
+        }
+        if self.escalate {
+            write!(f, " | escalate")?;
+        }
+        if let Some(prep) = self.prep {
+            write!(f, " | prep {}", format_duration(prep))?;
+        }
+        if let Some(estimate) = self.estimate {
+            write!(f, " | estimate {}", format_duration(estimate))?;
+        }
+        if let Some(cue) = &self.cue {
+            write!(f, " | {cue}")?;
+        }
+        if let Some(progress) = &self.progress {
+            write!(f, " | progress {progress}")?;
+        }
+        if let Some(replaces) = &self.replaces {
+            write!(f, " | replaces {replaces}")?;
+        }
+        if let Some(weight) = &self.details.weight {
+            write!(f, " | weight {weight}")?;
+        }
+        if let Some(ramp) = self.ramp {
+            write!(f, " | ramp {ramp}")?;
+        }
+        if !self.muscles.is_empty() {
+            write!(f, " | muscles {}", self.muscles.join(", "))?;
+        }
+        if !self.collect.is_empty() {
+            let collect: Vec<_> = self.collect.iter().map(Collect::name).collect();
+            write!(f, " | collect: {}", collect.join(", "))?;
+        }
+        if let Some(tempo) = &self.details.tempo {
+            write!(f, " | tempo {tempo}")?;
+        }
+        if self.details.metronome {
+            write!(f, " | metronome")?;
+        }
+        if let Some(equipment) = &self.details.equipment {
+            write!(f, " | equipment {equipment}")?;
+        }
+        if let Some(note) = &self.details.note {
+            write!(f, " | note {note}")?;
+        }
+        if let Some(demo) = &self.details.demo {
+            write!(f, " | demo {demo}")?;
+        }
+        for say in &self.details.say {
+            write!(f, " | say {say}")?;
+        }
+        Ok(())
+    }
+}
+
+// `count` of them, the ones in between worked out along the way, the last
+// one if just one
+fn ramp_percentages(percentages: &[u16], count: u16) -> Vec<f32> {
+    let Some(&last) = percentages.last() else {
+        return vec![];
+    };
+    if count <= 1 {
+        return vec![last as f32; count as usize];
+    }
+    (0..count)
+        .map(|i| {
+            let at = i as f32 * (percentages.len() - 1) as f32 / (count - 1) as f32;
+            let (below, above) = (at.floor() as usize, at.ceil() as usize);
+            let (below, above) = (percentages[below] as f32, percentages[above] as f32);
+            below + (above - below) * at.fract()
+        })
+        .collect()
+}
+
+/// Parses `[NAME] ROUNDS x (WORK work / REST rest)`, with the durations in
+/// whatever format the file uses.
+pub(crate) fn parse_intervals(
+    s: &str,
+    parse_duration: impl Fn(&str) -> Option<Duration>,
+) -> Result<WorkoutSetElement> {
+    let error = || unexpected("`[NAME] ROUNDS x (WORK work / REST rest)`", s.trim());
+
+    let (head, body) = s
+        .trim()
+        .strip_suffix(')')
+        .and_then(|s| s.split_once(" x ("))
+        .ok_or_else(error)?;
+    let (name, rounds) = match head.rsplit_once(' ') {
+        Some((name, rounds)) => (name.trim(), rounds),
+        None => ("Intervals", head),
+    };
+    let (work, rest) = body.split_once('/').ok_or_else(error)?;
+    let work = work.trim().strip_suffix("work").ok_or_else(error)?;
+    let rest = rest.trim().strip_suffix("rest").ok_or_else(error)?;
+
+    Ok(WorkoutSetElement::Intervals {
+        name: name.to_owned(),
+        rounds: rounds.trim().parse().map_err(|_| error())?,
+        work: parse_duration(work.trim()).ok_or_else(error)?,
+        rest: parse_duration(rest.trim()).ok_or_else(error)?,
+    })
+}
+
+/// The interval templates and what they're written as after their keyword.
+pub(crate) const TEMPLATES: [(&str, &str); 3] = [
+    ("Tabata", "[NAME] [ROUNDSx] [WORK / REST]"),
+    ("EMOM", "[NAME] MINUTES x INTERVAL"),
+    ("AMRAP", "[NAME] DURATION"),
+];
+
+/// Expands a `Tabata`, `EMOM` or `AMRAP` line after its keyword, with the
+/// durations in whatever format the file uses: a Tabata is 8 rounds of 20
+/// seconds of work and 10 of rest unless it says, an EMOM a block starting
+/// every interval and an AMRAP a timed excercise asking for the rounds done.
+pub(crate) fn parse_template(
+    template: &str,
+    s: &str,
+    parse_duration: impl Fn(&str) -> Option<Duration>,
+) -> Result<WorkoutSetElement> {
+    let error = || {
+        let (_, syntax) = TEMPLATES.iter().find(|(t, _)| *t == template).unwrap();
+        unexpected(
+            format!("`{template} {syntax}`"),
+            &format!("{template} {}", s.trim()),
+        )
+    };
+    let s = s.trim();
+    let named = |name: &str| match name.trim() {
+        "" => template.to_owned(),
+        name => name.to_owned(),
+    };
+
+    Ok(match template {
+        "Tabata" => {
+            let (head, times) = match s.split_once('/') {
+                Some((head, rest)) => {
+                    let (head, work) = head.trim().rsplit_once(' ').unwrap_or(("", head));
+                    let times = (parse_duration(work.trim()), parse_duration(rest.trim()));
+                    let (Some(work), Some(rest)) = times else {
+                        return Err(error());
+                    };
+                    (head.trim(), (work, rest))
+                }
+                None => (s, (Duration::from_secs(20), Duration::from_secs(10))),
+            };
+            let (name, rounds) = match head.rsplit_once(' ').unwrap_or(("", head)) {
+                (name, rounds) if rounds.ends_with('x') => (
+                    name,
+                    rounds[..rounds.len() - 1].parse().map_err(|_| error())?,
+                ),
+                _ => (head, 8),
+            };
+            WorkoutSetElement::Intervals {
+                name: named(name),
+                rounds,
+                work: times.0,
+                rest: times.1,
+            }
+        }
+        "EMOM" => {
+            let (head, interval) = s.rsplit_once(" x ").ok_or_else(error)?;
+            let (name, minutes) = head.rsplit_once(' ').unwrap_or(("", head));
+            WorkoutSetElement::Intervals {
+                name: named(name),
+                rounds: minutes.trim().parse().map_err(|_| error())?,
+                work: parse_duration(interval.trim()).ok_or_else(error)?,
+                rest: Duration::ZERO,
+            }
+        }
+        "AMRAP" => {
+            let (name, duration) = s.rsplit_once(' ').unwrap_or(("", s));
+            WorkoutSetElement::Excercise {
+                name: named(name),
+                amount: ExcerciseAmout::Time {
+                    duration: parse_duration(duration.trim()).ok_or_else(error)?,
+                    midbeep: false,
+                },
+                options: ExcerciseOptions {
+                    collect: vec![Collect::Reps],
+                    ..Default::default()
+                },
+            }
+        }
+        _ => return Err(error()),
+    })
+}
+
+/// Splits a `Workout` or `Set` header from its `| lead-in MM:SS`.
+pub(crate) fn split_lead_in(header: &str) -> Result<(&str, Option<Duration>)> {
+    let Some((header, attr)) = header.split_once('|') else {
+        return Ok((header, None));
+    };
+    let attr = attr.trim();
+    let Some(lead_in) = attr.strip_prefix("lead-in ") else {
+        return Err(anyhow::Error::msg(format!(
+            "Unknown attribute `{attr}` (expected `lead-in MM:SS`)"
+        )));
+    };
+    let lead_in = parse_duration(lead_in.trim())
+        .map_err(|_| unexpected("a lead-in in MM:SS or MM'SS", lead_in.trim()))?;
+    Ok((header.trim_end(), Some(lead_in)))
+}
+
+/// Splits `Workout NAME | lead-in MM:SS | audio PROFILE` into the name, its
+/// lead-in and its audio profile.
+pub(crate) fn split_workout_header(
+    header: &str,
+) -> Result<(&str, Option<Duration>, Option<String>)> {
+    let mut attrs = header.split('|');
+    let name = attrs.next().unwrap_or_default().trim_end();
+    let (mut lead_in, mut audio) = (None, None);
+    for attr in attrs {
+        let attr = attr.trim();
+        if let Some(profile) = attr.strip_prefix("audio ") {
+            audio = Some(profile.trim().to_owned());
+        } else if let Some(duration) = attr.strip_prefix("lead-in ") {
+            lead_in = Some(
+                parse_duration(duration.trim())
+                    .map_err(|_| unexpected("a lead-in in MM:SS or MM'SS", duration.trim()))?,
+            );
+        } else {
+            return Err(anyhow::Error::msg(format!(
+                "Unknown attribute `{attr}` (expected `lead-in MM:SS` or `audio PROFILE`)"
+            )));
+        }
+    }
+    Ok((name, lead_in, audio))
+}
+
+/// Splits `Name amount | attr | attr` into the part before the attributes and
+/// the parsed attributes.
+pub(crate) fn split_options(line: &str) -> Result<(&str, ExcerciseOptions)> {
+    let (line, mut options) = match line.split_once('|') {
+        Some((line, attributes)) => (line.trim_end(), ExcerciseOptions::parse(attributes)?),
+        None => (line, ExcerciseOptions::default()),
+    };
+    // `@3-1-2` after the amount, a tempo that's clicked
+    let Some((line, tempo)) = line
+        .rsplit_once(" @")
+        .filter(|(_, tempo)| !tempo.trim().contains(' '))
+    else {
+        return Ok((line, options));
+    };
+    let tempo = tempo.trim();
+    let phases: Vec<_> = tempo.split('-').collect();
+    if phases.len() < 2
+        || !phases
+            .iter()
+            .all(|p| p.eq_ignore_ascii_case("x") || p.parse::<u8>().is_ok())
+    {
+        return Err(unexpected("a tempo like @3-1-2", &format!("@{tempo}")));
+    }
+    options.details.tempo = Some(tempo.to_owned());
+    options.details.metronome = true;
+    Ok((line.trim_end(), options))
+}
+
+pub const PRE_SECTION_WAIT: Duration = Duration::from_secs(2);
+pub const REST_END_WARNING: Duration = Duration::from_secs(5);
+/// How long an untimed excercise waits when stdin isn't a terminal, unless
+/// it has an estimate.
+pub const UNATTENDED_WAIT: Duration = Duration::from_secs(60);
+/// Of the working weight, for `ramp` sets, unless the config says.
+pub const RAMP_PERCENTAGES: [u16; 3] = [50, 70, 85];
+pub const RAMP_REST: Duration = Duration::from_secs(60);
+/// Longer rests than this are warned about, they're probably a typo.
+const LONG_REST: Duration = Duration::from_secs(30 * 60);
+/// So are longer workouts.
+const LONG_WORKOUT: Duration = Duration::from_secs(3 * 60 * 60);
+/// What `workout fmt --fix` puts in, unless it's told or the config says.
+pub const DEFAULT_SET_REST: Duration = Duration::from_secs(60);
+
+// the label and the time
+type Show = dyn Fn(&str, &str);
+
+/// How [`Workout::run`] goes about it.
+pub struct RunOptions<'a> {
+    /// Where to start: the set, the round of it and the excercise, from 0.
+    pub from: (u16, u16, u16),
+    /// Plays the cues, nothing does by default.
+    pub beep: &'a dyn Fn(&Sound),
+    /// Told what's on and its countdown every tick, like the overlay is.
+    pub show: Option<&'a Show>,
+    /// Shown how it's going every tick, like the status line is.
+    pub frontends: &'a [&'a dyn Frontend],
+    /// Told every step and cue as it happens.
+    pub observers: Vec<Box<dyn WorkoutObserver>>,
+    /// Leaves the lines of the steps and cues to the `observers`.
+    pub quiet: bool,
+}
+impl Default for RunOptions<'_> {
+    fn default() -> Self {
+        Self {
+            from: (0, 0, 0),
+            beep: &|_| {},
+            show: None,
+            frontends: &[],
+            observers: vec![],
+            quiet: false,
+        }
+    }
+}
+
+/// What a workout file is written in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Text,
+    Markdown,
+    Toml,
+    Json,
+    Yaml,
+}
+impl FileFormat {
+    /// By the file's extension, the text format if it's none of the others.
+    pub fn of(file: &str) -> Self {
+        match Path::new(file).extension().and_then(|e| e.to_str()) {
+            Some("md" | "markdown") => Self::Markdown,
+            Some("toml") => Self::Toml,
+            Some("json") => Self::Json,
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Text,
+        }
+    }
+}
+impl FromStr for FileFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" | "txt" => Ok(Self::Text),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "toml" => Ok(Self::Toml),
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            _ => Err(anyhow::Error::msg(format!(
+                "Unknown format {s} (text, markdown, toml, json or yaml)"
+            ))),
+        }
+    }
+}
+
+pub struct Workout {
+    pub sections: Vec<WorkoutSet>,
+    pub name: String,
+    /// Default for sets that don't set their own lead-in.
+    pub lead_in: Option<Duration>,
+    /// The `Define`d excercises, its base's included, for files extending it.
+    pub defines: Vec<Definition>,
+    /// The config profile whose volume, device and beeps it's run with.
+    pub audio: Option<String>,
 }
-impl Workout<'_> {
+impl Workout {
+    /// A workout in the text format, which can't extend another file (see
+    /// [`Workout::load`]).
+    pub fn parse(source: &str) -> Result<Self> {
+        let (_, source) = split_format(source)?;
+        if let (Some(_), _) = split_extends(&source) {
+            return Err(anyhow::Error::msg(
+                "It extends another file, load it with `Workout::load`",
+            ));
+        }
+        if !split_includes(&source).0.is_empty() {
+            return Err(anyhow::Error::msg(
+                "It includes another file, load it with `Workout::load`",
+            ));
+        }
+        load_workout(&source, &[])
+    }
+
+    /// The workout in `source`, read from `file`: in the format its
+    /// extension says, with the `flags` for its `If` blocks and the files it
+    /// extends read relative to it.
+    pub fn load(file: &str, source: &str, flags: &[String]) -> Result<Self> {
+        Self::load_as(file, source, flags, FileFormat::of(file))
+    }
+
+    /// Like [`Workout::load`], whatever the extension says.
+    pub fn load_as(file: &str, source: &str, flags: &[String], format: FileFormat) -> Result<Self> {
+        load_extending(file, source, flags, format, 0)
+    }
+
+    /// Runs it in the terminal, reading keys from stdin, until it's done or
+    /// finished early.
+    pub fn run(&self, options: RunOptions) -> Result<SessionRecord> {
+        let mut session = Session::new(self, options.from, options.beep)?;
+        for observer in options.observers {
+            session.observe(observer);
+        }
+        session.set_printing(!options.quiet);
+        let mut frontends = options.frontends.to_vec();
+        if let Some(show) = &options.show {
+            frontends.push(show);
+        }
+        run_session(
+            &self.to_string(),
+            self.name.clone(),
+            session,
+            options.beep,
+            &frontends,
+        )
+    }
+
     pub fn length(&self) -> Duration {
+        self.length_with(None)
+    }
+
+    /// With `measured`, untimed excercises take as long as they usually do
+    /// and rests run over like they usually do.
+    pub fn length_with(&self, measured: Option<&Measured>) -> Duration {
+        let rest = |d: Duration| measured.map_or(d, |m| m.rest(d));
         self.sections
             .iter()
             .map(|s| {
                 let reps = s.reps as u32;
-                let rests = s.set_rest.unwrap_or_default();
-                let parts: Duration = s
-                    .parts
-                    .iter()
-                    .map(|p| match p {
-                        WorkoutSetElement::Excercise { amount, .. } => match amount {
-                            ExcerciseAmout::Time { duration, .. } => *duration,
-                            ExcerciseAmout::Reps(_) => Duration::default(),
-                        },
-                        WorkoutSetElement::Rest { duration } => *duration,
-                    })
-                    .sum();
+                let rests = rest(s.set_rest.unwrap_or_default());
+                let parts: Duration = s.parts.iter().map(|p| p.length_with(measured)).sum();
+
+                rests * reps.saturating_sub(1) + parts * reps
+            })
+            .sum::<Duration>()
+            + self.sections[..self.sections.len().saturating_sub(1)]
+                .iter()
+                .filter(|s| s.reps > 0)
+                .filter_map(|s| s.rest_after)
+                .map(rest)
+                .sum::<Duration>()
+    }
+
+    /// The name and length, see [`Workout::length_with`].
+    pub fn title(&self, measured: Option<&Measured>) -> String {
+        let mut title = format!(
+            "{} [~{}",
+            self.name,
+            locale::minutes(self.length_with(measured))
+        );
+        let prep = self.prep_length();
+        if !prep.is_zero() {
+            let _ = write!(
+                title,
+                ", {} of it prep",
+                locale::decimal(prep.as_secs_f64() / 60., 1)
+            );
+        }
+        title.push(']');
+        title
+    }
 
-                rests * (reps - 1) + parts * reps
+    /// The part of [`Workout::length`] spent setting up excercises.
+    pub fn prep_length(&self) -> Duration {
+        self.sections
+            .iter()
+            .map(|s| {
+                let prep: Duration = s.parts.iter().map(WorkoutSetElement::prep_length).sum();
+                prep * s.reps as u32
             })
             .sum()
     }
+
+    /// Planned work time of every muscle group tagged with `muscles`, timed
+    /// excercises and estimated ones, in the order they come up.
+    pub fn muscle_work(&self) -> Vec<(String, Duration)> {
+        let mut work = vec![];
+        for set in &self.sections {
+            for p in &set.parts {
+                p.add_muscle_work(set.reps as u32, &mut work);
+            }
+        }
+        work
+    }
+
+    /// What's probably a mistake in it, for `validate`.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if self.name.trim().is_empty() {
+            warnings.push("The workout has no name".to_owned());
+        }
+        if self.sections.is_empty() {
+            warnings.push("There are no sets".to_owned());
+        }
+        let long = |rest: &Duration| *rest > LONG_REST;
+
+        // by how they're spelled, once shouting, spaces, dashes and plurals
+        // are left out
+        let mut spellings: Vec<(String, Vec<&str>)> = vec![];
+        for (i, set) in self.sections.iter().enumerate() {
+            let label = match &set.name {
+                Some(name) => format!("Set {} ({name})", i + 1),
+                None => format!("Set {}", i + 1),
+            };
+            if set.parts.is_empty() {
+                warnings.push(format!("{label} has nothing in it"));
+            }
+            if set.reps == 0 {
+                warnings.push(format!("{label} is done 0 times, it's left out"));
+            }
+            if set.reps == 1 && set.set_rest.is_some() {
+                warnings.push(format!(
+                    "{label} is done once, its `Set rest` is never used (`Rest after set`?)"
+                ));
+            }
+            let ends_resting = matches!(set.parts.last(), Some(WorkoutSetElement::Rest { .. }));
+            if set.reps > 1 && set.set_rest.is_none() && !ends_resting {
+                warnings.push(format!(
+                    "{label} is done {} times back to back, without a `Set rest` (`workout fmt --fix` puts one in)",
+                    set.reps
+                ));
+            }
+            if i + 1 == self.sections.len() && set.rest_after.is_some() {
+                warnings.push(format!(
+                    "{label} is the last, its `Rest after set` is never used"
+                ));
+            }
+            for rest in set
+                .set_rest
+                .iter()
+                .chain(&set.rest_after)
+                .filter(|r| long(r))
+            {
+                warnings.push(format!(
+                    "{label} rests for {}, over {} minutes",
+                    format_duration(*rest),
+                    LONG_REST.as_secs() / 60
+                ));
+            }
+
+            for part in &set.parts {
+                part.visit(&mut |p| match p {
+                    WorkoutSetElement::Excercise {
+                        name,
+                        amount,
+                        options,
+                    } => {
+                        if options.details.metronome && options.details.metronome().is_none() {
+                            warnings.push(format!(
+                                "{label} has a metronome for {name} without a tempo like 3-1-1-0 to click"
+                            ));
+                        }
+                        if p.is_empty() {
+                            warnings.push(format!(
+                                "{label} has {name} for {amount}, it's left out"
+                            ));
+                        }
+                        if let ExcerciseAmout::Time { duration, .. } = amount {
+                            if duration.is_zero() {
+                                warnings.push(format!(
+                                    "{label} has {name} for 00:00, it waits for enter like a rep excercise"
+                                ));
+                            }
+                        }
+                        let key: String = name
+                            .to_lowercase()
+                            .chars()
+                            .filter(|c| c.is_alphanumeric())
+                            .collect();
+                        let key = key.strip_suffix('s').unwrap_or(&key).to_owned();
+                        match spellings.iter_mut().find(|(k, _)| *k == key) {
+                            Some((_, names)) if !names.contains(&name.as_str()) => names.push(name),
+                            Some(_) => {}
+                            None => spellings.push((key, vec![name])),
+                        }
+                    }
+                    WorkoutSetElement::Rest { duration } if duration.is_zero() => {
+                        warnings.push(format!("{label} has a rest of 00:00, it's left out"));
+                    }
+                    WorkoutSetElement::Intervals { name, .. } if p.is_empty() => {
+                        warnings.push(format!(
+                            "{label} has {name} intervals with no rounds or work, they're left out"
+                        ));
+                    }
+                    WorkoutSetElement::Rest { duration } if long(duration) => {
+                        warnings.push(format!(
+                            "{label} has a rest of {}, over {} minutes",
+                            format_duration(*duration),
+                            LONG_REST.as_secs() / 60
+                        ));
+                    }
+                    WorkoutSetElement::Circuit { reps, parts, .. } => {
+                        if parts.is_empty() {
+                            warnings.push(format!("{label} has a circuit with nothing in it"));
+                        }
+                        if *reps == 0 {
+                            warnings.push(format!("{label} has a circuit done 0 times, it's left out"));
+                        }
+                    }
+                    _ => {}
+                });
+            }
+        }
+        for (_, names) in spellings.iter().filter(|(_, names)| names.len() > 1) {
+            warnings.push(format!(
+                "{} look like the same excercise, spelled differently",
+                names.join(", ")
+            ));
+        }
+
+        let length = self.length();
+        if length > LONG_WORKOUT {
+            warnings.push(format!(
+                "It takes ~{}, over {} hours: minutes written where seconds were meant?",
+                locale::minutes(length),
+                LONG_WORKOUT.as_secs() / 3600
+            ));
+        }
+        warnings
+    }
+
+    /// Puts a set of ramp-up sets before every set with a `ramp` excercise,
+    /// at `percentages` of its weight (spread out if it asks for more or
+    /// fewer), with short rests in between.
+    pub fn expand_ramps(&mut self, percentages: &[u16]) {
+        let mut sections = vec![];
+        for mut set in self.sections.drain(..) {
+            for part in &mut set.parts {
+                let WorkoutSetElement::Excercise {
+                    name,
+                    amount,
+                    options,
+                } = part
+                else {
+                    continue;
+                };
+                let (Some(count), Some(weight)) = (options.ramp.take(), options.details.weight)
+                else {
+                    continue;
+                };
+
+                let mut parts = vec![];
+                for percent in ramp_percentages(percentages, count) {
+                    if !parts.is_empty() {
+                        parts.push(WorkoutSetElement::Rest {
+                            duration: RAMP_REST,
+                        });
+                    }
+                    // named apart, so progressions and stats go by the working sets
+                    parts.push(WorkoutSetElement::Excercise {
+                        name: format!("{name} ramp-up"),
+                        amount: amount.clone(),
+                        options: ExcerciseOptions {
+                            details: Box::new(ExcerciseDetails {
+                                weight: Some(weight.percent(percent)),
+                                ..(*options.details).clone()
+                            }),
+                            ..Default::default()
+                        },
+                    });
+                }
+                sections.push(WorkoutSet {
+                    name: Some("Ramp-up".to_owned()),
+                    parts,
+                    reps: 1,
+                    set_rest: None,
+                    rest_after: Some(RAMP_REST),
+                    lead_in: set.lead_in,
+                    groups: vec![],
+                });
+            }
+            sections.push(set);
+        }
+        self.sections = sections;
+    }
+
+    /// Scales the work of every excercise (not the rests), e.g. `0.7` for a
+    /// deload week.
+    pub fn scale(&mut self, factor: f64) {
+        self.visit_elements_mut(|p| match p {
+            WorkoutSetElement::Excercise {
+                amount, options, ..
+            } => {
+                amount.scale(factor);
+                options.estimate = options.estimate.map(|e| e.mul_f64(factor));
+            }
+            WorkoutSetElement::Intervals { work, .. } => {
+                *work = Duration::from_secs((work.as_secs_f64() * factor).round().max(1.) as u64)
+            }
+            WorkoutSetElement::Rest { .. } | WorkoutSetElement::Circuit { .. } => {}
+        });
+    }
+
+    /// Lays `self` over `base`. A set named like one of the base's overrides
+    /// its repetitions, lead-in and set rest where given, and each of its
+    /// excercises takes the place of the base's one of the same name (or the
+    /// one it `replaces`), everything else is added to the end of the set.
+    /// Sets the base doesn't have are added after its own.
+    pub fn extend(self, mut base: Workout) -> Result<Workout> {
+        for set in self.sections {
+            let Some(target) = base
+                .sections
+                .iter_mut()
+                .find(|s| set.name.is_some() && s.name == set.name)
+            else {
+                base.sections.push(set);
+                continue;
+            };
+
+            if set.reps != 1 {
+                target.reps = set.reps;
+            }
+            target.lead_in = set.lead_in.or(target.lead_in);
+            target.set_rest = set.set_rest.or(target.set_rest);
+            target.rest_after = set.rest_after.or(target.rest_after);
+
+            for mut part in set.parts {
+                let (replaces, explicit) = match &mut part {
+                    WorkoutSetElement::Excercise { name, options, .. } => {
+                        match options.replaces.take() {
+                            Some(replaces) => (Some(replaces), true),
+                            None => (Some(name.clone()), false),
+                        }
+                    }
+                    _ => (None, false),
+                };
+                let position = replaces.as_ref().and_then(|r| {
+                    target.parts.iter().position(
+                        |p| matches!(p, WorkoutSetElement::Excercise { name, .. } if name == r),
+                    )
+                });
+
+                match (position, replaces) {
+                    (Some(i), _) => target.parts[i] = part,
+                    (None, Some(replaces)) if explicit => {
+                        return Err(anyhow::Error::msg(format!(
+                            "Set {} of {} has no {replaces} to replace",
+                            set.name.as_deref().unwrap_or_default(),
+                            base.name
+                        )))
+                    }
+                    (None, _) => target.parts.push(part),
+                }
+            }
+        }
+
+        base.name = self.name;
+        base.lead_in = self.lead_in.or(base.lead_in);
+        base.audio = self.audio.or(base.audio);
+        base.defines = self.defines;
+        Ok(base)
+    }
+
+    /// Calls `f` with every element of every set, nested ones included.
+    pub fn visit_elements_mut(&mut self, mut f: impl FnMut(&mut WorkoutSetElement)) {
+        for set in &mut self.sections {
+            for p in &mut set.parts {
+                p.visit_mut(&mut f);
+            }
+        }
+    }
+}
+impl Display for Workout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.title(None))
+    }
+}
+
+pub struct WorkoutSet {
+    pub name: Option<String>,
+    pub parts: Vec<WorkoutSetElement>,
+    pub reps: u16,
+    /// Between its repetitions.
+    pub set_rest: Option<Duration>,
+    /// Between its last repetition and the next set.
+    pub rest_after: Option<Duration>,
+    /// Countdown before each repetition, [`PRE_SECTION_WAIT`] if not set.
+    pub lead_in: Option<Duration>,
+    /// Labels and the part they start at, only used for reporting.
+    pub groups: Vec<(usize, String)>,
+}
+impl WorkoutSet {
+    pub fn group_of(&self, part: usize) -> Option<&str> {
+        self.groups
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= part)
+            .map(|(_, label)| label.as_str())
+    }
+}
+impl Display for WorkoutSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "{name}")?;
+        } else {
+            write!(f, "[UNKNOWN]")?;
+        }
+        if self.reps > 1 {
+            write!(f, " x{}", self.reps)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub enum ExcerciseAmout {
+    Time {
+        #[serde(with = "crate::record::secs")]
+        duration: Duration,
+        midbeep: bool,
+    },
+    Reps(u16),
+    /// As many reps as possible, counted afterwards.
+    Failure,
+    /// On a machine, done once its display says so.
+    Calories(u16),
+    Meters(u32),
+}
+impl ExcerciseAmout {
+    /// `00:30`, `00:30"`, `x12`, `xF`, `20cal` or `500m`, as in the text
+    /// format.
+    pub fn parse(amount: &str) -> Result<Self> {
+        Ok(if amount == "xF" {
+            ExcerciseAmout::Failure
+        } else if let Some(reps) = amount.strip_prefix('x') {
+            ExcerciseAmout::Reps(
+                reps.parse()
+                    .map_err(|_| unexpected("reps like x12", amount))?,
+            )
+        } else if let Some(cal) = amount.strip_suffix("cal") {
+            ExcerciseAmout::Calories(
+                cal.parse()
+                    .map_err(|_| unexpected("calories like 20cal", amount))?,
+            )
+        } else if let Some(meters) = amount.strip_suffix('m') {
+            ExcerciseAmout::Meters(
+                meters
+                    .parse()
+                    .map_err(|_| unexpected("meters like 500m", amount))?,
+            )
+        } else {
+            let midbeep = amount.ends_with('"');
+            ExcerciseAmout::Time {
+                duration: parse_duration(amount).map_err(|_| {
+                    unexpected("an amount like 00:30, 00'30, x12, 20cal or 500m", amount)
+                })?,
+                midbeep,
+            }
+        })
+    }
+
+    /// As written in the text format.
+    pub fn text(&self) -> String {
+        match self {
+            ExcerciseAmout::Time { duration, midbeep } => {
+                let q = if *midbeep { "\"" } else { "" };
+                format!("{}{q}", format_duration(*duration))
+            }
+            amount => amount.to_string(),
+        }
+    }
+
+    /// Rounded to whole seconds / reps, but never down to nothing.
+    pub fn scale(&mut self, factor: f64) {
+        let scale = |n: f64| (n * factor).round().max(1.);
+        match self {
+            ExcerciseAmout::Time { duration, .. } => {
+                *duration = Duration::from_secs(scale(duration.as_secs_f64()) as u64)
+            }
+            ExcerciseAmout::Reps(r) | ExcerciseAmout::Calories(r) => *r = scale(*r as f64) as u16,
+            ExcerciseAmout::Meters(m) => *m = scale(*m as f64) as u32,
+            ExcerciseAmout::Failure => {}
+        }
+    }
+}
+impl Display for ExcerciseAmout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExcerciseAmout::Time { duration, .. } => write!(f, "{duration:?}"),
+            ExcerciseAmout::Reps(r) => write!(f, "x{r}"),
+            ExcerciseAmout::Failure => write!(f, "xF"),
+            ExcerciseAmout::Calories(c) => write!(f, "{c}cal"),
+            ExcerciseAmout::Meters(m) => write!(f, "{m}m"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum WorkoutSetElement {
+    Excercise {
+        name: String,
+        amount: ExcerciseAmout,
+        options: ExcerciseOptions,
+    },
+    Rest {
+        duration: Duration,
+    },
+    /// `rounds x (work / rest)`, e.g. a Tabata finisher.
+    Intervals {
+        name: String,
+        rounds: u16,
+        work: Duration,
+        rest: Duration,
+    },
+    /// Parts repeated right after each other, like a mini-circuit in a set.
+    Circuit {
+        reps: u16,
+        parts: Vec<WorkoutSetElement>,
+        /// Rests it puts in between its parts by itself.
+        rest: Option<CircuitRest>,
+    },
+}
+
+/// After which parts of a circuit (or superset) its rest comes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RestAfter {
+    /// Every excercise.
+    Each,
+    /// Every two excercises, and an odd one out at the end.
+    Pair,
+    /// Only the whole round.
+    Round,
+}
+impl FromStr for RestAfter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "each" => Ok(Self::Each),
+            "pair" => Ok(Self::Pair),
+            "round" => Ok(Self::Round),
+            _ => Err(unexpected("each, pair or round", s)),
+        }
+    }
+}
+impl Display for RestAfter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Each => "each",
+            Self::Pair => "pair",
+            Self::Round => "round",
+        })
+    }
+}
+
+/// `rest 00:30 after pair` after a circuit's `xN`. None comes after the last
+/// round, the set's rests take over from there.
+#[derive(Clone, Copy)]
+pub struct CircuitRest {
+    pub duration: Duration,
+    pub after: RestAfter,
+}
+impl CircuitRest {
+    /// `xN` and what comes after it in a `Circuit` header, the rest read with
+    /// `parse`.
+    pub fn parse_header(
+        header: &str,
+        parse: impl Fn(&str) -> Option<Duration>,
+    ) -> Option<(u16, Option<Self>)> {
+        let mut words = header.split_whitespace();
+        let reps = words.next()?.strip_prefix('x')?.parse().ok()?;
+        let rest = match words.next() {
+            None => return Some((reps, None)),
+            Some("rest") => words.next().and_then(parse)?,
+            Some(_) => return None,
+        };
+        let after = match (words.next(), words.next(), words.next()) {
+            (Some("after"), Some(after), None) => after.parse().ok()?,
+            _ => return None,
+        };
+        Some((
+            reps,
+            Some(Self {
+                duration: rest,
+                after,
+            }),
+        ))
+    }
+
+    /// A round of `parts` with the rests put in.
+    pub fn round(&self, parts: &[WorkoutSetElement], last: bool) -> Vec<WorkoutSetElement> {
+        let excercises = parts.iter().filter(|p| p.is_excercise()).count();
+        let mut out = vec![];
+        let mut done = 0;
+        for p in parts {
+            out.push(p.clone());
+            if !p.is_excercise() {
+                continue;
+            }
+            done += 1;
+            let rest = match self.after {
+                RestAfter::Each => true,
+                RestAfter::Pair => done % 2 == 0 || done == excercises,
+                RestAfter::Round => done == excercises,
+            };
+            if rest && !(last && done == excercises) {
+                out.push(WorkoutSetElement::Rest {
+                    duration: self.duration,
+                });
+            }
+        }
+        out
+    }
+
+    /// How many it puts into `reps` rounds of `parts`.
+    pub fn count(&self, parts: &[WorkoutSetElement], reps: u16) -> u32 {
+        let excercises = parts.iter().filter(|p| p.is_excercise()).count() as u32;
+        let per_round = match self.after {
+            RestAfter::Each => excercises,
+            RestAfter::Pair => excercises.div_ceil(2),
+            RestAfter::Round => excercises.min(1),
+        };
+        (per_round * reps as u32).saturating_sub(1)
+    }
+}
+impl Display for CircuitRest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "rest {} after {}",
+            format_duration(self.duration),
+            self.after
+        )
+    }
+}
+impl WorkoutSetElement {
+    /// Whether it counts as an excercise for starting positions.
+    pub fn is_excercise(&self) -> bool {
+        !matches!(self, WorkoutSetElement::Rest { .. })
+    }
+
+    /// Whether there's nothing to it, like `x0` or a `Rest 00:00`, so it's
+    /// left out when running. A `00:00` excercise isn't, it waits for enter.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            WorkoutSetElement::Excercise { amount, .. } => matches!(
+                amount,
+                ExcerciseAmout::Reps(0) | ExcerciseAmout::Calories(0) | ExcerciseAmout::Meters(0)
+            ),
+            WorkoutSetElement::Rest { duration } => duration.is_zero(),
+            WorkoutSetElement::Intervals { rounds, work, .. } => *rounds == 0 || work.is_zero(),
+            WorkoutSetElement::Circuit { reps, parts, .. } => {
+                *reps == 0 || parts.iter().all(Self::is_empty)
+            }
+        }
+    }
+
+    pub fn length(&self) -> Duration {
+        self.length_with(None)
+    }
+
+    pub fn length_with(&self, measured: Option<&Measured>) -> Duration {
+        if self.is_empty() {
+            return Duration::ZERO;
+        }
+        match self {
+            WorkoutSetElement::Excercise {
+                name,
+                amount,
+                options,
+            } => {
+                let prep = options.prep.unwrap_or_default();
+                match amount {
+                    // a 00:00 one takes as long as a rep excercise
+                    ExcerciseAmout::Time { duration, .. } if !duration.is_zero() => {
+                        *duration + prep
+                    }
+                    _ => {
+                        let took = measured.and_then(|m| m.excercise(name, amount));
+                        took.or(options.estimate).unwrap_or_default() + prep
+                    }
+                }
+            }
+            WorkoutSetElement::Rest { duration } => {
+                measured.map_or(*duration, |m| m.rest(*duration))
+            }
+            WorkoutSetElement::Intervals {
+                rounds, work, rest, ..
+            } => (*work + *rest) * *rounds as u32,
+            WorkoutSetElement::Circuit { reps, parts, rest } => {
+                let rests = rest.map_or(Duration::ZERO, |rest| {
+                    measured.map_or(rest.duration, |m| m.rest(rest.duration))
+                        * rest.count(parts, *reps)
+                });
+                parts
+                    .iter()
+                    .map(|p| p.length_with(measured))
+                    .sum::<Duration>()
+                    * *reps as u32
+                    + rests
+            }
+        }
+    }
+
+    pub fn prep_length(&self) -> Duration {
+        match self {
+            WorkoutSetElement::Excercise { options, .. } => options.prep.unwrap_or_default(),
+            WorkoutSetElement::Circuit { reps, parts, .. } => {
+                parts.iter().map(Self::prep_length).sum::<Duration>() * *reps as u32
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    fn add_muscle_work(&self, times: u32, work: &mut Vec<(String, Duration)>) {
+        match self {
+            WorkoutSetElement::Excercise {
+                amount, options, ..
+            } => {
+                let time = match amount {
+                    ExcerciseAmout::Time { duration, .. } => *duration,
+                    _ => options.estimate.unwrap_or_default(),
+                } * times;
+                for muscle in &options.muscles {
+                    match work.iter_mut().find(|(m, _)| m == muscle) {
+                        Some((_, total)) => *total += time,
+                        None => work.push((muscle.clone(), time)),
+                    }
+                }
+            }
+            WorkoutSetElement::Circuit { reps, parts, .. } => {
+                for p in parts {
+                    p.add_muscle_work(times * *reps as u32, work);
+                }
+            }
+            WorkoutSetElement::Rest { .. } | WorkoutSetElement::Intervals { .. } => {}
+        }
+    }
+
+    /// Calls `f` with this and every element nested in it.
+    pub fn visit<'a>(&'a self, f: &mut impl FnMut(&'a WorkoutSetElement)) {
+        f(self);
+        if let WorkoutSetElement::Circuit { parts, .. } = self {
+            for p in parts {
+                p.visit(f);
+            }
+        }
+    }
+
+    /// Like [`WorkoutSetElement::visit`], mutably.
+    pub fn visit_mut(&mut self, f: &mut impl FnMut(&mut WorkoutSetElement)) {
+        f(self);
+        if let WorkoutSetElement::Circuit { parts, .. } = self {
+            for p in parts {
+                p.visit_mut(f);
+            }
+        }
+    }
+}
+impl Display for WorkoutSetElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkoutSetElement::Excercise { name, amount, .. } => {
+                write!(f, "[EXCERCISE]: {name} {amount}")
+            }
+            WorkoutSetElement::Rest { duration } => write!(f, "[REST]: {duration:?}"),
+            WorkoutSetElement::Intervals {
+                name,
+                rounds,
+                work,
+                rest,
+            } => write!(
+                f,
+                "[INTERVALS]: {name} {rounds} x ({} work / {} rest)",
+                format_duration(*work),
+                format_duration(*rest)
+            ),
+            WorkoutSetElement::Circuit { reps, parts, rest } => {
+                write!(f, "[CIRCUIT]: x{reps}, {} parts", parts.len())?;
+                match rest {
+                    Some(rest) => write!(f, ", {rest}"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let error = || unexpected("a duration in MM:SS or MM'SS", s);
+    let (mins, secs) = s
+        .get(..5)
+        .and_then(|d| d.split_once([':', '\'']))
+        .ok_or_else(error)?;
+    let mins: u64 = mins.parse().map_err(|_| error())?;
+    let secs: u64 = secs.parse().map_err(|_| error())?;
+    Ok(Duration::from_secs(mins * 60 + secs))
+}
+
+/// Something in a workout file where something else was expected, put at
+/// its column when the line it's on is known.
+#[derive(Debug)]
+pub struct Unexpected {
+    pub expected: String,
+    pub got: String,
+}
+impl Display for Unexpected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expected {}, got: {}", self.expected, self.got)
+    }
+}
+impl std::error::Error for Unexpected {}
+
+pub(crate) fn unexpected(expected: impl Into<String>, got: &str) -> anyhow::Error {
+    Unexpected {
+        expected: expected.into(),
+        got: got.to_owned(),
+    }
+    .into()
+}
+
+// `line N: ` in front of an error about that line, with the column where
+// what was unexpected starts
+pub(crate) fn at_line(number: usize, line: &str, e: anyhow::Error) -> anyhow::Error {
+    let column = e
+        .downcast_ref::<Unexpected>()
+        .filter(|u| !u.got.is_empty())
+        .and_then(|u| line.find(&u.got))
+        .map(|at| line[..at].chars().count() + 1);
+    match column {
+        Some(column) => anyhow::Error::msg(format!("line {number}, column {column}: {e}")),
+        None => anyhow::Error::msg(format!("line {number}: {e}")),
+    }
+}
+
+pub(crate) fn parse_set_header(set: &str) -> (Option<&str>, u16) {
+    let set = set.trim();
+    if set.is_empty() {
+        return (None, 1);
+    }
+    if let Some(Ok(r)) = set.strip_prefix('x').map(|v| v.parse::<u16>()) {
+        return (None, r);
+    }
+
+    if let Some((name, reps)) = set.rsplit_once(' ') {
+        if let Some(Ok(r)) = reps.strip_prefix('x').map(|v| v.parse::<u16>()) {
+            (Some(name), r)
+        } else {
+            (Some(set), 1)
+        }
+    } else {
+        (Some(set), 1)
+    }
+}
+
+/// An excercise of a `Define` block: it's filled in wherever it's used by
+/// name, with the amount and attributes there taking precedence.
+#[derive(Clone)]
+pub struct Definition {
+    pub name: String,
+    pub amount: Option<ExcerciseAmout>,
+    pub options: ExcerciseOptions,
+}
+
+// what comes after `Excercise` in a `Define` block or a library; one that's
+// already in the base builds on it
+fn parse_definition(rest: &str, base: &[Definition]) -> Result<Definition> {
+    let (rest, options) = split_options(rest)?;
+    // the amount can be left to where it's used
+    let (name, amount) = match rest
+        .rsplit_once(' ')
+        .map(|(name, amount)| (name, ExcerciseAmout::parse(amount)))
+    {
+        Some((name, Ok(amount))) => (name, Some(amount)),
+        _ => (rest, None),
+    };
+    let (amount, options) = match base.iter().find(|d| d.name == name.trim()) {
+        Some(base) => (
+            amount.or_else(|| base.amount.clone()),
+            options.inherit(&base.options),
+        ),
+        None => (amount, options),
+    };
+    Ok(Definition {
+        name: name.trim().to_owned(),
+        amount,
+        options,
+    })
+}
+
+// the lines after a `Define` header, up to and including its `End`
+fn parse_define(
+    lines: &[&str],
+    l: &mut usize,
+    defines: &mut Vec<Definition>,
+    base: &[Definition],
+) -> Result<()> {
+    while *l < lines.len() {
+        let line = lines[*l].trim();
+        if line == "End" {
+            *l += 1;
+            return Ok(());
+        }
+
+        let Some(rest) = line.strip_prefix("Excercise ") else {
+            return Err(unexpected("`Excercise` or `End` of define", line));
+        };
+        defines.push(parse_definition(rest, base)?);
+        *l += 1;
+    }
+    Err(anyhow::Error::msg("Define is missing its `End`"))
+}
+
+/// The excercises of a library file: `Excercise` lines like a `Define`
+/// block's, without the `Define` and `End`, and `#` comments.
+pub fn load_library(source: &str) -> Result<Vec<Definition>> {
+    let mut defines = vec![];
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let definition = match line.strip_prefix("Excercise ") {
+            Some(rest) => parse_definition(rest, &[]),
+            None => Err(unexpected("`Excercise`", line)),
+        };
+        defines.push(definition.map_err(|e| at_line(i + 1, line, e))?);
+    }
+    Ok(defines)
+}
+
+// an `Include`d library, relative to the workout or else in the config's
+// `exercises` directory
+fn read_library(file: &str, include: &str) -> Result<Vec<Definition>> {
+    let relative = match Path::new(file).parent() {
+        Some(dir) => dir.join(include),
+        None => include.into(),
+    };
+    let path = match (relative.exists(), dirs::config_dir()) {
+        (false, Ok(config)) if config.join("exercises").join(include).exists() => {
+            config.join("exercises").join(include)
+        }
+        _ => relative,
+    };
+    let source = fs::read_to_string(&path)
+        .map_err(|e| anyhow::Error::msg(format!("Couldn't read {}: {e}", path.display())))?;
+    load_library(&source).map_err(|e| anyhow::Error::msg(format!("{}, {e}", path.display())))
+}
+
+// every `.txt` in the config's `exercises` directory, by file name, for
+// every text workout
+// a broken file is left out, it'd break every workout otherwise
+fn config_library() -> Vec<Definition> {
+    let Ok(dir) = dirs::config_dir().map(|d| d.join("exercises")) else {
+        return vec![];
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return vec![];
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "txt"))
+        .collect();
+    files.sort();
+
+    let mut defines = vec![];
+    for file in files {
+        match fs::read_to_string(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|s| load_library(&s))
+        {
+            Ok(defined) => defines.extend(defined),
+            Err(e) => eprintln!("Leaving out {}, {e}", file.display()),
+        }
+    }
+    defines
+}
+
+// the config's library is only read for an excercise the file doesn't
+// define or include itself, and those win over it
+fn load_with_library(source: &str, defines: &mut Vec<Definition>) -> Result<Workout> {
+    if let Ok(workout) = load_workout(source, defines) {
+        let mut resolved = true;
+        for part in workout.sections.iter().flat_map(|s| &s.parts) {
+            part.visit(&mut |p| {
+                if let WorkoutSetElement::Excercise { name, .. } = p {
+                    resolved &= workout.defines.iter().any(|d| &d.name == name);
+                }
+            });
+        }
+        if resolved {
+            return Ok(workout);
+        }
+    }
+    for defined in config_library() {
+        if !defines.iter().any(|d| d.name == defined.name) {
+            defines.push(defined);
+        }
+    }
+    load_workout(source, defines)
+}
+
+fn parse_element(t: &str, rest: &str, defines: &[Definition]) -> Result<Option<WorkoutSetElement>> {
+    let p = match t {
+        "Excercise" => {
+            let (rest, options) = split_options(rest)?;
+            let find = |name: &str| defines.iter().find(|d| d.name == name.trim());
+
+            let (name, amount, options) = match find(rest) {
+                Some(defined) => {
+                    let Some(amount) = defined.amount.clone() else {
+                        return Err(anyhow::Error::msg(format!(
+                            "{} is defined without an amount, give it one",
+                            defined.name
+                        )));
+                    };
+                    (rest, amount, options.inherit(&defined.options))
+                }
+                None => {
+                    let Some((name, amount)) = rest.rsplit_once(' ') else {
+                        return Err(unexpected("an amount after the excercise", rest));
+                    };
+                    let options = match find(name) {
+                        Some(defined) => options.inherit(&defined.options),
+                        None => options,
+                    };
+                    (name, ExcerciseAmout::parse(amount)?, options)
+                }
+            };
+
+            WorkoutSetElement::Excercise {
+                name: name.trim().to_owned(),
+                amount,
+                options,
+            }
+        }
+        "Intervals" => parse_intervals(rest, |d| parse_duration(d).ok())?,
+        "Tabata" | "EMOM" | "AMRAP" => parse_template(t, rest, |d| parse_duration(d).ok())?,
+        "Rest" => WorkoutSetElement::Rest {
+            duration: parse_duration(rest)?,
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(p))
+}
+
+// a `Circuit xN` header and the lines after it, up to and including its
+// `End`
+fn parse_circuit(
+    header: &str,
+    lines: &[&str],
+    l: &mut usize,
+    defines: &[Definition],
+) -> Result<WorkoutSetElement> {
+    let Some((reps, circuit_rest)) = CircuitRest::parse_header(header, |d| parse_duration(d).ok())
+    else {
+        return Err(unexpected(
+            "`Circuit xN` or `Circuit xN rest MM:SS after each|pair|round`",
+            &format!("Circuit {header}"),
+        ));
+    };
+    *l += 1;
+
+    let mut parts = vec![];
+    while *l < lines.len() {
+        let line = lines[*l].trim();
+        if line == "End" {
+            *l += 1;
+            return Ok(WorkoutSetElement::Circuit {
+                reps,
+                parts,
+                rest: circuit_rest,
+            });
+        }
+
+        let (t, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let p = match t {
+            "Circuit" => parse_circuit(rest, lines, l, defines)?,
+            _ => {
+                let p = parse_element(t, rest, defines)?
+                    .ok_or_else(|| unexpected("`End` of circuit", line.trim()))?;
+                *l += 1;
+                p
+            }
+        };
+        parts.push(p);
+    }
+    Err(anyhow::Error::msg("Circuit is missing its `End`"))
 }
-impl Display for Workout<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} [~{:.1} mins]",
-            self.name,
-            self.length().as_secs_f64() / 60.
-        )?;
-        Ok(())
+
+/// The version of the file syntax this reads and writes, from a `Format N`
+/// line on top. Files without one are from before there were versions and
+/// read as format 1. A change that would make an older file read differently
+/// gets a new format, with a step in [`upgrade`] to rewrite such files; files
+/// of a newer format than this are refused instead of being misread.
+pub const FORMAT: u16 = 2;
+
+/// Takes the `Format N` line off the top, leaving an empty line so line
+/// numbers stay the same.
+pub fn split_format(source: &str) -> Result<(u16, String)> {
+    let Some((at, line)) = source
+        .lines()
+        .enumerate()
+        .find(|(_, l)| !l.trim().is_empty())
+    else {
+        return Ok((1, source.to_owned()));
+    };
+    let Some(format) = line.trim().strip_prefix("Format ") else {
+        return Ok((1, source.to_owned()));
+    };
+    let format = match format.trim().parse() {
+        Ok(f) if f >= 1 => f,
+        _ => {
+            return Err(anyhow::Error::msg(format!(
+                "Expected `Format N`, got: {line}"
+            )))
+        }
+    };
+    if format > FORMAT {
+        return Err(anyhow::Error::msg(format!(
+            "Written in format {format}, this version reads up to format {FORMAT}: time to update"
+        )));
     }
+
+    let mut rest = String::new();
+    for (i, line) in source.lines().enumerate() {
+        if i != at {
+            rest.push_str(line);
+        }
+        rest.push('\n');
+    }
+    Ok((format, rest))
 }
 
-pub struct WorkoutSet<'a> {
-    name: Option<&'a str>,
-    parts: Vec<WorkoutSetElement<'a>>,
-    reps: u16,
-    set_rest: Option<Duration>,
+/// Brings a file of an older format up to [`FORMAT`], `None` if it already
+/// is.
+pub fn upgrade(source: &str) -> Result<Option<String>> {
+    let (mut format, source) = split_format(source)?;
+    if format == FORMAT {
+        return Ok(None);
+    }
+    // a step for every format after it, in turn
+    while format < FORMAT {
+        // 2 only added the `Format` line
+        format += 1;
+    }
+    Ok(Some(format!("Format {FORMAT}\n{source}")))
 }
-impl Display for WorkoutSet<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(name) = &self.name {
-            write!(f, "{name}")?;
-        } else {
-            write!(f, "[UNKNOWN]")?;
+
+/// Puts a `Set rest` line into every set of a text format workout that's done
+/// more than once without one (or a rest at its end), after the set's last
+/// line. Returns how many went in.
+pub fn add_set_rests(source: &str, rest: Duration) -> (String, usize) {
+    let is_header = |line: &str| {
+        let line = line.trim_start();
+        line.starts_with("Set") && !line.starts_with("Set rest ")
+    };
+    let lines: Vec<&str> = source.lines().collect();
+    // the lines one goes after
+    let mut after = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(header) = lines[i]
+            .trim_start()
+            .strip_prefix("Set")
+            .filter(|_| is_header(lines[i]))
+        else {
+            i += 1;
+            continue;
+        };
+        let reps = split_lead_in(header).map_or(1, |(header, _)| parse_set_header(header).1);
+        let (mut last, mut has_rest, mut ends_resting) = (i, false, false);
+        i += 1;
+        while i < lines.len() && !is_header(lines[i]) {
+            let line = lines[i].trim();
+            has_rest |= line.starts_with("Set rest ");
+            if !line.is_empty() {
+                last = i;
+            }
+            if !line.is_empty() && !line.starts_with("Rest after set ") {
+                ends_resting = line.starts_with("Rest ");
+            }
+            i += 1;
         }
-        if self.reps > 1 {
-            write!(f, " x{}", self.reps)?;
+        if reps > 1 && !has_rest && !ends_resting {
+            after.push(last);
         }
+    }
 
-        Ok(())
+    let mut out = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        out.push_str(line);
+        out.push('\n');
+        if after.contains(&i) {
+            out.push_str(&format!("Set rest {}\n", format_duration(rest)));
+        }
     }
+    (out, after.len())
 }
 
-pub enum ExcerciseAmout {
-    Time { duration: Duration, midbeep: bool },
-    Reps(u16),
-}
-impl Display for ExcerciseAmout {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ExcerciseAmout::Time { duration, .. } => write!(f, "{duration:?}"),
-            ExcerciseAmout::Reps(r) => write!(f, "x{r}"),
+/// The `Include`d files right after the header (and `Extends`), and the
+/// source with their lines left empty, so the line numbers stay the same.
+pub fn split_includes(source: &str) -> (Vec<String>, String) {
+    let mut includes = vec![];
+    let mut rest = String::new();
+    // past the header, until the first line that isn't one
+    let (mut header, mut done) = (false, false);
+    for line in source.lines() {
+        let trimmed = line.trim();
+        match trimmed.strip_prefix("Include ") {
+            Some(file) if header && !done => includes.push(file.trim().to_owned()),
+            _ => {
+                rest.push_str(line);
+                if !trimmed.is_empty() {
+                    done = header;
+                    header = true;
+                }
+            }
         }
+        rest.push('\n');
     }
+    (includes, rest)
 }
 
-pub enum WorkoutSetElement<'a> {
-    Excercise {
-        name: &'a str,
-        amount: ExcerciseAmout,
-    },
-    Rest {
-        duration: Duration,
-    },
+/// Takes the `Extends FILE` line after the `Workout` header out of a text
+/// format workout, leaving an empty line so line numbers stay the same.
+pub fn split_extends(source: &str) -> (Option<String>, String) {
+    let mut lines = source
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty());
+    let extends = lines.nth(1).and_then(|(i, l)| {
+        let file = l.trim().strip_prefix("Extends ")?;
+        Some((i, file.trim().to_owned()))
+    });
+    let Some((at, file)) = extends else {
+        return (None, source.to_owned());
+    };
+
+    let mut rest = String::new();
+    for (i, line) in source.lines().enumerate() {
+        if i != at {
+            rest.push_str(line);
+        }
+        rest.push('\n');
+    }
+    (Some(file), rest)
 }
-impl Display for WorkoutSetElement<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            WorkoutSetElement::Excercise { name, amount, .. } => {
-                write!(f, "[EXCERCISE]: {name} {amount}")
+
+fn load_extending(
+    file: &str,
+    source: &str,
+    flags: &[String],
+    format: FileFormat,
+    depth: usize,
+) -> Result<Workout> {
+    // the tables are standard syntax, without a `Format` line
+    let source = match format {
+        FileFormat::Text | FileFormat::Markdown => split_format(source)?.1,
+        _ => source.to_owned(),
+    };
+    let source = &preprocess::resolve_conditionals(&source, flags)?;
+    // which file the line is in, with files extending each other
+    let in_file = |e: anyhow::Error| anyhow::Error::msg(format!("{file}, {e}"));
+    match format {
+        FileFormat::Markdown => markdown::load_markdown(source).map_err(in_file),
+        FileFormat::Toml => toml_format::load_toml(source),
+        FileFormat::Json => toml_format::load_json(source),
+        FileFormat::Yaml => toml_format::load_yaml(source),
+        FileFormat::Text => {
+            let (extends, source) = split_extends(source);
+            let (includes, source) = split_includes(&source);
+            // the included ones win over the base's, and those over the config's
+            let mut defines = vec![];
+            for include in &includes {
+                defines.extend(read_library(file, include).map_err(in_file)?);
             }
-            WorkoutSetElement::Rest { duration } => write!(f, "[REST]: {duration:?}"),
+            let Some(base) = extends else {
+                return load_with_library(&source, &mut defines).map_err(in_file);
+            };
+            if depth >= 8 {
+                return Err(anyhow::Error::msg(format!(
+                    "{file} extends too deep, do the files extend each other?"
+                )));
+            }
+
+            // relative to the file extending it
+            let base = match Path::new(file).parent() {
+                Some(dir) => dir.join(base).to_string_lossy().into_owned(),
+                None => base,
+            };
+            let base_source = fs::read_to_string(&base)
+                .map_err(|e| anyhow::Error::msg(format!("Couldn't read {base}: {e}")))?;
+            // first, so its `Define`s can be used
+            let base =
+                load_extending(&base, &base_source, flags, FileFormat::of(&base), depth + 1)?;
+            defines.extend(base.defines.iter().cloned());
+            load_with_library(&source, &mut defines)
+                .map_err(in_file)?
+                .extend(base)
         }
     }
 }
 
-pub fn load_workout(source: &str) -> Result<Workout> {
-    fn parse_dur(s: &str) -> Result<Duration> {
-        let (mins, secs) = s[..5].split_at(2);
-        let secs = &secs[1..];
-        Ok(Duration::from_secs(
-            mins.parse::<u64>()? * 60 + secs.parse::<u64>()?,
-        ))
-    }
+/// `base` holds the excercises defined in the file it extends and the
+/// libraries it includes, if any.
+pub fn load_workout(source: &str, base: &[Definition]) -> Result<Workout> {
+    // and the number of each, from 1
+    let (numbers, lines): (Vec<usize>, Vec<&str>) = source
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| (i + 1, l))
+        .unzip();
 
-    let lines: Vec<&str> = source.lines().filter(|l| !l.trim().is_empty()).collect();
+    // errors are about the line `l` was on
+    let mut l = 0;
+    parse_lines(&lines, &mut l, base).map_err(|e| match numbers.get(l) {
+        Some(number) => at_line(*number, lines[l], e),
+        None => anyhow::Error::msg(format!("at the end: {e}")),
+    })
+}
 
-    let Some(workout_name) = lines[0]
-        .trim_start()
-        .strip_prefix("Workout ") else {
-        return Err(anyhow::Error::msg("Didn't provide workout name"));
+fn parse_lines(lines: &[&str], l: &mut usize, base: &[Definition]) -> Result<Workout> {
+    let Some(workout_name) = lines
+        .first()
+        .and_then(|line| line.trim_start().strip_prefix("Workout "))
+    else {
+        return Err(anyhow::Error::msg(format!(
+            "Expected `Workout NAME`, got: {}",
+            lines.first().map_or("nothing", |line| line.trim())
+        )));
     };
+    let (workout_name, lead_in, audio) = split_workout_header(workout_name)?;
+
+    *l = 1;
+    // the file's own come first, so they win over the base's
+    let mut defines = vec![];
+    while *l < lines.len() && lines[*l].trim() == "Define" {
+        *l += 1;
+        parse_define(lines, l, &mut defines, base)?;
+    }
+    defines.extend(base.iter().cloned());
 
-    let mut l = 1;
     let mut sections = vec![];
-    while l < lines.len() {
-        let Some(set) = lines[l]
-            .trim_start()
-            .strip_prefix("Set") else {
-            return Err(anyhow::Error::msg("Expected start of set"));
+    // groups carry over into the next sets
+    let mut group: Option<String> = None;
+    while *l < lines.len() {
+        let Some(set) = lines[*l].trim_start().strip_prefix("Set") else {
+            return Err(unexpected(
+                "an excercise, a rest or a `Set`",
+                lines[*l].trim(),
+            ));
         };
 
-        let get_name_reps = || {
-            let set = set.trim_start();
-            if set.is_empty() {
-                return (None, 1);
-            }
-
-            if let Some((name, reps)) = set.rsplit_once(' ') {
-                if let Some(Ok(r)) = reps.strip_prefix('x').map(|v| v.parse::<u16>()) {
-                    (Some(name), r)
-                } else {
-                    (Some(set), 1)
-                }
-            } else {
-                (Some(set), 1)
-            }
-        };
-        let set_name_reps = get_name_reps();
-        l += 1;
+        let (set, set_lead_in) = split_lead_in(set)?;
+        let set_name_reps = parse_set_header(set);
+        *l += 1;
 
         let mut set_parts = vec![];
-        while l < lines.len() {
-            let line = lines[l].trim_start();
-            let Some((t, rest)) = line.split_once(' ') else {
-                break;
-            };
+        let mut groups: Vec<_> = group.iter().map(|g| (0, g.clone())).collect();
+        while *l < lines.len() {
+            let line = lines[*l].trim();
+            // a bare `Tabata` takes nothing after it
+            let (t, rest) = line.split_once(' ').unwrap_or((line, ""));
             let p = match t {
-                "Excercise" => {
-                    let Some((name, amount)) = rest.rsplit_once(' ') else {
-                        return Err(anyhow::Error::msg("No amount provided for excercise"));
-                    };
-
-                    let amount =
-                        {
-                            if let Some(reps) = amount.strip_prefix('x') {
-                                ExcerciseAmout::Reps(reps.parse().map_err(|_| {
-                                    anyhow::Error::msg("Coudln't parse excercise reps")
-                                })?)
-                            } else {
-                                let midbeep = amount.ends_with('"');
-                                ExcerciseAmout::Time {
-                                    duration: parse_dur(amount).map_err(|_| {
-                                        anyhow::Error::msg("Couldn't parse excercise duration")
-                                    })?,
-                                    midbeep,
-                                }
-                            }
-                        };
-
-                    WorkoutSetElement::Excercise { name, amount }
+                "Group" => {
+                    let label = rest.trim().to_owned();
+                    groups.push((set_parts.len(), label.clone()));
+                    group = Some(label);
+                    *l += 1;
+                    continue;
                 }
-                "Rest" => WorkoutSetElement::Rest {
-                    duration: parse_dur(rest)
-                        .map_err(|_| anyhow::Error::msg("Couldn't parse rest duration"))?,
+                "Circuit" => {
+                    set_parts.push(parse_circuit(rest, lines, l, &defines)?);
+                    continue;
+                }
+                "Rest" if rest.starts_with("after set ") => break,
+                _ => match parse_element(t, rest, &defines)? {
+                    Some(p) => p,
+                    None => break,
                 },
-                _ => break,
             };
             set_parts.push(p);
-            l += 1;
+            *l += 1;
         }
 
-        let set_rest = if l < lines.len() {
-            lines[l]
-                .trim_start()
-                .strip_prefix("Set rest ")
-                .and_then(|r| parse_dur(r).ok())
-        } else {
-            None
-        };
-        if set_rest.is_some() {
-            l += 1;
+        // `Set rest` and `Rest after set`, in any order
+        let (mut set_rest, mut rest_after) = (None, None);
+        while *l < lines.len() {
+            let line = lines[*l].trim_start();
+            if let Some(rest) = line.strip_prefix("Set rest ") {
+                set_rest = Some(parse_duration(rest.trim())?);
+            } else if let Some(rest) = line.strip_prefix("Rest after set ") {
+                rest_after = Some(parse_duration(rest.trim())?);
+            } else {
+                break;
+            }
+            *l += 1;
         }
 
         sections.push(WorkoutSet {
-            name: set_name_reps.0,
+            name: set_name_reps.0.map(str::to_owned),
             reps: set_name_reps.1,
             parts: set_parts,
             set_rest,
+            rest_after,
+            lead_in: set_lead_in,
+            groups,
         });
     }
 
     Ok(Workout {
-        name: workout_name,
+        name: workout_name.to_owned(),
         sections,
+        lead_in,
+        defines,
+        audio,
     })
 }
 
-pub fn do_workout(workout: Workout, from: (u16, u16, u16), beep: impl Fn(BeepLevel)) -> Result<()> {
-    const PRE_SECTION_WAIT: Duration = Duration::from_secs(2);
-    const REST_END_WARNING: Duration = Duration::from_secs(5);
+fn format_element(out: &mut String, p: &WorkoutSetElement, depth: usize) {
+    let indent = "\t".repeat(depth);
+    match p {
+        WorkoutSetElement::Excercise {
+            name,
+            amount,
+            options,
+        } => {
+            out.push_str(&format!(
+                "{indent}Excercise {name} {}{options}\n",
+                amount.text()
+            ));
+        }
+        WorkoutSetElement::Rest { duration } => {
+            out.push_str(&format!("{indent}Rest {}\n", format_duration(*duration)));
+        }
+        WorkoutSetElement::Intervals {
+            name,
+            rounds,
+            work,
+            rest,
+        } => {
+            out.push_str(&format!(
+                "{indent}Intervals {name} {rounds} x ({} work / {} rest)\n",
+                format_duration(*work),
+                format_duration(*rest)
+            ));
+        }
+        WorkoutSetElement::Circuit { reps, parts, rest } => {
+            match rest {
+                Some(rest) => out.push_str(&format!("{indent}Circuit x{reps} {rest}\n")),
+                None => out.push_str(&format!("{indent}Circuit x{reps}\n")),
+            }
+            for p in parts {
+                format_element(out, p, depth + 1);
+            }
+            out.push_str(&format!("{indent}End\n"));
+        }
+    }
+}
+
+pub fn format_workout(workout: &Workout) -> String {
+    let mut out = format!("Format {FORMAT}\nWorkout {}", workout.name);
+    if let Some(lead_in) = workout.lead_in {
+        out.push_str(&format!(" | lead-in {}", format_duration(lead_in)));
+    }
+    if let Some(audio) = &workout.audio {
+        out.push_str(&format!(" | audio {audio}"));
+    }
+    out.push('\n');
 
-    let from = (from.0 as usize, from.1 as usize, from.2 as usize);
+    let mut group = None;
+    for s in &workout.sections {
+        out.push_str("\nSet");
+        if let Some(name) = &s.name {
+            out.push(' ');
+            out.push_str(name);
+        }
+        if s.reps != 1 {
+            out.push_str(&format!(" x{}", s.reps));
+        }
+        if let Some(lead_in) = s.lead_in {
+            out.push_str(&format!(" | lead-in {}", format_duration(lead_in)));
+        }
+        out.push('\n');
 
-    println!("Beginning {workout}");
+        for (pi, p) in s.parts.iter().enumerate() {
+            if let Some(g) = s.group_of(pi).filter(|g| group != Some(*g)) {
+                out.push_str(&format!("\tGroup {g}\n"));
+                group = Some(g);
+            }
+            format_element(&mut out, p, 1);
+        }
 
-    beep(BeepLevel::High);
-    beep(BeepLevel::Mid);
-    beep(BeepLevel::Low);
+        if let Some(rest) = s.set_rest {
+            out.push_str(&format!("Set rest {}\n", format_duration(rest)));
+        }
+        if let Some(rest) = s.rest_after {
+            out.push_str(&format!("Rest after set {}\n", format_duration(rest)));
+        }
+    }
 
-    if from != (0, 0, 0) {
-        let parts = workout.sections[from.0]
-            .parts
-            .iter()
-            .filter(|p: _| matches!(p, WorkoutSetElement::Excercise { .. }))
-            .count();
+    out
+}
 
-        if from.0 > workout.sections.len()
-            || from.1 as u16 > workout.sections[from.0].reps
-            || from.2 > parts
-        {
-            return Err(anyhow::Error::msg("Starting position is out of bounds"));
+// of the step it's paused in, if it's timed
+fn time_left<B: Fn(&Sound)>(session: &Session<B>, now: Instant) -> String {
+    match session.progress(now).remaining {
+        // rounded up, it's not over yet
+        Some(left) => format!(
+            " with {} left",
+            format_duration(Duration::from_secs(left.as_secs_f64().ceil() as u64))
+        ),
+        None => String::new(),
+    }
+}
+
+/// Lets the steps still ahead be dropped, retimed or added to while paused.
+fn edit_remaining<B: Fn(&Sound)>(session: &mut Session<B>, input: &mpsc::Receiver<String>) {
+    use std::io::{stdout, Write};
+
+    loop {
+        let upcoming: Vec<_> = session.upcoming().collect();
+        println!("\n  Still ahead:");
+        for (i, (_, what)) in upcoming.iter().enumerate() {
+            println!("  {:3}. {what}", i + 1);
         }
+        print!("  drop N, time N MM:SS, set (one more round of this one) or enter when done: ");
+        let _ = stdout().flush();
 
-        print!(
-            "Starting from set {}",
-            workout.sections[from.0].name.unwrap_or("[UNKNOWN]")
-        );
-        if from.1 != 0 {
-            print!(" ({} / {})", from.1 + 1, workout.sections[from.0].reps);
+        let Ok(line) = input.recv() else {
+            println!();
+            return;
+        };
+        let mut words = line.split_whitespace();
+        let step = |n: Option<&str>| {
+            n.and_then(|n| n.parse::<usize>().ok())
+                .and_then(|n| upcoming.get(n.wrapping_sub(1)))
+                .map(|(i, _)| *i)
+                .ok_or_else(|| anyhow::Error::msg("Expected the number of a step"))
+        };
+        let result = match words.next() {
+            None | Some("done") => return,
+            Some("drop") => step(words.next()).and_then(|i| session.drop_step(i)),
+            Some("time") => step(words.next()).and_then(|i| {
+                let duration = words
+                    .next()
+                    .map(parse_duration)
+                    .unwrap_or_else(|| Err(anyhow::Error::msg("Expected a duration like 00:45")))?;
+                session.set_duration(i, duration)
+            }),
+            Some("set") => session.add_round(),
+            Some(other) => Err(anyhow::Error::msg(format!("Unknown edit: {other}"))),
+        };
+        match result {
+            Ok(edit) => println!("    {edit}"),
+            Err(e) => println!("    {e}"),
         }
-        println!(" {}. excercise", from.2 + 1);
     }
+}
 
-    let mut first = true;
-    for s in workout.sections.iter().skip(from.0) {
-        println!("\nSection {s}");
+// why it was finished early, for the stats
+fn ask_abort_reason(input: &mpsc::Receiver<String>, unattended: bool) -> Option<AbortReason> {
+    use std::io::{stdout, Write};
 
-        let start = if first {
-            thread::sleep(Duration::from_secs(6));
-            from.1 as u16
-        } else {
-            0
+    let reasons: Vec<_> = AbortReason::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, r)| format!("{} {}", i + 1, r.name()))
+        .collect();
+    print!("Why did you stop? {} (enter to skip) ", reasons.join(", "));
+    let _ = stdout().flush();
+    // nobody might be there to answer
+    let answer = match unattended {
+        true => input.recv_timeout(UNATTENDED_WAIT).ok(),
+        false => input.recv().ok(),
+    };
+    let Some(answer) = answer else {
+        println!();
+        return None;
+    };
+    let reason = AbortReason::parse(&answer);
+    if reason.is_none() && !answer.trim().is_empty() {
+        println!("    Not one of them, left out");
+    }
+    reason
+}
+
+// space or comma separated
+fn ask_for_tags(input: &mpsc::Receiver<String>) -> Vec<String> {
+    use std::io::{stdout, Write};
+
+    print!("Tags for this session, like travel (enter to skip)? ");
+    let _ = stdout().flush();
+    let Ok(answer) = input.recv() else {
+        println!();
+        return vec![];
+    };
+    answer
+        .split([' ', ','])
+        .filter(|t| !t.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+// answers to the `collect:` attributes, once the session is over
+fn collect_answers(steps: &mut [StepRecord], input: &mpsc::Receiver<String>) {
+    use std::io::{stdout, Write};
+
+    let mut asked = false;
+    for step in steps {
+        let StepKind::Excercise { name, amount, .. } = &step.kind else {
+            continue;
         };
-        for section_repetition in start..s.reps {
-            if section_repetition > 0 {
-                println!(
-                    "\nRepeating section ({} / {})",
-                    section_repetition + 1,
-                    s.reps
-                );
+        if step.collect.is_empty() {
+            continue;
+        }
+        if !asked {
+            println!("\nFor your coach:");
+            asked = true;
+        }
+        println!(
+            "  {name} {amount} (set {}, round {})",
+            step.set + 1,
+            step.set_rep + 1
+        );
+
+        for collect in step.collect.clone() {
+            print!(
+                "{}",
+                match collect {
+                    Collect::Rpe => "    RPE (1-10)? ",
+                    Collect::Reps => "    How many reps? ",
+                    Collect::Video => "    Filmed it? [y/N] ",
+                }
+            );
+            let _ = stdout().flush();
+            let Ok(answer) = input.recv() else {
+                println!();
+                continue;
+            };
+            let answer = answer.trim();
+            match collect {
+                Collect::Rpe => step.rpe = answer.parse().ok(),
+                Collect::Reps => step.reps = answer.parse().ok().or(step.reps),
+                Collect::Video => step.video = matches!(answer, "y" | "yes"),
             }
+        }
+    }
+}
 
-            beep(BeepLevel::Mid);
-            beep(BeepLevel::Mid);
+pub fn run_session<B: Fn(&Sound)>(
+    title: &str,
+    name: String,
+    mut session: Session<B>,
+    beep: impl Fn(&Sound),
+    frontends: &[&dyn Frontend],
+) -> Result<SessionRecord> {
+    use std::io::{stdin, stdout, IsTerminal, Write};
 
-            thread::sleep(PRE_SECTION_WAIT);
+    let start_time = Local::now();
 
-            let start = if first {
-                first = false;
+    let (input_tx, input) = mpsc::channel();
+    let unattended = !stdin().is_terminal();
+    let keys = Keys::spawn(input_tx);
+    // what to do for a command
+    let press = |key: &str| match keys.single() {
+        true => format!("press {key}"),
+        false => format!("type {key} and press enter"),
+    };
+    let enter = if keys.single() { "any key" } else { "enter" };
 
-                let mut exes_left = from.2 + 1;
-                s.parts
-                    .iter()
-                    .enumerate()
-                    .find_map(|(i, p)| {
-                        if let WorkoutSetElement::Excercise { .. } = p {
-                            exes_left -= 1;
-                            if exes_left == 0 {
-                                return Some(i);
-                            }
-                        }
+    println!("Beginning {title}");
+    let controls = format!(
+        "f to finish early, {} to pause, n to skip, b to go back one, r to start it over, m to mute",
+        if keys.single() { "p or space" } else { "p" }
+    );
+    match keys.single() {
+        true => println!("(press {controls})"),
+        false => println!("(type {controls} and press enter)"),
+    }
 
-                        None
-                    })
-                    .ok_or(anyhow::Error::msg("Starting position is out of bounds"))?
-            } else {
-                0
-            };
-            for pi in start..s.parts.len() {
-                let p = &s.parts[pi];
-                println!("  {p}");
-
-                use ExcerciseAmout::*;
-                use WorkoutSetElement::*;
-                match &p {
-                    Excercise { amount, .. } => {
-                        beep(BeepLevel::High);
-
-                        match amount {
-                            Time { duration, midbeep } => {
-                                if *midbeep {
-                                    let dur_half = duration.div_f64(2.);
-
-                                    thread::sleep(dur_half);
-                                    println!("    Reached midpoint");
-                                    beep(BeepLevel::Mid);
-                                    thread::sleep(dur_half);
-                                } else {
-                                    thread::sleep(*duration);
-                                }
-
-                                beep(BeepLevel::Low);
-                            }
+    beep(&Sound::Start);
 
-                            Reps(_) => {
-                                use std::io::{stdin, stdout, Write};
+    let mut resume_from = None;
+    let mut resume_offset = None;
+    let mut prompted = false;
+    // log index of a to failure excercise waiting for its rep count
+    let mut asking_reps = None;
+    // nothing's redrawn more often than this, or for a new step
+    let every = session.redraw_every().as_secs();
+    let mut timed = None;
+    let started = Instant::now();
+    // past it, nobody's there to answer anything either
+    let stop_at = session.max_length().map(|max| started + max);
+    let mut stopped = false;
+    // by `f` or the longest a session may run, even past the last excercise
+    let mut finished_early = false;
+    session.start(started);
+    while session.state() != State::Finished {
+        // after every tick and key press, what's changed is up to them
+        if !frontends.is_empty() {
+            let view = View::of(&session, started, Instant::now());
+            for frontend in frontends {
+                frontend.draw(&view);
+            }
+        }
 
-                                print!("    Press enter to continue! ");
-                                stdout().flush()?;
-                                let mut s = String::new();
-                                stdin().read_line(&mut s)?;
-                            }
-                        }
-                    }
+        let awaiting = session.state() == State::AwaitingInput;
+        let stopwatch = awaiting
+            && matches!(
+                session.current_step().map(|s| &s.kind),
+                Some(StepKind::Excercise {
+                    amount: ExcerciseAmout::Failure,
+                    ..
+                })
+            );
+        if awaiting && !prompted {
+            if stopwatch {
+                println!("    Press {enter} when you can't go on!");
+            } else {
+                print!("    Press {enter} to continue! ");
+                stdout().flush()?;
+            }
+            prompted = true;
+        }
 
-                    Rest { duration } => {
-                        if let Some(Excercise { name, .. }) = s.parts.get(pi + 1) {
-                            println!("    next: {name}")
-                        }
+        let mut deadline = session.next_deadline();
+        // the frontends count every second
+        if stopwatch || (!frontends.is_empty() && session.state() != State::Paused) {
+            let elapsed = session.elapsed(Instant::now());
+            let next_tick = Duration::from_secs((elapsed.as_secs() / every + 1) * every) - elapsed;
+            let tick = Instant::now() + next_tick;
+            deadline = Some(deadline.map_or(tick, |d| d.min(tick)));
+        }
+        // nobody might be there to press enter, so it goes on by itself
+        let wait = match session.current_step().map(|s| &s.kind) {
+            Some(StepKind::Excercise { estimate, .. }) if awaiting && unattended => {
+                Some(estimate.unwrap_or(UNATTENDED_WAIT))
+            }
+            _ => None,
+        };
+        if let Some(wait) = wait {
+            let at = Instant::now() + wait.saturating_sub(session.elapsed(Instant::now()));
+            deadline = Some(deadline.map_or(at, |d| d.min(at)));
+        }
+        if let Some(at) = stop_at {
+            deadline = Some(deadline.map_or(at, |d| d.min(at)));
+        }
 
-                        match duration.checked_sub(REST_END_WARNING) {
-                            Some(dur_first) if !dur_first.is_zero() => {
-                                thread::sleep(dur_first);
-                                println!("    {}s left", REST_END_WARNING.as_secs());
-                                beep(BeepLevel::Mid);
-                                thread::sleep(REST_END_WARNING);
-                            }
-                            _ => thread::sleep(*duration),
-                        }
+        // without anyone pressing enter
+        let mut auto_advanced = false;
+        let line = match deadline {
+            Some(at) => {
+                let timeout = at.saturating_duration_since(Instant::now());
+                match input.recv_timeout(timeout) {
+                    Ok(line) => Some(line),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    // no more input behaves like pressing enter
+                    Err(RecvTimeoutError::Disconnected) if awaiting => {
+                        auto_advanced = true;
+                        Some(String::new())
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        thread::sleep(timeout);
+                        None
                     }
                 }
             }
+            None => Some(input.recv().unwrap_or_default()),
+        };
+        // out of the way of whatever's printed next, they're drawn again after
+        for frontend in frontends {
+            frontend.clear();
+        }
+        // left in `running.json`, it's saved as finished early on the next run
+        if line.as_deref() == Some(INTERRUPT) {
+            return Err(Interrupted.into());
+        }
 
-            if section_repetition < s.reps - 1 {
-                if let Some(dur) = s.set_rest {
-                    println!("[REST]: {dur:?}");
+        let now = Instant::now();
+        if stop_at.is_some_and(|at| now >= at) {
+            println!(
+                "\n    Stopped after {}, the longest a session may run",
+                format_duration(now.duration_since(started))
+            );
+            resume_from = session.resume_position();
+            resume_offset = session.resume_offset(now);
+            session.stop(now);
+            stopped = true;
+            finished_early = true;
+            continue;
+        }
+        let line = match (line, wait) {
+            (None, Some(wait)) if session.elapsed(now) >= wait => {
+                println!("\n    No input for {}, going on", format_duration(wait));
+                auto_advanced = true;
+                Some(String::new())
+            }
+            (line, _) => line,
+        };
+        let at = session.elapsed(now).as_secs() / every;
+        if stopwatch && line.is_none() && timed != Some(at) {
+            print!("\r    {} ", format_duration(session.elapsed(now)));
+            stdout().flush()?;
+            timed = Some(at);
+        }
 
-                    let dur = dur.saturating_sub(PRE_SECTION_WAIT);
+        if let (Some(index), Some(line)) = (asking_reps, &line) {
+            asking_reps = None;
+            if let Ok(reps) = line.trim().parse() {
+                session.record_reps(index, reps);
+                continue;
+            }
+        }
 
-                    match dur.checked_sub(REST_END_WARNING) {
-                        Some(dur_first) if !dur_first.is_zero() => {
-                            thread::sleep(dur_first);
-                            println!("  {}s left", REST_END_WARNING.as_secs());
-                            beep(BeepLevel::Mid);
-                            thread::sleep(REST_END_WARNING);
-                        }
-                        _ => thread::sleep(dur),
-                    }
+        // space pauses, it'd be gone after trimming
+        match line.as_deref().map(|l| if l == " " { l } else { l.trim() }) {
+            Some("f" | "finish") => {
+                resume_from = session.resume_position();
+                resume_offset = session.resume_offset(now);
+                session.stop(now);
+                finished_early = true;
+            }
+            Some("p" | "pause" | " ") if session.state() != State::Paused => {
+                session.pause(now);
+                println!(
+                    "    Paused{}: press {enter} to go on, or {} to edit what's left",
+                    time_left(&session, now),
+                    press("e")
+                );
+            }
+            Some("e" | "edit") if session.state() == State::Paused => {
+                keys.set_lines(true);
+                edit_remaining(&mut session, &input);
+                keys.set_lines(false);
+                println!(
+                    "    Paused{}: press {enter} to go on",
+                    time_left(&session, now)
+                );
+            }
+            Some("n" | "next") if session.state() != State::Confirming => {
+                session.skip(now);
+                prompted = false;
+            }
+            Some("m" | "mute") => {
+                let muted = !session.muted();
+                session.mute(muted);
+                match muted {
+                    true => println!("    Muted, {} to turn the sound back on", press("m")),
+                    false => println!("    Unmuted"),
+                }
+            }
+            Some("b" | "back") => {
+                session.back(now);
+                prompted = false;
+            }
+            Some("r" | "restart") => {
+                session.restart(now);
+                prompted = false;
+            }
+            Some(_) if session.state() == State::Paused => {
+                session.resume(now);
+                prompted = false;
+            }
+            Some(_) if session.state() == State::Acknowledging => session.acknowledge(now),
+            Some("n" | "no") if session.state() == State::Confirming => session.decline(now),
+            Some(_) if session.state() == State::Confirming => session.advance(now),
+            Some(line) if session.state() == State::AwaitingInput => {
+                let index = session.log().len();
+                let for_reps = matches!(
+                    session.current_step().map(|s| &s.kind),
+                    Some(StepKind::Excercise {
+                        amount: ExcerciseAmout::Reps(_),
+                        ..
+                    })
+                );
+                if stopwatch {
+                    println!(
+                        "\r    Took {}, how many reps?",
+                        format_duration(session.elapsed(now))
+                    );
+                    asking_reps = Some(index);
+                }
+                session.advance(now);
+                if session.acknowledging() {
+                    session.set_acknowledged(index + 1, !auto_advanced);
                 }
+                // the reps actually done can be typed instead of just enter
+                if let (true, Ok(reps)) = (for_reps, line.parse()) {
+                    session.record_reps(index, reps);
+                    session.adapt_rest(now);
+                }
+                prompted = false;
             }
+            _ => {}
+        }
+        let paused = session.state() == State::Paused;
+        session.tick(now);
+        // it went idle
+        if !paused && session.state() == State::Paused {
+            println!(
+                "    Paused{}: press {enter} to go on, or {} to edit what's left",
+                time_left(&session, now),
+                press("e")
+            );
+            prompted = false;
         }
     }
 
-    println!("Reached the end. Good job!");
+    let view = View {
+        label: "Done".to_owned(),
+        ..View::of(&session, started, Instant::now())
+    };
+    for frontend in frontends {
+        frontend.draw(&view);
+    }
+    if finished_early {
+        println!("\nFinishing early.");
+    } else {
+        println!("Reached the end. Good job!");
+    }
 
     thread::sleep(Duration::from_secs(2));
 
-    beep(BeepLevel::Low);
-    beep(BeepLevel::Mid);
-    beep(BeepLevel::High);
+    beep(&Sound::Complete);
 
     thread::sleep(Duration::from_secs(2));
 
-    Ok(())
+    if let Some(report) = session.timing_report() {
+        print!("{report}");
+    }
+
+    let end = Local::now();
+    let sets = session.sets().to_vec();
+    let edits = session.edits().to_vec();
+    let mut tags = session.tags().to_vec();
+    let asks_for_tags = session.asks_for_tags();
+    let mut steps = session.into_log();
+    keys.set_lines(true);
+    let abort_reason = match finished_early {
+        true => ask_abort_reason(&input, unattended || stopped),
+        false => None,
+    };
+    if !stopped {
+        collect_answers(&mut steps, &input);
+    }
+    // nobody might be there to answer
+    if asks_for_tags && !unattended && !stopped {
+        for tag in ask_for_tags(&input) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+
+    Ok(SessionRecord {
+        workout: name,
+        start: start_time,
+        end,
+        completed: !finished_early,
+        resume_from,
+        resume_offset,
+        abort_reason,
+        program: None,
+        day: None,
+        week: None,
+        tags,
+        sets,
+        steps,
+        edits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> String {
+        match Workout::parse(source) {
+            Ok(_) => panic!("parsed: {source}"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn durations_take_a_colon_or_an_apostrophe() {
+        assert_eq!(parse_duration("00:20").unwrap(), Duration::from_secs(20));
+        assert_eq!(parse_duration("01'30").unwrap(), Duration::from_secs(90));
+        // the midbeep mark is left to the amount
+        assert_eq!(parse_duration("00:30\"").unwrap(), Duration::from_secs(30));
+        assert_eq!(
+            parse_duration("90s").unwrap_err().to_string(),
+            "Expected a duration in MM:SS or MM'SS, got: 90s"
+        );
+    }
+
+    #[test]
+    fn parses_either_separator_in_a_file() {
+        let workout =
+            Workout::parse("Workout T\n\nSet Main\n\tExcercise Push 00'20\"\n\tRest 00:10\n")
+                .unwrap();
+        let parts = &workout.sections[0].parts;
+        assert!(matches!(
+            parts[0],
+            WorkoutSetElement::Excercise {
+                amount: ExcerciseAmout::Time {
+                    duration,
+                    midbeep: true
+                },
+                ..
+            } if duration == Duration::from_secs(20)
+        ));
+        assert!(matches!(
+            parts[1],
+            WorkoutSetElement::Rest { duration } if duration == Duration::from_secs(10)
+        ));
+    }
+
+    #[test]
+    fn errors_say_the_line_and_column() {
+        assert_eq!(
+            error("Workout T\n\nSet Main\n\tExcercise Push 90s\n"),
+            "line 4, column 17: Expected an amount like 00:30, 00'30, x12, 20cal or 500m, got: 90s"
+        );
+        assert_eq!(
+            error("Workout T\nSet Main\n\tExcercise Push x12 | prep 5s\n"),
+            "line 3, column 28: Expected a prep duration in MM:SS or MM'SS, got: 5s"
+        );
+        // blank lines still count
+        assert_eq!(
+            error("Workout T\n\n\nPush x12\n"),
+            "line 4, column 1: Expected an excercise, a rest or a `Set`, got: Push x12"
+        );
+    }
+
+    fn template(line: &str) -> WorkoutSetElement {
+        let workout = Workout::parse(&format!("Workout T\nSet Main\n\t{line}\n")).unwrap();
+        workout.sections[0].parts[0].clone()
+    }
+
+    fn intervals(element: WorkoutSetElement) -> (String, u16, u64, u64) {
+        match element {
+            WorkoutSetElement::Intervals {
+                name,
+                rounds,
+                work,
+                rest,
+            } => (name, rounds, work.as_secs(), rest.as_secs()),
+            _ => panic!("not intervals"),
+        }
+    }
+
+    #[test]
+    fn tabata() {
+        let tabata = |line| intervals(template(line));
+        assert_eq!(
+            tabata("Tabata 8x 00'20 / 00'10"),
+            ("Tabata".into(), 8, 20, 10)
+        );
+        assert_eq!(
+            tabata("Tabata Burpees 4x 00:30 / 00:15"),
+            ("Burpees".into(), 4, 30, 15)
+        );
+        assert_eq!(tabata("Tabata"), ("Tabata".into(), 8, 20, 10));
+    }
+
+    #[test]
+    fn emom() {
+        let emom = |line| intervals(template(line));
+        assert_eq!(emom("EMOM 10 x 01'00"), ("EMOM".into(), 10, 60, 0));
+        assert_eq!(emom("EMOM Cleans 6 x 01:30"), ("Cleans".into(), 6, 90, 0));
+    }
+
+    #[test]
+    fn amrap() {
+        let WorkoutSetElement::Excercise {
+            name,
+            amount: ExcerciseAmout::Time { duration, .. },
+            options,
+        } = template("AMRAP 12'00")
+        else {
+            panic!("not a timed excercise");
+        };
+        assert_eq!(name, "AMRAP");
+        assert_eq!(duration, Duration::from_secs(720));
+        assert_eq!(options.collect.len(), 1);
+    }
+
+    #[test]
+    fn templates_say_their_syntax() {
+        assert_eq!(
+            error("Workout T\nSet Main\n\tEMOM 10 01:00\n"),
+            "line 3, column 2: Expected `EMOM [NAME] MINUTES x INTERVAL`, got: EMOM 10 01:00"
+        );
+    }
+
+    #[test]
+    fn a_tempo_after_the_amount_is_clicked() {
+        let WorkoutSetElement::Excercise {
+            amount, options, ..
+        } = template("Excercise Squats 01'00 @3-1-2")
+        else {
+            panic!("not an excercise");
+        };
+        assert!(
+            matches!(amount, ExcerciseAmout::Time { duration, .. } if duration.as_secs() == 60)
+        );
+        assert_eq!(options.details.tempo.as_deref(), Some("3-1-2"));
+        assert_eq!(
+            options.details.metronome(),
+            Some([3, 1, 2].map(Duration::from_secs).to_vec())
+        );
+        assert_eq!(
+            error("Workout T\nSet Main\n\tExcercise Squats x5 @fast\n"),
+            "line 3, column 22: Expected a tempo like @3-1-2, got: @fast"
+        );
+    }
+
+    #[test]
+    fn errors_about_the_whole_line_have_no_column() {
+        assert_eq!(
+            error("Set Main\n"),
+            "line 1: Expected `Workout NAME`, got: Set Main"
+        );
+    }
+
+    #[test]
+    fn the_text_format_round_trips() {
+        let workout = Workout::parse(include_str!("../workouts/example.txt")).unwrap();
+        let written = format_workout(&workout);
+        assert_eq!(format_workout(&Workout::parse(&written).unwrap()), written);
+    }
 }